@@ -0,0 +1,591 @@
+use crate::quadtree::Point;
+use crate::tiles::{ContentFormat, TileContext};
+use std::convert::TryInto;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComponentType {
+    Byte,
+    UnsignedByte,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+    Float,
+    Double,
+}
+
+// point record fields only exported when asked for, as they add to every tile
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OptionalAttribute {
+    ReturnNumber,
+    NumberOfReturns,
+    ScanAngle,
+    ScanDirection,
+    GpsTime,
+    PointSourceId,
+    UserData,
+}
+
+// a per-point property, stored as little endian values in point order
+#[derive(Clone, Debug)]
+pub struct PointAttribute {
+    pub name: String,
+    pub component_type: ComponentType,
+    pub values: Vec<u8>,
+    pub enum_type: Option<&'static str>,
+    // added to the stored values to get the property values
+    pub offset: Option<f64>,
+    // property value of points without a value
+    pub no_data: Option<f64>,
+    pub description: Option<String>,
+}
+
+pub const CLASSIFICATION_ENUM: &str = "Classification";
+
+// ASPRS standard classes, the remaining codes are reserved or user definable
+pub const CLASSIFICATION_NAMES: [&str; 19] = [
+    "Created, never classified",
+    "Unclassified",
+    "Ground",
+    "Low Vegetation",
+    "Medium Vegetation",
+    "High Vegetation",
+    "Building",
+    "Low Point (noise)",
+    "Model Key-point",
+    "Water",
+    "Rail",
+    "Road Surface",
+    "Overlap Points",
+    "Wire - Guard (Shield)",
+    "Wire - Conductor (Phase)",
+    "Transmission Tower",
+    "Wire-structure Connector (Insulator)",
+    "Bridge Deck",
+    "High Noise",
+];
+
+impl ComponentType {
+    pub fn size(&self) -> usize {
+        match self {
+            ComponentType::Byte | ComponentType::UnsignedByte => 1,
+            ComponentType::Short | ComponentType::UnsignedShort => 2,
+            ComponentType::Int | ComponentType::UnsignedInt | ComponentType::Float => 4,
+            ComponentType::Double => 8,
+        }
+    }
+
+    pub fn batch_table_name(&self) -> &'static str {
+        match self {
+            ComponentType::Byte => "BYTE",
+            ComponentType::UnsignedByte => "UNSIGNED_BYTE",
+            ComponentType::Short => "SHORT",
+            ComponentType::UnsignedShort => "UNSIGNED_SHORT",
+            ComponentType::Int => "INT",
+            ComponentType::UnsignedInt => "UNSIGNED_INT",
+            ComponentType::Float => "FLOAT",
+            ComponentType::Double => "DOUBLE",
+        }
+    }
+
+    pub fn metadata_name(&self) -> &'static str {
+        match self {
+            ComponentType::Byte => "INT8",
+            ComponentType::UnsignedByte => "UINT8",
+            ComponentType::Short => "INT16",
+            ComponentType::UnsignedShort => "UINT16",
+            ComponentType::Int => "INT32",
+            ComponentType::UnsignedInt => "UINT32",
+            ComponentType::Float => "FLOAT32",
+            ComponentType::Double => "FLOAT64",
+        }
+    }
+}
+
+impl OptionalAttribute {
+    fn name(&self) -> &'static str {
+        match self {
+            OptionalAttribute::ReturnNumber => "ReturnNumber",
+            OptionalAttribute::NumberOfReturns => "NumberOfReturns",
+            OptionalAttribute::ScanAngle => "ScanAngle",
+            OptionalAttribute::ScanDirection => "ScanDirectionFlag",
+            OptionalAttribute::GpsTime => "GpsTime",
+            OptionalAttribute::PointSourceId => "PointSourceId",
+            OptionalAttribute::UserData => "UserData",
+        }
+    }
+
+    fn component_type(&self) -> ComponentType {
+        match self {
+            OptionalAttribute::ScanAngle => ComponentType::Float,
+            OptionalAttribute::GpsTime => ComponentType::Double,
+            OptionalAttribute::PointSourceId => ComponentType::UnsignedShort,
+            _ => ComponentType::UnsignedByte,
+        }
+    }
+}
+
+impl PointAttribute {
+    pub fn new<S: Into<String>>(name: S, component_type: ComponentType, capacity: usize) -> Self {
+        Self {
+            name: name.into(),
+            component_type,
+            values: Vec::with_capacity(capacity * component_type.size()),
+            enum_type: None,
+            offset: None,
+            no_data: None,
+            description: None,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.values.len() / self.component_type.size()
+    }
+
+    // the same values as 32 bit floats, wider types lose precision
+    pub fn to_float(&self) -> Self {
+        let size = self.component_type.size();
+
+        let mut attribute = Self {
+            values: Vec::with_capacity(self.count() * 4),
+            component_type: ComponentType::Float,
+            ..self.clone()
+        };
+
+        for value in self.values.chunks_exact(size) {
+            let value = match self.component_type {
+                ComponentType::Byte => value[0] as i8 as f32,
+                ComponentType::UnsignedByte => value[0] as f32,
+                ComponentType::Short => i16::from_le_bytes([value[0], value[1]]) as f32,
+                ComponentType::UnsignedShort => u16::from_le_bytes([value[0], value[1]]) as f32,
+                ComponentType::Int => i32::from_le_bytes(value.try_into().unwrap()) as f32,
+                ComponentType::UnsignedInt => u32::from_le_bytes(value.try_into().unwrap()) as f32,
+                ComponentType::Float => f32::from_le_bytes(value.try_into().unwrap()),
+                ComponentType::Double => f64::from_le_bytes(value.try_into().unwrap()) as f32,
+            };
+
+            attribute.values.extend_from_slice(&value.to_le_bytes());
+        }
+
+        attribute
+    }
+}
+
+pub fn classification_name(code: u8) -> String {
+    match CLASSIFICATION_NAMES.get(code as usize) {
+        Some(name) => name.to_string(),
+        None if code < 64 => format!("Reserved {}", code),
+        None => format!("User Definable {}", code),
+    }
+}
+
+pub fn point_attributes(points: &[Point], context: &TileContext) -> Vec<PointAttribute> {
+    let options = context.options;
+
+    let mut classification =
+        PointAttribute::new("Classification", ComponentType::UnsignedByte, points.len());
+    classification.enum_type = Some(CLASSIFICATION_ENUM);
+
+    let mut intensity = PointAttribute::new(
+        "Intensity",
+        if options.normalize_intensity {
+            ComponentType::UnsignedByte
+        } else {
+            ComponentType::UnsignedShort
+        },
+        points.len(),
+    );
+
+    let mut key_point = PointAttribute::new("KeyPoint", ComponentType::UnsignedByte, points.len());
+
+    let mut edge_of_flight_line = PointAttribute::new(
+        "EdgeOfFlightLine",
+        ComponentType::UnsignedByte,
+        points.len(),
+    );
+
+    let mut overlap = PointAttribute::new("Overlap", ComponentType::UnsignedByte, points.len());
+
+    let mut withheld = PointAttribute::new("Withheld", ComponentType::UnsignedByte, points.len());
+
+    let mut synthetic = PointAttribute::new("Synthetic", ComponentType::UnsignedByte, points.len());
+
+    for point in points {
+        classification.values.push(point.classification);
+
+        // LAS intensities are normalized to 16 bits, 257 maps 65535 to 255
+        if options.normalize_intensity {
+            intensity
+                .values
+                .push(((point.intensity as u32 + 128) / 257) as u8);
+        } else {
+            intensity
+                .values
+                .extend_from_slice(&point.intensity.to_le_bytes());
+        }
+
+        key_point.values.push(point.is_key_point as u8);
+        edge_of_flight_line
+            .values
+            .push(point.is_edge_of_flight_line as u8);
+        overlap.values.push(point.is_overlap as u8);
+        withheld.values.push(point.is_withheld as u8);
+        synthetic.values.push(point.is_synthetic as u8);
+    }
+
+    let mut attributes = vec![
+        classification,
+        intensity,
+        key_point,
+        edge_of_flight_line,
+        overlap,
+        withheld,
+        synthetic,
+    ];
+
+    for optional_attribute in &options.attributes {
+        attributes.push(optional_point_attribute(
+            *optional_attribute,
+            points,
+            options.content_format,
+        ));
+    }
+
+    attributes.extend(
+        context
+            .extra_bytes
+            .attributes(points, options.content_format),
+    );
+
+    attributes
+}
+
+fn optional_point_attribute(
+    optional_attribute: OptionalAttribute,
+    points: &[Point],
+    content_format: ContentFormat,
+) -> PointAttribute {
+    if optional_attribute == OptionalAttribute::GpsTime && content_format == ContentFormat::Glb {
+        return gps_time_offsets(points);
+    }
+
+    let mut attribute = PointAttribute::new(
+        optional_attribute.name(),
+        optional_attribute.component_type(),
+        points.len(),
+    );
+
+    for point in points {
+        match optional_attribute {
+            OptionalAttribute::ReturnNumber => attribute.values.push(point.return_number),
+            OptionalAttribute::NumberOfReturns => attribute.values.push(point.number_of_returns),
+            // degrees, negative to the left of nadir
+            OptionalAttribute::ScanAngle => attribute
+                .values
+                .extend_from_slice(&point.scan_angle.to_le_bytes()),
+            OptionalAttribute::ScanDirection => {
+                attribute.values.push(point.scan_direction_flag as u8)
+            }
+            OptionalAttribute::GpsTime => attribute
+                .values
+                .extend_from_slice(&point.gps_time.to_le_bytes()),
+            OptionalAttribute::PointSourceId => attribute
+                .values
+                .extend_from_slice(&point.point_source_id.to_le_bytes()),
+            OptionalAttribute::UserData => attribute.values.push(point.user_data),
+        }
+    }
+
+    attribute
+}
+
+// glTF vertex attributes cannot be doubles, so glb content stores seconds since the earliest point
+// of the tile, which is the offset of the property
+fn gps_time_offsets(points: &[Point]) -> PointAttribute {
+    let mut attribute = PointAttribute::new(
+        OptionalAttribute::GpsTime.name(),
+        ComponentType::Float,
+        points.len(),
+    );
+
+    let epoch = match gps_time_range(points) {
+        Some((minimum, _)) => minimum,
+        None => 0.0,
+    };
+
+    attribute.offset = Some(epoch);
+
+    for point in points {
+        attribute
+            .values
+            .extend_from_slice(&((point.gps_time - epoch) as f32).to_le_bytes());
+    }
+
+    attribute
+}
+
+pub fn gps_time_range(points: &[Point]) -> Option<(f64, f64)> {
+    points.iter().fold(None, |range, point| match range {
+        None => Some((point.gps_time, point.gps_time)),
+        Some((minimum, maximum)) => Some((
+            f64::min(minimum, point.gps_time),
+            f64::max(maximum, point.gps_time),
+        )),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extra_bytes::ExtraBytesSchema;
+    use crate::frame::LocalFrame;
+    use crate::job::TilingOptions;
+    use crate::sink::DirectorySink;
+
+    fn attributes(points: &[Point], options: TilingOptions) -> Vec<PointAttribute> {
+        let sink = DirectorySink::new("unused");
+        let frame = LocalFrame::new(6378137.0, 0.0, 0.0);
+        let extra_bytes = ExtraBytesSchema::default();
+        let context = TileContext {
+            sink: &sink,
+            frame: &frame,
+            options: &options,
+            extra_bytes: &extra_bytes,
+            transform: None,
+        };
+
+        point_attributes(points, &context)
+    }
+
+    fn named<'a>(attributes: &'a [PointAttribute], name: &str) -> &'a PointAttribute {
+        attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn normalized_intensity() {
+        let intensities = [0, 128, 129, 257, 385, 386, 32_767, 65_406, 65_407, 65_535];
+
+        let points = intensities
+            .iter()
+            .map(|&intensity| Point {
+                intensity,
+                ..Point::at(0.0, 0.0, 0.0)
+            })
+            .collect::<Vec<_>>();
+
+        let normalized = attributes(
+            &points,
+            TilingOptions {
+                normalize_intensity: true,
+                ..TilingOptions::default()
+            },
+        );
+        let intensity = named(&normalized, "Intensity");
+
+        // the nearest 8 bit value, 257 apart
+        assert_eq!(intensity.component_type, ComponentType::UnsignedByte);
+        assert_eq!(intensity.values, [0, 0, 1, 1, 1, 2, 127, 254, 255, 255]);
+
+        let raw = attributes(&points, TilingOptions::default());
+        let intensity = named(&raw, "Intensity");
+
+        assert_eq!(intensity.component_type, ComponentType::UnsignedShort);
+        assert_eq!(
+            intensity.values,
+            intensities
+                .iter()
+                .flat_map(|intensity: &u16| intensity.to_le_bytes())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn return_and_scan_attributes() {
+        let points = [
+            Point {
+                return_number: 1,
+                number_of_returns: 3,
+                scan_angle: -12.5,
+                scan_direction_flag: true,
+                ..Point::at(0.0, 0.0, 0.0)
+            },
+            Point {
+                return_number: 3,
+                number_of_returns: 3,
+                scan_angle: 30.0,
+                ..Point::at(0.0, 0.0, 0.0)
+            },
+        ];
+
+        let attributes = attributes(
+            &points,
+            TilingOptions {
+                attributes: vec![
+                    OptionalAttribute::ReturnNumber,
+                    OptionalAttribute::NumberOfReturns,
+                    OptionalAttribute::ScanAngle,
+                    OptionalAttribute::ScanDirection,
+                ],
+                ..TilingOptions::default()
+            },
+        );
+
+        assert_eq!(named(&attributes, "ReturnNumber").values, [1, 3]);
+        assert_eq!(named(&attributes, "NumberOfReturns").values, [3, 3]);
+        assert_eq!(named(&attributes, "ScanDirectionFlag").values, [1, 0]);
+
+        let scan_angle = named(&attributes, "ScanAngle");
+        assert_eq!(scan_angle.component_type, ComponentType::Float);
+        assert_eq!(
+            scan_angle.values,
+            [(-12.5f32).to_le_bytes(), 30.0f32.to_le_bytes()].concat()
+        );
+    }
+
+    #[test]
+    fn gps_time_offsets_in_glb() {
+        // adjusted standard GPS times, whose fractions a 32 bit float of the full time would lose
+        let times = [1_300_000_010.25, 1_300_000_000.125, 1_300_000_123.5];
+
+        let points = times
+            .iter()
+            .map(|&gps_time| Point {
+                gps_time,
+                ..Point::at(0.0, 0.0, 0.0)
+            })
+            .collect::<Vec<_>>();
+
+        let options = |content_format| TilingOptions {
+            content_format,
+            attributes: vec![OptionalAttribute::GpsTime],
+            ..TilingOptions::default()
+        };
+
+        let glb = attributes(&points, options(ContentFormat::Glb));
+        let gps_time = named(&glb, "GpsTime");
+
+        assert_eq!(gps_time.component_type, ComponentType::Float);
+        assert_eq!(gps_time.offset, Some(1_300_000_000.125));
+
+        let decoded = gps_time
+            .values
+            .chunks_exact(4)
+            .map(|value| {
+                gps_time.offset.unwrap() + f32::from_le_bytes(value.try_into().unwrap()) as f64
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(decoded, times);
+
+        let pnts = attributes(&points, options(ContentFormat::Pnts));
+        let gps_time = named(&pnts, "GpsTime");
+
+        assert_eq!(gps_time.component_type, ComponentType::Double);
+        assert_eq!(gps_time.offset, None);
+        assert_eq!(
+            gps_time.values,
+            times
+                .iter()
+                .flat_map(|time: &f64| time.to_le_bytes())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn point_source_and_user_data() {
+        let points = [
+            Point {
+                point_source_id: 513,
+                user_data: 7,
+                ..Point::at(0.0, 0.0, 0.0)
+            },
+            Point {
+                point_source_id: u16::MAX,
+                user_data: 255,
+                ..Point::at(0.0, 0.0, 0.0)
+            },
+        ];
+
+        let selected = attributes(
+            &points,
+            TilingOptions {
+                attributes: vec![
+                    OptionalAttribute::PointSourceId,
+                    OptionalAttribute::UserData,
+                ],
+                ..TilingOptions::default()
+            },
+        );
+
+        let point_source_id = named(&selected, "PointSourceId");
+        assert_eq!(point_source_id.component_type, ComponentType::UnsignedShort);
+        assert_eq!(point_source_id.values, [1, 2, 255, 255]);
+        assert_eq!(named(&selected, "UserData").values, [7, 255]);
+
+        // only the selected ones are exported
+        let defaults = attributes(&points, TilingOptions::default());
+        assert!(defaults
+            .iter()
+            .all(|attribute| attribute.name != "PointSourceId" && attribute.name != "UserData"));
+    }
+
+    #[test]
+    fn float_values_of_every_component_type() {
+        let cases: [(ComponentType, Vec<u8>, [f32; 2]); 8] = [
+            (ComponentType::Byte, vec![0x80, 0x7f], [-128.0, 127.0]),
+            (ComponentType::UnsignedByte, vec![0, 0xff], [0.0, 255.0]),
+            (
+                ComponentType::Short,
+                [i16::MIN.to_le_bytes(), 300i16.to_le_bytes()].concat(),
+                [-32_768.0, 300.0],
+            ),
+            (
+                ComponentType::UnsignedShort,
+                [0u16.to_le_bytes(), u16::MAX.to_le_bytes()].concat(),
+                [0.0, 65_535.0],
+            ),
+            (
+                ComponentType::Int,
+                [(-16_777_216i32).to_le_bytes(), 16_777_217i32.to_le_bytes()].concat(),
+                // beyond 24 bits, the nearest float
+                [-16_777_216.0, 16_777_216.0],
+            ),
+            (
+                ComponentType::UnsignedInt,
+                [7u32.to_le_bytes(), u32::MAX.to_le_bytes()].concat(),
+                [7.0, 4_294_967_296.0],
+            ),
+            (
+                ComponentType::Float,
+                [(-0.1f32).to_le_bytes(), f32::MAX.to_le_bytes()].concat(),
+                [-0.1, f32::MAX],
+            ),
+            (
+                ComponentType::Double,
+                [0.1f64.to_le_bytes(), (-1e300f64).to_le_bytes()].concat(),
+                [0.1, f32::NEG_INFINITY],
+            ),
+        ];
+
+        for (component_type, values, expected) in cases {
+            let mut attribute = PointAttribute::new("Value", component_type, 2);
+            attribute.values = values;
+            attribute.offset = Some(5.0);
+
+            let float = attribute.to_float();
+
+            assert_eq!(float.component_type, ComponentType::Float);
+            assert_eq!(float.count(), 2);
+            assert_eq!(float.offset, Some(5.0));
+            assert_eq!(
+                float.values,
+                [expected[0].to_le_bytes(), expected[1].to_le_bytes()].concat(),
+                "{:?}",
+                component_type
+            );
+        }
+    }
+}
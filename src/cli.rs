@@ -0,0 +1,341 @@
+use clap::{ArgAction, Parser, ValueEnum};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[clap(
+    name = "cesium_3d_tiles_generator",
+    version,
+    about = "Generates Cesium 3D Tiles point cloud tilesets from LAS/LAZ files"
+)]
+pub struct Args {
+    /// LAS/LAZ files, or directories containing them, to tile
+    #[clap(value_name = "INPUT", required_unless_present = "input-list")]
+    pub inputs: Vec<PathBuf>,
+
+    /// File listing inputs one per line, relative paths are relative to the file
+    #[clap(long, value_name = "FILE")]
+    pub input_list: Vec<PathBuf>,
+
+    /// Search input directories recursively
+    #[clap(short, long)]
+    pub recursive: bool,
+
+    /// Only tile files found in input directories whose path matches this glob, can be repeated
+    #[clap(long, value_name = "GLOB")]
+    pub include: Vec<String>,
+
+    /// Skip files found in input directories whose path matches this glob, can be repeated
+    #[clap(long, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// Directory the tileset is written to
+    #[clap(short, long, value_name = "DIR")]
+    pub output: PathBuf,
+
+    /// Maximum number of points stored in a single tile
+    #[clap(short, long, default_value_t = 100000, value_parser = parse_positive)]
+    pub capacity: usize,
+
+    /// What to do when the output directory already contains files
+    #[clap(long, value_enum, default_value_t = OverwritePolicy::Fail)]
+    pub overwrite: OverwritePolicy,
+
+    /// What to do when a LAS/LAZ file cannot be processed
+    #[clap(long, value_enum, default_value_t = OnError::Abort)]
+    pub on_error: OnError,
+
+    /// CRS of the input points, overriding the LAS headers: EPSG:<code>, a WKT string or a WKT file
+    #[clap(long, value_name = "CRS")]
+    pub source_crs: Option<String>,
+
+    /// Geoid grid (GTX or PGM) the input heights are orthometric to, converted to ellipsoidal heights
+    #[clap(long, value_name = "FILE")]
+    pub geoid: Option<PathBuf>,
+
+    /// Bounding volume written for every tile
+    #[clap(long, value_enum, default_value_t = BoundingVolume::Box)]
+    pub bounding_volume: BoundingVolume,
+
+    /// Spatial subdivision, octree suits terrestrial and mobile scans with a lot of vertical extent
+    #[clap(long, value_enum, default_value_t = Subdivision::Quadtree)]
+    pub subdivision: Subdivision,
+
+    /// Tile content format, glb writes 3D Tiles 1.1 glTF point clouds
+    #[clap(long, value_enum, default_value_t = Format::Pnts)]
+    pub format: Format,
+
+    /// Per-point color encoding of pnts content, tiles without input colors get one constant color
+    #[clap(long, value_enum, default_value_t = Colors::Rgb)]
+    pub colors: Colors,
+
+    /// Source of the alpha channel of rgba colors, scaled to 8 bits
+    #[clap(long, value_enum, default_value_t = Alpha::Intensity)]
+    pub alpha: Alpha,
+
+    /// Write one implicit tiling tile set with subtree files per input instead of nested tile sets,
+    /// needs box bounding volumes
+    #[clap(long)]
+    pub implicit_tiling: bool,
+
+    /// Print the tiling plan read from the LAS headers and exit without writing any tiles
+    #[clap(long)]
+    pub dry_run: bool,
+
+    /// Export intensity as 8 bits scaled down from the 16 bit LAS range instead of 16 bits
+    #[clap(long)]
+    pub normalize_intensity: bool,
+
+    /// Optional point attributes to export besides classification, flags and intensity, comma separated
+    #[clap(long, value_enum, value_delimiter = ',', value_name = "ATTRIBUTES")]
+    pub attributes: Vec<Attribute>,
+
+    /// Export the extra dimensions described in the Extra Bytes VLR of the inputs
+    #[clap(long)]
+    pub extra_bytes: bool,
+
+    /// Write pnts positions as 16 bit integers where the quantization error stays below this many meters (e.g. 0.001)
+    #[clap(long, value_name = "TOLERANCE")]
+    pub quantize_positions: Option<f64>,
+
+    /// Draco compress positions, colors and attributes, for pnts and glb content
+    #[clap(long)]
+    pub draco: bool,
+
+    /// Quantization bits of Draco compressed positions
+    #[clap(long, value_name = "BITS", default_value_t = 14, requires = "draco", value_parser = clap::value_parser!(u8).range(1..=30))]
+    pub draco_position_bits: u8,
+
+    /// Quantization bits of Draco compressed floating point attributes, integers stay lossless
+    #[clap(long, value_name = "BITS", default_value_t = 12, requires = "draco", value_parser = clap::value_parser!(u8).range(1..=30))]
+    pub draco_generic_bits: u8,
+
+    /// Meshopt compress glb vertex attributes, faster to decode than Draco
+    #[clap(long)]
+    pub meshopt: bool,
+
+    /// Quantize meshopt compressed glb positions to this many bits, stored as 16 bit integers (KHR_mesh_quantization)
+    #[clap(long, value_name = "BITS", requires = "meshopt", value_parser = clap::value_parser!(u8).range(1..=16))]
+    pub meshopt_position_bits: Option<u8>,
+
+    /// Tile all inputs as one point cloud instead of one tile set per file
+    #[clap(long)]
+    pub unified: bool,
+
+    /// Memory the tiling may use, shared by the worker threads, larger files are sorted on disk (e.g. 512M, 16G)
+    #[clap(long, value_name = "SIZE", value_parser = parse_size)]
+    pub memory_budget: Option<usize>,
+
+    /// Directory for the points spilled to disk, defaults to the system temporary directory
+    #[clap(long, value_name = "DIR")]
+    pub temp_dir: Option<PathBuf>,
+
+    /// Number of worker threads, defaults to the number of logical CPUs
+    #[clap(short = 'j', long, value_parser = parse_positive)]
+    pub threads: Option<usize>,
+
+    /// Print more progress information, repeat for even more
+    #[clap(short, long, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Only print errors
+    #[clap(short, long, conflicts_with = "verbose")]
+    pub quiet: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Refuse to write into a non-empty output directory
+    Fail,
+    /// Write into the output directory, replacing files with the same name
+    Overwrite,
+    /// Remove the output directory before writing
+    Clean,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnError {
+    /// Stop at the first file that fails
+    Abort,
+    /// Skip failed files and report them at the end
+    Skip,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoundingVolume {
+    /// Box oriented to the local east-north-up frame
+    Box,
+    /// Geographic region in radians with minimum and maximum heights
+    Region,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Subdivision {
+    /// Split tiles in four, horizontally only
+    Quadtree,
+    /// Split tiles in eight, horizontally and vertically
+    Octree,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// 3D Tiles 1.0 point cloud tiles
+    Pnts,
+    /// 3D Tiles 1.1 binary glTF with a point primitive
+    Glb,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Colors {
+    /// 8 bits per channel
+    Rgb,
+    /// 8 bits per channel and an alpha channel from --alpha
+    Rgba,
+    /// 16 bits per point, 5 bits for red and blue and 6 for green
+    Rgb565,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Alpha {
+    /// Intensity, scaled down from 16 bits
+    Intensity,
+    /// User data byte
+    UserData,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Attribute {
+    /// Return number of the pulse
+    ReturnNumber,
+    /// Number of returns of the pulse
+    NumberOfReturns,
+    /// Scan angle in degrees
+    ScanAngle,
+    /// Scan direction flag, set when the mirror moves left to right
+    ScanDirection,
+    /// GPS time, in glb content as seconds since the earliest point of the tile; inputs whose point format lacks it fail
+    GpsTime,
+    /// Point source id, the flight line or scanner the point comes from
+    PointSourceId,
+    /// User data byte
+    UserData,
+}
+
+impl Args {
+    pub fn log_level(&self) -> log::LevelFilter {
+        if self.quiet {
+            return log::LevelFilter::Error;
+        }
+
+        match self.verbose {
+            0 => log::LevelFilter::Info,
+            1 => log::LevelFilter::Debug,
+            _ => log::LevelFilter::Trace,
+        }
+    }
+}
+
+fn parse_positive(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(0) => Err("must be greater than zero".to_string()),
+        Ok(value) => Ok(value),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// a number of bytes with an optional binary K, M, G or T suffix
+fn parse_size(value: &str) -> Result<usize, String> {
+    let value = value.trim();
+
+    let (number, multiplier) = match value.char_indices().last() {
+        Some((index, suffix)) if suffix.is_ascii_alphabetic() => {
+            let multiplier = match suffix.to_ascii_uppercase() {
+                'K' => 1 << 10,
+                'M' => 1 << 20,
+                'G' => 1 << 30,
+                'T' => 1 << 40,
+                _ => return Err(format!("unknown size suffix {:?}", suffix)),
+            };
+
+            (&value[..index], multiplier)
+        }
+        _ => (value, 1),
+    };
+
+    match number.trim().parse::<usize>() {
+        Ok(0) => Err("must be greater than zero".to_string()),
+        Ok(number) => number
+            .checked_mul(multiplier)
+            .ok_or_else(|| "size is too large".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    fn parse(args: &[&str]) -> Result<Args, clap::Error> {
+        Args::try_parse_from(
+            ["cesium_3d_tiles_generator", "--output", "out", "input.las"]
+                .iter()
+                .chain(args),
+        )
+    }
+
+    #[test]
+    fn arguments() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn compression_bits_require_their_compression() {
+        for bits in ["--draco-position-bits", "--draco-generic-bits"] {
+            assert!(parse(&[bits, "10"]).is_err());
+
+            let args = parse(&["--draco", bits, "10"]).unwrap();
+            assert!(args.draco);
+        }
+
+        assert!(parse(&["--meshopt-position-bits", "12"]).is_err());
+        assert!(parse(&["--meshopt", "--meshopt-position-bits", "12"]).is_ok());
+
+        // the defaults do not ask for Draco
+        let args = parse(&[]).unwrap();
+        assert_eq!(
+            (args.draco_position_bits, args.draco_generic_bits),
+            (14, 12)
+        );
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("1024"), Ok(1024));
+        assert_eq!(parse_size("64K"), Ok(64 << 10));
+        assert_eq!(parse_size("512M"), Ok(512 << 20));
+        assert_eq!(parse_size("16G"), Ok(16 << 30));
+        assert_eq!(parse_size("2T"), Ok(2 << 40));
+
+        // suffixes in either case, blanks around the number and the suffix
+        assert_eq!(parse_size("512m"), parse_size("512M"));
+        assert_eq!(parse_size("16g"), Ok(16 << 30));
+        assert_eq!(parse_size(" 2 k "), Ok(2 << 10));
+    }
+
+    #[test]
+    fn invalid_sizes() {
+        for value in ["", " ", "M", "0", "0G", "-1M", "1.5G", "12X", "G16", "16GB"] {
+            assert!(parse_size(value).is_err(), "{:?}", value);
+        }
+
+        assert_eq!(
+            parse_size("12X"),
+            Err("unknown size suffix 'X'".to_string())
+        );
+        assert_eq!(
+            parse_size(&format!("{}T", usize::MAX >> 39)),
+            Err("size is too large".to_string())
+        );
+        assert!(parse_size(&format!("{}0", usize::MAX)).is_err());
+    }
+}
@@ -0,0 +1,88 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ellipsoid {
+    pub a: f64,
+    pub inverse_flattening: f64,
+}
+
+pub const WGS84: Ellipsoid = Ellipsoid {
+    a: 6378137.0,
+    inverse_flattening: 298.257223563,
+};
+
+pub const GRS80: Ellipsoid = Ellipsoid {
+    a: 6378137.0,
+    inverse_flattening: 298.257222101,
+};
+
+pub const INTERNATIONAL_1924: Ellipsoid = Ellipsoid {
+    a: 6378388.0,
+    inverse_flattening: 297.0,
+};
+
+pub const BESSEL_1841: Ellipsoid = Ellipsoid {
+    a: 6377397.155,
+    inverse_flattening: 299.1528128,
+};
+
+pub const CLARKE_1866: Ellipsoid = Ellipsoid {
+    a: 6378206.4,
+    inverse_flattening: 294.978698214,
+};
+
+pub const AIRY_1830: Ellipsoid = Ellipsoid {
+    a: 6377563.396,
+    inverse_flattening: 299.3249646,
+};
+
+impl Ellipsoid {
+    pub fn flattening(&self) -> f64 {
+        if self.inverse_flattening == 0.0 {
+            0.0
+        } else {
+            1.0 / self.inverse_flattening
+        }
+    }
+
+    pub fn eccentricity_squared(&self) -> f64 {
+        let f = self.flattening();
+
+        f * (2.0 - f)
+    }
+
+    pub fn eccentricity(&self) -> f64 {
+        self.eccentricity_squared().sqrt()
+    }
+
+    pub fn geodetic_to_geocentric(&self, lat: f64, lon: f64, h: f64) -> (f64, f64, f64) {
+        let lat_rad = lat.to_radians();
+        let lon_rad = lon.to_radians();
+        let t: f64 = 1.0 - self.flattening();
+        let nn = self.a / (1.0 - (1.0 - t.powf(2.0)) * lat_rad.sin().powf(2.0)).sqrt();
+        let x = (nn + h) * lat_rad.cos() * lon_rad.cos();
+        let y = (nn + h) * lat_rad.cos() * lon_rad.sin();
+        let z = (t.powf(2.0) * nn + h) * lat_rad.sin();
+
+        (x, y, z)
+    }
+
+    // Bowring's method, accurate to well below a millimetre for points near the surface
+    pub fn geocentric_to_geodetic(&self, x: f64, y: f64, z: f64) -> (f64, f64, f64) {
+        let e2 = self.eccentricity_squared();
+        let b = self.a * (1.0 - self.flattening());
+        let ep2 = (self.a * self.a - b * b) / (b * b);
+        let p = (x * x + y * y).sqrt();
+
+        let theta = (z * self.a).atan2(p * b);
+        let lat = (z + ep2 * b * theta.sin().powi(3)).atan2(p - e2 * self.a * theta.cos().powi(3));
+        let lon = y.atan2(x);
+
+        let n = self.a / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+        let h = if lat.cos().abs() > 1e-10 {
+            p / lat.cos() - n
+        } else {
+            z.abs() - b
+        };
+
+        (lat.to_degrees(), lon.to_degrees(), h)
+    }
+}
@@ -0,0 +1,341 @@
+use crate::crs::ellipsoid::{
+    Ellipsoid, AIRY_1830, BESSEL_1841, CLARKE_1866, GRS80, INTERNATIONAL_1924, WGS84,
+};
+use crate::crs::projection::Projection;
+use crate::crs::{Crs, CrsKind, Datum, Helmert, METRE};
+
+pub fn crs(code: u32) -> Option<Crs> {
+    geographic(code)
+        .or_else(|| projected(code))
+        .or_else(|| geocentric(code))
+}
+
+pub fn datum(code: u32) -> Option<(&'static str, Datum)> {
+    let (name, ellipsoid, to_wgs84) = match code {
+        6326 => ("WGS 84", WGS84, Helmert::default()),
+        6258 => ("ETRS89", GRS80, Helmert::default()),
+        6269 => ("NAD83", GRS80, Helmert::default()),
+        1116 => ("NAD83 (2011)", GRS80, Helmert::default()),
+        6171 => ("RGF93", GRS80, Helmert::default()),
+        6283 => ("GDA94", GRS80, Helmert::default()),
+        1168 => ("GDA2020", GRS80, Helmert::default()),
+        6167 => ("NZGD2000", GRS80, Helmert::default()),
+        6765 => ("Slovenia 1996", GRS80, Helmert::default()),
+        6761 => ("HTRS96", GRS80, Helmert::default()),
+        6267 => (
+            "NAD27",
+            CLARKE_1866,
+            Helmert::translation(-8.0, 160.0, 176.0),
+        ),
+        6230 => (
+            "ED50",
+            INTERNATIONAL_1924,
+            Helmert::translation(-87.0, -98.0, -121.0),
+        ),
+        6277 => (
+            "OSGB 1936",
+            AIRY_1830,
+            Helmert {
+                tx: 446.448,
+                ty: -125.157,
+                tz: 542.06,
+                rx: 0.15,
+                ry: 0.247,
+                rz: 0.842,
+                ds: -20.489,
+            },
+        ),
+        6314 => (
+            "DHDN",
+            BESSEL_1841,
+            Helmert {
+                tx: 598.1,
+                ty: 73.7,
+                tz: 418.2,
+                rx: 0.202,
+                ry: 0.045,
+                rz: -2.455,
+                ds: 6.7,
+            },
+        ),
+        6312 => (
+            "MGI",
+            BESSEL_1841,
+            Helmert {
+                tx: 577.326,
+                ty: 90.129,
+                tz: 463.919,
+                rx: 5.137,
+                ry: 1.474,
+                rz: 5.297,
+                ds: 2.4232,
+            },
+        ),
+        1031 => (
+            "MGI 1901",
+            BESSEL_1841,
+            Helmert::translation(682.0, -203.0, 480.0),
+        ),
+        6289 => (
+            "Amersfoort",
+            BESSEL_1841,
+            Helmert {
+                tx: 565.417,
+                ty: 50.3319,
+                tz: 465.552,
+                rx: -0.398957,
+                ry: 0.343988,
+                rz: -1.8774,
+                ds: 4.0725,
+            },
+        ),
+        _ => return None,
+    };
+
+    Some((name, Datum::new(ellipsoid, to_wgs84)))
+}
+
+pub fn datum_by_name(name: &str) -> Option<Datum> {
+    let name = normalize(name);
+
+    // ESRI WKT prefixes datum names with "D_"
+    datum_code_by_name(&name)
+        .or_else(|| name.strip_prefix('d').and_then(datum_code_by_name))
+        .and_then(datum)
+        .map(|(_, datum)| datum)
+}
+
+fn datum_code_by_name(name: &str) -> Option<u32> {
+    let code = match name {
+        "wgs1984" | "wgs84" | "worldgeodeticsystem1984" | "worldgeodeticsystem1984ensemble" => 6326,
+        "europeanterrestrialreferencesystem1989"
+        | "etrs1989"
+        | "etrs89"
+        | "europeanterrestrialreferencesystem1989ensemble" => 6258,
+        "northamericandatum1983" | "northamerican1983" | "nad83" => 6269,
+        "nad832011" | "northamerican19832011" | "nad83nationalspatialreferencesystem2011" => 1116,
+        "reseaugeodesiquefrancais1993" | "rgf1993" | "rgf93" => 6171,
+        "geocentricdatumofaustralia1994" | "gda1994" | "gda94" => 6283,
+        "geocentricdatumofaustralia2020" | "gda2020" => 1168,
+        "newzealandgeodeticdatum2000" | "nzgd2000" => 6167,
+        "slovenia1996" | "slovenia96" => 6765,
+        "croatianterrestrialreferencesystem" | "htrs96" => 6761,
+        "northamericandatum1927" | "northamerican1927" | "nad27" => 6267,
+        "europeandatum1950" | "european1950" | "ed50" => 6230,
+        "osgb1936" | "osgb36" => 6277,
+        "deutscheshauptdreiecksnetz" | "dhdn" => 6314,
+        "militargeographischeinstitut" | "mgi" => 6312,
+        "mgi1901" => 1031,
+        "amersfoort" => 6289,
+        _ => return None,
+    };
+
+    Some(code)
+}
+
+pub fn ellipsoid_by_name(name: &str) -> Option<Ellipsoid> {
+    match normalize(name).as_str() {
+        "wgs84" | "wgs1984" => Some(WGS84),
+        "grs1980" | "grs80" => Some(GRS80),
+        "international1924" | "international1909hayford" | "hayford1909" => {
+            Some(INTERNATIONAL_1924)
+        }
+        "bessel1841" => Some(BESSEL_1841),
+        "clarke1866" => Some(CLARKE_1866),
+        "airy1830" => Some(AIRY_1830),
+        _ => None,
+    }
+}
+
+pub fn geographic(code: u32) -> Option<Crs> {
+    let datum_code = match code {
+        4326 => 6326,
+        4258 => 6258,
+        4269 => 6269,
+        6318 => 1116,
+        4171 => 6171,
+        4283 => 6283,
+        7844 => 1168,
+        4167 => 6167,
+        4765 => 6765,
+        4761 => 6761,
+        4267 => 6267,
+        4230 => 6230,
+        4277 => 6277,
+        4314 => 6314,
+        4312 => 6312,
+        3906 => 1031,
+        4289 => 6289,
+        _ => return None,
+    };
+
+    let (name, datum) = datum(datum_code)?;
+
+    Some(Crs {
+        name: name.to_string(),
+        kind: CrsKind::Geographic,
+        datum,
+        linear_unit: METRE,
+        vertical_unit: METRE,
+    })
+}
+
+pub fn geocentric(code: u32) -> Option<Crs> {
+    let datum_code = match code {
+        4978 => 6326,
+        4936 => 6258,
+        _ => return None,
+    };
+
+    let (name, datum) = datum(datum_code)?;
+
+    Some(Crs {
+        name: name.to_string(),
+        kind: CrsKind::Geocentric,
+        datum,
+        linear_unit: METRE,
+        vertical_unit: METRE,
+    })
+}
+
+pub fn projected(code: u32) -> Option<Crs> {
+    let (geographic_code, projection) = match code {
+        32601..=32660 => (4326, Projection::utm((code - 32600) as u8, false)),
+        32701..=32760 => (4326, Projection::utm((code - 32700) as u8, true)),
+        25828..=25838 => (4258, Projection::utm((code - 25800) as u8, false)),
+        3040..=3049 => (4258, Projection::utm((code - 3012) as u8, false)),
+        8682 => (4258, Projection::utm(34, false)),
+        26901..=26923 => (4269, Projection::utm((code - 26900) as u8, false)),
+        6328..=6329 => (6318, Projection::utm((code - 6269) as u8, false)),
+        6330..=6348 => (6318, Projection::utm((code - 6329) as u8, false)),
+        26701..=26722 => (4267, Projection::utm((code - 26700) as u8, false)),
+        23028..=23038 => (4230, Projection::utm((code - 23000) as u8, false)),
+        28348..=28358 => (4283, Projection::utm((code - 28300) as u8, true)),
+        7846..=7859 => (7844, Projection::utm((code - 7800) as u8, true)),
+        27700 => (
+            4277,
+            Projection::TransverseMercator {
+                latitude_of_origin: 49.0,
+                central_meridian: -2.0,
+                scale_factor: 0.9996012717,
+                false_easting: 400000.0,
+                false_northing: -100000.0,
+            },
+        ),
+        2193 => (
+            4167,
+            Projection::TransverseMercator {
+                latitude_of_origin: 0.0,
+                central_meridian: 173.0,
+                scale_factor: 0.9996,
+                false_easting: 1600000.0,
+                false_northing: 10000000.0,
+            },
+        ),
+        31466..=31469 => {
+            let zone = (code - 31464) as f64;
+
+            (
+                4314,
+                Projection::TransverseMercator {
+                    latitude_of_origin: 0.0,
+                    central_meridian: zone * 3.0,
+                    scale_factor: 1.0,
+                    false_easting: zone * 1000000.0 + 500000.0,
+                    false_northing: 0.0,
+                },
+            )
+        }
+        3765 => (
+            4761,
+            Projection::TransverseMercator {
+                latitude_of_origin: 0.0,
+                central_meridian: 16.5,
+                scale_factor: 0.9999,
+                false_easting: 500000.0,
+                false_northing: 0.0,
+            },
+        ),
+        3794 => (
+            4765,
+            Projection::TransverseMercator {
+                latitude_of_origin: 0.0,
+                central_meridian: 15.0,
+                scale_factor: 0.9999,
+                false_easting: 500000.0,
+                false_northing: -5000000.0,
+            },
+        ),
+        6316 => (
+            3906,
+            Projection::TransverseMercator {
+                latitude_of_origin: 0.0,
+                central_meridian: 21.0,
+                scale_factor: 0.9999,
+                false_easting: 7500000.0,
+                false_northing: 0.0,
+            },
+        ),
+        2154 => (
+            4171,
+            Projection::LambertConformalConic {
+                latitude_of_origin: 46.5,
+                central_meridian: 3.0,
+                standard_parallel_1: 49.0,
+                standard_parallel_2: 44.0,
+                scale_factor: 1.0,
+                false_easting: 700000.0,
+                false_northing: 6600000.0,
+            },
+        ),
+        3034 => (
+            4258,
+            Projection::LambertConformalConic {
+                latitude_of_origin: 52.0,
+                central_meridian: 10.0,
+                standard_parallel_1: 35.0,
+                standard_parallel_2: 65.0,
+                scale_factor: 1.0,
+                false_easting: 4000000.0,
+                false_northing: 2800000.0,
+            },
+        ),
+        31287 => (
+            4312,
+            Projection::LambertConformalConic {
+                latitude_of_origin: 47.5,
+                central_meridian: 13.0 + 1.0 / 3.0,
+                standard_parallel_1: 49.0,
+                standard_parallel_2: 46.0,
+                scale_factor: 1.0,
+                false_easting: 400000.0,
+                false_northing: 400000.0,
+            },
+        ),
+        _ => return None,
+    };
+
+    let geographic = geographic(geographic_code)?;
+
+    Some(Crs {
+        name: format!("EPSG:{}", code),
+        kind: CrsKind::Projected(projection),
+        ..geographic
+    })
+}
+
+pub fn linear_unit(code: u32) -> Option<f64> {
+    match code {
+        9001 => Some(METRE),
+        9002 => Some(crate::crs::FOOT),
+        9003 => Some(crate::crs::US_SURVEY_FOOT),
+        _ => None,
+    }
+}
+
+pub fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
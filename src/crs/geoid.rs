@@ -0,0 +1,483 @@
+use crate::error::{Error, Result};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+// GTX grids mark cells without data with this value
+const GTX_NO_DATA: f32 = -88.8888;
+
+// geoid undulation grid, stored row by row from south to north and west to east
+pub struct Geoid {
+    name: String,
+    lat_min: f64,
+    lon_min: f64,
+    lat_step: f64,
+    lon_step: f64,
+    rows: usize,
+    columns: usize,
+    wraps: bool,
+    undulations: Vec<f32>,
+}
+
+impl Geoid {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)?;
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("gtx") => Self::from_gtx(name, &bytes),
+            Some("pgm") => Self::from_pgm(name, &bytes),
+            _ => Err(Error::Geoid(format!(
+                "{:?} is not a GTX or PGM geoid grid",
+                path
+            ))),
+        }
+    }
+
+    pub fn from_gtx(name: String, bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 40 {
+            return Err(Error::Geoid(format!("{} has a truncated GTX header", name)));
+        }
+
+        let f64_at = |offset: usize| {
+            let mut buffer = [0u8; 8];
+            buffer.copy_from_slice(&bytes[offset..offset + 8]);
+            f64::from_be_bytes(buffer)
+        };
+
+        let i32_at = |offset: usize| {
+            let mut buffer = [0u8; 4];
+            buffer.copy_from_slice(&bytes[offset..offset + 4]);
+            i32::from_be_bytes(buffer)
+        };
+
+        let rows = i32_at(32).max(0) as usize;
+        let columns = i32_at(36).max(0) as usize;
+
+        let undulations: Vec<f32> = bytes[40..]
+            .chunks_exact(4)
+            .take(rows * columns)
+            .map(|b| f32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+            .map(|value| {
+                if (value - GTX_NO_DATA).abs() < 1e-3 {
+                    f32::NAN
+                } else {
+                    value
+                }
+            })
+            .collect();
+
+        Self::new(
+            name,
+            f64_at(0),
+            f64_at(8),
+            f64_at(16),
+            f64_at(24),
+            rows,
+            columns,
+            undulations,
+        )
+    }
+
+    // GeographicLib grids, 16 bit values scaled by the Offset and Scale comments, rows from north to south
+    pub fn from_pgm(name: String, bytes: &[u8]) -> Result<Self> {
+        let invalid = |message: &str| Error::Geoid(format!("{}: {}", name, message));
+
+        let mut offset = None;
+        let mut scale = None;
+        let mut fields = vec![];
+        let mut position = 0;
+
+        while fields.len() < 4 {
+            while position < bytes.len() && bytes[position].is_ascii_whitespace() {
+                position += 1;
+            }
+
+            if position >= bytes.len() {
+                return Err(invalid("truncated PGM header"));
+            }
+
+            let start = position;
+
+            if bytes[position] == b'#' {
+                while position < bytes.len() && bytes[position] != b'\n' {
+                    position += 1;
+                }
+
+                let comment = String::from_utf8_lossy(&bytes[start + 1..position]);
+                let mut words = comment.split_whitespace();
+
+                match (
+                    words.next(),
+                    words.next().and_then(|v| v.parse::<f64>().ok()),
+                ) {
+                    (Some("Offset"), Some(value)) => offset = Some(value),
+                    (Some("Scale"), Some(value)) => scale = Some(value),
+                    _ => {}
+                }
+            } else {
+                while position < bytes.len() && !bytes[position].is_ascii_whitespace() {
+                    position += 1;
+                }
+
+                fields.push(String::from_utf8_lossy(&bytes[start..position]).to_string());
+            }
+        }
+
+        // a single whitespace character separates the header from the raster
+        position += 1;
+
+        if fields[0] != "P5" {
+            return Err(invalid("only binary (P5) PGM grids are supported"));
+        }
+
+        let number = |field: &str| {
+            field
+                .parse::<usize>()
+                .map_err(|_| invalid("invalid PGM dimensions"))
+        };
+
+        let columns = number(&fields[1])?;
+        let rows = number(&fields[2])?;
+
+        if number(&fields[3])? != 65535 {
+            return Err(invalid("only 16 bit PGM grids are supported"));
+        }
+
+        let offset = offset.ok_or_else(|| invalid("missing Offset in the PGM header"))?;
+        let scale = scale.ok_or_else(|| invalid("missing Scale in the PGM header"))?;
+
+        let raster = bytes.get(position..).unwrap_or_default();
+
+        if raster.len() < rows * columns * 2 {
+            return Err(invalid("truncated PGM raster"));
+        }
+
+        let mut undulations = Vec::with_capacity(rows * columns);
+
+        for row in (0..rows).rev() {
+            let row = &raster[row * columns * 2..(row + 1) * columns * 2];
+
+            undulations.extend(
+                row.chunks_exact(2)
+                    .map(|b| (offset + scale * u16::from_be_bytes([b[0], b[1]]) as f64) as f32),
+            );
+        }
+
+        let lat_step = 180.0 / (rows.max(2) - 1) as f64;
+        let lon_step = 360.0 / columns.max(1) as f64;
+
+        Self::new(
+            name,
+            -90.0,
+            0.0,
+            lat_step,
+            lon_step,
+            rows,
+            columns,
+            undulations,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        name: String,
+        lat_min: f64,
+        lon_min: f64,
+        lat_step: f64,
+        lon_step: f64,
+        rows: usize,
+        columns: usize,
+        undulations: Vec<f32>,
+    ) -> Result<Self> {
+        if rows < 2
+            || columns < 2
+            || lat_step.is_nan()
+            || lat_step <= 0.0
+            || lon_step.is_nan()
+            || lon_step <= 0.0
+        {
+            return Err(Error::Geoid(format!(
+                "{} has an invalid grid definition",
+                name
+            )));
+        }
+
+        if undulations.len() != rows * columns {
+            return Err(Error::Geoid(format!(
+                "{} holds {} values, expected {} x {}",
+                name,
+                undulations.len(),
+                rows,
+                columns
+            )));
+        }
+
+        let wraps = (columns as f64 * lon_step - 360.0).abs() < lon_step / 2.0;
+
+        Ok(Self {
+            name,
+            lat_min,
+            lon_min,
+            lat_step,
+            lon_step,
+            rows,
+            columns,
+            wraps,
+            undulations,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // geoid height above the ellipsoid in metres, bilinearly interpolated
+    pub fn undulation(&self, lat: f64, lon: f64) -> Result<f64> {
+        let lon = self.lon_min + (lon - self.lon_min).rem_euclid(360.0);
+
+        let row = (lat - self.lat_min) / self.lat_step;
+        let column = (lon - self.lon_min) / self.lon_step;
+
+        let last_column = if self.wraps {
+            self.columns as f64
+        } else {
+            (self.columns - 1) as f64
+        };
+
+        if !(row >= 0.0 && row <= (self.rows - 1) as f64 && column <= last_column) {
+            return Err(self.not_covered(lat, lon));
+        }
+
+        let row_0 = (row.floor() as usize).min(self.rows - 2);
+        let column_0 = (column.floor() as usize).min(last_column as usize - 1);
+        let column_1 = (column_0 + 1) % self.columns;
+
+        let dy = row - row_0 as f64;
+        let dx = column - column_0 as f64;
+
+        // cells with zero weight are skipped so no data next to an exact grid node does not spread
+        let undulation = [
+            (row_0, column_0, (1.0 - dy) * (1.0 - dx)),
+            (row_0, column_1, (1.0 - dy) * dx),
+            (row_0 + 1, column_0, dy * (1.0 - dx)),
+            (row_0 + 1, column_1, dy * dx),
+        ]
+        .iter()
+        .filter(|(_, _, weight)| *weight > 0.0)
+        .map(|&(row, column, weight)| weight * self.undulations[row * self.columns + column] as f64)
+        .sum::<f64>();
+
+        if undulation.is_nan() {
+            return Err(self.not_covered(lat, lon));
+        }
+
+        Ok(undulation)
+    }
+
+    fn not_covered(&self, lat: f64, lon: f64) -> Error {
+        Error::Geoid(format!(
+            "{} does not cover latitude {:.6}, longitude {:.6}",
+            self.name,
+            lat,
+            if lon > 180.0 { lon - 360.0 } else { lon }
+        ))
+    }
+}
+
+impl fmt::Debug for Geoid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Geoid")
+            .field("name", &self.name)
+            .field("lat_min", &self.lat_min)
+            .field("lon_min", &self.lon_min)
+            .field("lat_step", &self.lat_step)
+            .field("lon_step", &self.lon_step)
+            .field("rows", &self.rows)
+            .field("columns", &self.columns)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn gtx(header: [f64; 4], rows: i32, columns: i32, values: &[f32]) -> Vec<u8> {
+        let mut bytes = header
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect::<Vec<_>>();
+
+        bytes.extend_from_slice(&rows.to_be_bytes());
+        bytes.extend_from_slice(&columns.to_be_bytes());
+        bytes.extend(values.iter().flat_map(|value| value.to_be_bytes()));
+
+        bytes
+    }
+
+    // bilinear in the grid indices, so interpolation reproduces it exactly
+    fn surface(row: f64, column: f64) -> f64 {
+        10.0 * row + column + row * column
+    }
+
+    // 3 rows from latitude 40 to 42 and 4 columns from longitude 10 to 13
+    fn regional() -> Vec<u8> {
+        let values = (0..3)
+            .flat_map(|row| (0..4).map(move |column| surface(row as f64, column as f64) as f32))
+            .collect::<Vec<_>>();
+
+        gtx([40.0, 10.0, 1.0, 1.0], 3, 4, &values)
+    }
+
+    fn assert_undulation(geoid: &Geoid, lat: f64, lon: f64, expected: f64) {
+        let undulation = geoid.undulation(lat, lon).unwrap();
+
+        assert!(
+            (undulation - expected).abs() < 1e-5,
+            "undulation at {}, {} is {}, expected {}",
+            lat,
+            lon,
+            undulation,
+            expected
+        );
+    }
+
+    #[test]
+    fn gtx_rows_run_from_south_to_north() {
+        let geoid = Geoid::from_gtx("regional.gtx".to_string(), &regional()).unwrap();
+
+        assert_eq!(geoid.name(), "regional.gtx");
+        assert_undulation(&geoid, 40.0, 10.0, 0.0);
+        assert_undulation(&geoid, 40.0, 13.0, 3.0);
+        assert_undulation(&geoid, 42.0, 10.0, 20.0);
+        assert_undulation(&geoid, 42.0, 13.0, surface(2.0, 3.0));
+    }
+
+    #[test]
+    fn bilinear_interpolation() {
+        let geoid = Geoid::from_gtx("regional.gtx".to_string(), &regional()).unwrap();
+
+        assert_undulation(&geoid, 40.5, 10.5, surface(0.5, 0.5));
+        assert_undulation(&geoid, 41.25, 11.75, surface(1.25, 1.75));
+        assert_undulation(&geoid, 41.9, 12.1, surface(1.9, 2.1));
+    }
+
+    #[test]
+    fn longitudes_are_taken_modulo_360() {
+        let geoid = Geoid::from_gtx("regional.gtx".to_string(), &regional()).unwrap();
+
+        assert_undulation(&geoid, 41.5, 12.5 - 360.0, surface(1.5, 2.5));
+        assert_undulation(&geoid, 41.5, 12.5 + 720.0, surface(1.5, 2.5));
+    }
+
+    #[test]
+    fn positions_outside_the_grid() {
+        let geoid = Geoid::from_gtx("regional.gtx".to_string(), &regional()).unwrap();
+
+        assert!(geoid.undulation(39.9, 11.0).is_err());
+        assert!(geoid.undulation(42.1, 11.0).is_err());
+        assert!(geoid.undulation(41.0, 13.5).is_err());
+        assert!(geoid.undulation(41.0, 9.5).is_err());
+    }
+
+    #[test]
+    fn cells_without_data() {
+        let mut values = vec![1.0; 9];
+        values[4] = GTX_NO_DATA;
+
+        let geoid = Geoid::from_gtx(
+            "holes.gtx".to_string(),
+            &gtx([0.0, 0.0, 1.0, 1.0], 3, 3, &values),
+        )
+        .unwrap();
+
+        assert!(geoid.undulation(0.5, 0.5).is_err());
+        assert!(geoid.undulation(1.0, 1.0).is_err());
+        assert_undulation(&geoid, 0.0, 1.0, 1.0);
+        assert_undulation(&geoid, 1.0, 0.0, 1.0);
+    }
+
+    #[test]
+    fn global_grids_wrap_around_the_antimeridian() {
+        // columns at 0, 90, 180 and 270 degrees, the cell east of 270 ends at the first column
+        let values = [0.0, 1.0, 2.0, 3.0].repeat(2);
+
+        let geoid = Geoid::from_gtx(
+            "global.gtx".to_string(),
+            &gtx([-45.0, 0.0, 90.0, 90.0], 2, 4, &values),
+        )
+        .unwrap();
+
+        assert_undulation(&geoid, 0.0, 315.0, 1.5);
+        assert_undulation(&geoid, 0.0, -45.0, 1.5);
+        assert_undulation(&geoid, 0.0, 359.0, 3.0 * 1.0 / 90.0);
+    }
+
+    #[test]
+    fn invalid_gtx_grids() {
+        assert!(Geoid::from_gtx("short.gtx".to_string(), &regional()[..39]).is_err());
+        assert!(Geoid::from_gtx("values.gtx".to_string(), &regional()[..80]).is_err());
+        assert!(Geoid::from_gtx(
+            "step.gtx".to_string(),
+            &gtx([0.0, 0.0, 0.0, 1.0], 2, 2, &[0.0; 4])
+        )
+        .is_err());
+        assert!(Geoid::from_gtx(
+            "rows.gtx".to_string(),
+            &gtx([0.0, 0.0, 1.0, 1.0], 1, 2, &[0.0; 2])
+        )
+        .is_err());
+    }
+
+    fn pgm(header: &str, raster: &[u16]) -> Vec<u8> {
+        let mut bytes = header.as_bytes().to_vec();
+        bytes.extend(raster.iter().flat_map(|value| value.to_be_bytes()));
+
+        bytes
+    }
+
+    // 3 rows at latitudes 90, 0 and -90 and 4 columns every 90 degrees from longitude 0
+    const PGM_HEADER: &str = "P5\n# Geoid file\n# Offset -100\n# Scale 0.5\n4 3\n65535\n";
+
+    #[test]
+    fn pgm_offset_scale_and_rows_from_north_to_south() {
+        let raster = [0, 2, 4, 6, 100, 102, 104, 106, 200, 202, 204, 206];
+
+        let geoid = Geoid::from_pgm("egm.pgm".to_string(), &pgm(PGM_HEADER, &raster)).unwrap();
+
+        assert_undulation(&geoid, 90.0, 0.0, -100.0);
+        assert_undulation(&geoid, 0.0, 90.0, -100.0 + 0.5 * 102.0);
+        assert_undulation(&geoid, -90.0, 270.0, -100.0 + 0.5 * 206.0);
+        assert_undulation(&geoid, 45.0, 45.0, -100.0 + 0.5 * 51.0);
+        assert_undulation(&geoid, 0.0, -45.0, -100.0 + 0.5 * 103.0);
+    }
+
+    #[test]
+    fn invalid_pgm_grids() {
+        let raster = [0; 12];
+
+        for header in [
+            "P2\n# Offset -100\n# Scale 0.5\n4 3\n65535\n",
+            "P5\n# Offset -100\n4 3\n65535\n",
+            "P5\n# Scale 0.5\n4 3\n65535\n",
+            "P5\n# Offset -100\n# Scale 0.5\n4 3\n255\n",
+            "P5\n# Offset -100\n# Scale 0.5\n4 x\n65535\n",
+            "P5\n# Offset -100\n# Scale 0.5\n4 3",
+        ] {
+            assert!(Geoid::from_pgm("bad.pgm".to_string(), &pgm(header, &raster)).is_err());
+        }
+
+        assert!(Geoid::from_pgm("bad.pgm".to_string(), &pgm(PGM_HEADER, &raster[..11])).is_err());
+    }
+}
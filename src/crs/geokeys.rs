@@ -0,0 +1,451 @@
+use crate::crs::ellipsoid::Ellipsoid;
+use crate::crs::epsg;
+use crate::crs::projection::Projection;
+use crate::crs::{Crs, CrsKind, Datum, Helmert, METRE};
+use crate::error::{Error, Result};
+use log::warn;
+use std::collections::HashMap;
+
+const GT_MODEL_TYPE: u16 = 1024;
+const GT_CITATION: u16 = 1026;
+const GEOGRAPHIC_TYPE: u16 = 2048;
+const GEOG_CITATION: u16 = 2049;
+const GEOG_GEODETIC_DATUM: u16 = 2050;
+const GEOG_ELLIPSOID: u16 = 2056;
+const GEOG_SEMI_MAJOR_AXIS: u16 = 2057;
+const GEOG_INV_FLATTENING: u16 = 2059;
+const PROJECTED_CS_TYPE: u16 = 3072;
+const PCS_CITATION: u16 = 3073;
+const PROJECTION: u16 = 3074;
+const PROJ_COORD_TRANS: u16 = 3075;
+const PROJ_LINEAR_UNITS: u16 = 3076;
+const PROJ_STD_PARALLEL_1: u16 = 3078;
+const PROJ_STD_PARALLEL_2: u16 = 3079;
+const PROJ_NAT_ORIGIN_LONG: u16 = 3080;
+const PROJ_NAT_ORIGIN_LAT: u16 = 3081;
+const PROJ_FALSE_EASTING: u16 = 3082;
+const PROJ_FALSE_NORTHING: u16 = 3083;
+const PROJ_FALSE_ORIGIN_LONG: u16 = 3084;
+const PROJ_FALSE_ORIGIN_LAT: u16 = 3085;
+const PROJ_FALSE_ORIGIN_EASTING: u16 = 3086;
+const PROJ_FALSE_ORIGIN_NORTHING: u16 = 3087;
+const PROJ_SCALE_AT_NAT_ORIGIN: u16 = 3092;
+const VERTICAL_UNITS: u16 = 4099;
+
+const MODEL_TYPE_PROJECTED: u16 = 1;
+const MODEL_TYPE_GEOGRAPHIC: u16 = 2;
+const MODEL_TYPE_GEOCENTRIC: u16 = 3;
+
+const COORD_TRANS_TRANSVERSE_MERCATOR: u16 = 1;
+const COORD_TRANS_LAMBERT_CONF_CONIC_2SP: u16 = 8;
+const COORD_TRANS_LAMBERT_CONF_CONIC_1SP: u16 = 9;
+
+const USER_DEFINED: u16 = 32767;
+
+const DOUBLE_PARAMS_TAG: u16 = 34736;
+const ASCII_PARAMS_TAG: u16 = 34737;
+
+enum GeoKey {
+    Short(u16),
+    Double(Vec<f64>),
+    Ascii(String),
+}
+
+struct GeoKeys(HashMap<u16, GeoKey>);
+
+pub fn parse(
+    directory: &[u8],
+    doubles: Option<&[u8]>,
+    ascii: Option<&[u8]>,
+) -> Result<Option<Crs>> {
+    let keys = GeoKeys::read(directory, doubles, ascii)?;
+
+    let model_type = match keys.short(GT_MODEL_TYPE) {
+        Some(model_type) => model_type,
+        None if keys.defined(PROJECTED_CS_TYPE).is_some() => MODEL_TYPE_PROJECTED,
+        None if keys.defined(GEOGRAPHIC_TYPE).is_some() => MODEL_TYPE_GEOGRAPHIC,
+        None => return Ok(None),
+    };
+
+    let mut crs = match model_type {
+        MODEL_TYPE_PROJECTED => projected(&keys)?,
+        MODEL_TYPE_GEOGRAPHIC => Crs {
+            kind: CrsKind::Geographic,
+            ..geographic(&keys)?
+        },
+        MODEL_TYPE_GEOCENTRIC => Crs {
+            kind: CrsKind::Geocentric,
+            ..geographic(&keys)?
+        },
+        _ => {
+            return Err(Error::Crs(format!(
+                "Unsupported GeoTIFF model type {}",
+                model_type
+            )))
+        }
+    };
+
+    if let Some(unit) = keys.defined(VERTICAL_UNITS) {
+        crs.vertical_unit = unit_factor(unit)?;
+    }
+
+    Ok(Some(crs))
+}
+
+impl GeoKeys {
+    fn read(directory: &[u8], doubles: Option<&[u8]>, ascii: Option<&[u8]>) -> Result<Self> {
+        let shorts: Vec<u16> = directory
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .collect();
+
+        let doubles: Vec<f64> = doubles
+            .unwrap_or_default()
+            .chunks_exact(8)
+            .map(|b| f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+            .collect();
+
+        let ascii = ascii.unwrap_or_default();
+
+        let number_of_keys = *shorts
+            .get(3)
+            .ok_or_else(|| Error::Crs("Truncated GeoKeyDirectory record".to_string()))?
+            as usize;
+
+        let mut keys = HashMap::new();
+
+        for entry in shorts[4..].chunks_exact(4).take(number_of_keys) {
+            let (id, location, count, value) = (entry[0], entry[1], entry[2] as usize, entry[3]);
+            let offset = value as usize;
+
+            let key = match location {
+                0 => GeoKey::Short(value),
+                DOUBLE_PARAMS_TAG => match doubles.get(offset..offset + count) {
+                    Some(values) => GeoKey::Double(values.to_vec()),
+                    None => {
+                        warn!("GeoKey {} points outside the GeoDoubleParams record", id);
+                        continue;
+                    }
+                },
+                ASCII_PARAMS_TAG => match ascii.get(offset..offset + count) {
+                    Some(text) => GeoKey::Ascii(
+                        String::from_utf8_lossy(text)
+                            .trim_end_matches(['|', '\0'])
+                            .to_string(),
+                    ),
+                    None => {
+                        warn!("GeoKey {} points outside the GeoAsciiParams record", id);
+                        continue;
+                    }
+                },
+                _ => continue,
+            };
+
+            keys.insert(id, key);
+        }
+
+        Ok(GeoKeys(keys))
+    }
+
+    fn short(&self, id: u16) -> Option<u16> {
+        match self.0.get(&id) {
+            Some(GeoKey::Short(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    // codes that are present and not marked as user defined
+    fn defined(&self, id: u16) -> Option<u16> {
+        self.short(id)
+            .filter(|&value| value != 0 && value != USER_DEFINED)
+    }
+
+    fn double(&self, id: u16) -> Option<f64> {
+        match self.0.get(&id) {
+            Some(GeoKey::Double(values)) => values.first().copied(),
+            _ => None,
+        }
+    }
+
+    fn ascii(&self, id: u16) -> Option<&str> {
+        match self.0.get(&id) {
+            Some(GeoKey::Ascii(text)) => Some(text),
+            _ => None,
+        }
+    }
+
+    // names of user defined systems, falling back to the citation of the whole GeoTIFF
+    fn citation(&self, id: u16) -> &str {
+        self.ascii(id)
+            .or_else(|| self.ascii(GT_CITATION))
+            .filter(|name| !name.is_empty())
+            .unwrap_or("user defined")
+    }
+}
+
+fn geographic(keys: &GeoKeys) -> Result<Crs> {
+    if let Some(code) = keys.defined(GEOGRAPHIC_TYPE) {
+        return epsg::geographic(code as u32)
+            .ok_or_else(|| Error::Crs(format!("Unsupported geographic CRS EPSG:{}", code)));
+    }
+
+    let datum = match keys.defined(GEOG_GEODETIC_DATUM) {
+        Some(code) => epsg::datum(code as u32)
+            .map(|(_, datum)| datum)
+            .ok_or_else(|| Error::Crs(format!("Unsupported datum EPSG:{}", code)))?,
+        None => {
+            let ellipsoid = match (
+                keys.double(GEOG_SEMI_MAJOR_AXIS),
+                keys.double(GEOG_INV_FLATTENING),
+            ) {
+                (Some(a), Some(inverse_flattening)) => Ellipsoid {
+                    a,
+                    inverse_flattening,
+                },
+                _ => match keys.defined(GEOG_ELLIPSOID) {
+                    Some(7030) | None => crate::crs::ellipsoid::WGS84,
+                    Some(7019) => crate::crs::ellipsoid::GRS80,
+                    Some(code) => {
+                        return Err(Error::Crs(format!("Unsupported ellipsoid EPSG:{}", code)))
+                    }
+                },
+            };
+
+            warn!("User defined datum without a WGS84 transformation, treating it as WGS84");
+
+            Datum::new(ellipsoid, Helmert::default())
+        }
+    };
+
+    Ok(Crs {
+        name: keys.citation(GEOG_CITATION).to_string(),
+        kind: CrsKind::Geographic,
+        datum,
+        linear_unit: METRE,
+        vertical_unit: METRE,
+    })
+}
+
+fn projected(keys: &GeoKeys) -> Result<Crs> {
+    let linear_unit = match keys.defined(PROJ_LINEAR_UNITS) {
+        Some(unit) => Some(unit_factor(unit)?),
+        None => None,
+    };
+
+    if let Some(code) = keys.defined(PROJECTED_CS_TYPE) {
+        let crs = epsg::projected(code as u32)
+            .ok_or_else(|| Error::Crs(format!("Unsupported projected CRS EPSG:{}", code)))?;
+
+        return Ok(match linear_unit {
+            Some(unit) => Crs {
+                linear_unit: unit,
+                vertical_unit: unit,
+                ..crs
+            },
+            None => crs,
+        });
+    }
+
+    let linear_unit = linear_unit.unwrap_or(METRE);
+    let base = geographic(keys)?;
+
+    let projection = match keys.defined(PROJECTION) {
+        Some(code @ 16001..=16060) => Projection::utm((code - 16000) as u8, false),
+        Some(code @ 16101..=16160) => Projection::utm((code - 16100) as u8, true),
+        Some(code) => return Err(Error::Crs(format!("Unsupported projection EPSG:{}", code))),
+        None => user_defined_projection(keys, linear_unit)?,
+    };
+
+    Ok(Crs {
+        name: keys.citation(PCS_CITATION).to_string(),
+        kind: CrsKind::Projected(projection),
+        linear_unit,
+        vertical_unit: linear_unit,
+        ..base
+    })
+}
+
+fn user_defined_projection(keys: &GeoKeys, linear_unit: f64) -> Result<Projection> {
+    let double = |ids: &[u16], default: f64| {
+        ids.iter()
+            .find_map(|&id| keys.double(id))
+            .unwrap_or(default)
+    };
+
+    let latitude_of_origin = double(&[PROJ_NAT_ORIGIN_LAT, PROJ_FALSE_ORIGIN_LAT], 0.0);
+    let central_meridian = double(&[PROJ_NAT_ORIGIN_LONG, PROJ_FALSE_ORIGIN_LONG], 0.0);
+    let scale_factor = double(&[PROJ_SCALE_AT_NAT_ORIGIN], 1.0);
+    let false_easting = double(&[PROJ_FALSE_EASTING, PROJ_FALSE_ORIGIN_EASTING], 0.0) * linear_unit;
+    let false_northing =
+        double(&[PROJ_FALSE_NORTHING, PROJ_FALSE_ORIGIN_NORTHING], 0.0) * linear_unit;
+
+    match keys.short(PROJ_COORD_TRANS) {
+        Some(COORD_TRANS_TRANSVERSE_MERCATOR) => Ok(Projection::TransverseMercator {
+            latitude_of_origin,
+            central_meridian,
+            scale_factor,
+            false_easting,
+            false_northing,
+        }),
+        Some(COORD_TRANS_LAMBERT_CONF_CONIC_2SP) => Ok(Projection::LambertConformalConic {
+            latitude_of_origin,
+            central_meridian,
+            standard_parallel_1: double(&[PROJ_STD_PARALLEL_1], latitude_of_origin),
+            standard_parallel_2: double(&[PROJ_STD_PARALLEL_2], latitude_of_origin),
+            scale_factor: 1.0,
+            false_easting,
+            false_northing,
+        }),
+        Some(COORD_TRANS_LAMBERT_CONF_CONIC_1SP) => Ok(Projection::LambertConformalConic {
+            latitude_of_origin,
+            central_meridian,
+            standard_parallel_1: latitude_of_origin,
+            standard_parallel_2: latitude_of_origin,
+            scale_factor,
+            false_easting,
+            false_northing,
+        }),
+        Some(method) => Err(Error::Crs(format!(
+            "Unsupported GeoTIFF coordinate transformation {}",
+            method
+        ))),
+        None => Err(Error::Crs(
+            "User defined projection without a coordinate transformation".to_string(),
+        )),
+    }
+}
+
+fn unit_factor(code: u16) -> Result<f64> {
+    epsg::linear_unit(code as u32)
+        .ok_or_else(|| Error::Crs(format!("Unsupported linear unit EPSG:{}", code)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crs::US_SURVEY_FOOT;
+
+    // a GeoKeyDirectory with its double and ASCII parameters, as the three LAS records store them
+    #[derive(Default)]
+    struct Directory {
+        keys: Vec<[u16; 4]>,
+        doubles: Vec<f64>,
+        ascii: String,
+    }
+
+    impl Directory {
+        fn short(mut self, id: u16, value: u16) -> Self {
+            self.keys.push([id, 0, 1, value]);
+            self
+        }
+
+        fn double(mut self, id: u16, value: f64) -> Self {
+            self.keys
+                .push([id, DOUBLE_PARAMS_TAG, 1, self.doubles.len() as u16]);
+            self.doubles.push(value);
+            self
+        }
+
+        fn ascii(mut self, id: u16, value: &str) -> Self {
+            let text = format!("{}|", value);
+
+            self.keys.push([
+                id,
+                ASCII_PARAMS_TAG,
+                text.len() as u16,
+                self.ascii.len() as u16,
+            ]);
+            self.ascii.push_str(&text);
+            self
+        }
+
+        fn parse(&self) -> Option<Crs> {
+            let directory = [1, 1, 0, self.keys.len() as u16]
+                .iter()
+                .chain(self.keys.iter().flatten())
+                .flat_map(|short| short.to_le_bytes())
+                .collect::<Vec<_>>();
+
+            let doubles = self
+                .doubles
+                .iter()
+                .flat_map(|double| double.to_le_bytes())
+                .collect::<Vec<_>>();
+
+            parse(&directory, Some(&doubles), Some(self.ascii.as_bytes())).unwrap()
+        }
+    }
+
+    #[test]
+    fn projected_epsg_code() {
+        let crs = Directory::default()
+            .short(GT_MODEL_TYPE, MODEL_TYPE_PROJECTED)
+            .short(PROJECTED_CS_TYPE, 32617)
+            .ascii(GT_CITATION, "WGS 84 / UTM zone 17N")
+            .parse()
+            .unwrap();
+
+        assert_eq!(crs, epsg::projected(32617).unwrap());
+        assert_eq!(crs.kind, CrsKind::Projected(Projection::utm(17, false)));
+    }
+
+    // NAD27 / Texas South Central, EPSG:32040, spelled out as a user defined projection
+    #[test]
+    fn user_defined_lambert_conformal_conic_2sp() {
+        let crs = Directory::default()
+            .short(GT_MODEL_TYPE, MODEL_TYPE_PROJECTED)
+            .ascii(GT_CITATION, "Texas South Central")
+            .short(GEOG_GEODETIC_DATUM, 6267)
+            .short(PROJECTED_CS_TYPE, USER_DEFINED)
+            .ascii(PCS_CITATION, "NAD27 / Texas South Central")
+            .short(PROJ_COORD_TRANS, COORD_TRANS_LAMBERT_CONF_CONIC_2SP)
+            .short(PROJ_LINEAR_UNITS, 9003)
+            .double(PROJ_STD_PARALLEL_1, 28.0 + 23.0 / 60.0)
+            .double(PROJ_STD_PARALLEL_2, 30.0 + 17.0 / 60.0)
+            .double(PROJ_FALSE_ORIGIN_LAT, 27.0 + 50.0 / 60.0)
+            .double(PROJ_FALSE_ORIGIN_LONG, -99.0)
+            .double(PROJ_FALSE_ORIGIN_EASTING, 2000000.0)
+            .double(PROJ_FALSE_ORIGIN_NORTHING, 0.0)
+            .parse()
+            .unwrap();
+
+        assert_eq!(crs.name, "NAD27 / Texas South Central");
+        assert_eq!(crs.linear_unit, US_SURVEY_FOOT);
+        assert_eq!(crs.datum, epsg::datum(6267).unwrap().1);
+        assert_eq!(
+            crs.kind,
+            CrsKind::Projected(Projection::LambertConformalConic {
+                latitude_of_origin: 27.0 + 50.0 / 60.0,
+                central_meridian: -99.0,
+                standard_parallel_1: 28.0 + 23.0 / 60.0,
+                standard_parallel_2: 30.0 + 17.0 / 60.0,
+                scale_factor: 1.0,
+                false_easting: 2000000.0 * US_SURVEY_FOOT,
+                false_northing: 0.0,
+            })
+        );
+    }
+
+    #[test]
+    fn names_fall_back_to_the_citation() {
+        let directory = Directory::default()
+            .short(GT_MODEL_TYPE, MODEL_TYPE_GEOGRAPHIC)
+            .ascii(GT_CITATION, "Local survey")
+            .short(GEOGRAPHIC_TYPE, USER_DEFINED)
+            .short(GEOG_GEODETIC_DATUM, 6326);
+
+        assert_eq!(directory.parse().unwrap().name, "Local survey");
+
+        let directory = directory.ascii(GEOG_CITATION, "GCS WGS 84");
+
+        assert_eq!(directory.parse().unwrap().name, "GCS WGS 84");
+
+        let crs = Directory::default()
+            .short(GT_MODEL_TYPE, MODEL_TYPE_GEOGRAPHIC)
+            .short(GEOG_GEODETIC_DATUM, 6326)
+            .parse()
+            .unwrap();
+
+        assert_eq!(crs.name, "user defined");
+    }
+}
@@ -0,0 +1,352 @@
+mod ellipsoid;
+mod epsg;
+mod geoid;
+mod geokeys;
+mod projection;
+mod wkt;
+
+pub use crate::crs::ellipsoid::{Ellipsoid, WGS84};
+pub use crate::crs::geoid::Geoid;
+pub use crate::crs::projection::Projection;
+
+use crate::error::{Error, Result};
+use std::path::Path;
+use std::str::FromStr;
+
+const PROJECTION_USER_ID: &str = "LASF_Projection";
+
+const GEO_KEY_DIRECTORY_RECORD_ID: u16 = 34735;
+
+const GEO_DOUBLE_PARAMS_RECORD_ID: u16 = 34736;
+
+const GEO_ASCII_PARAMS_RECORD_ID: u16 = 34737;
+
+const OGC_WKT_RECORD_ID: u16 = 2112;
+
+pub const METRE: f64 = 1.0;
+
+pub const FOOT: f64 = 0.3048;
+
+pub const US_SURVEY_FOOT: f64 = 1200.0 / 3937.0;
+
+// seven parameter position vector transformation, rotations in arc seconds and scale in ppm
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Helmert {
+    pub tx: f64,
+    pub ty: f64,
+    pub tz: f64,
+    pub rx: f64,
+    pub ry: f64,
+    pub rz: f64,
+    pub ds: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Datum {
+    pub ellipsoid: Ellipsoid,
+    pub to_wgs84: Helmert,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CrsKind {
+    Geographic,
+    Projected(Projection),
+    Geocentric,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Crs {
+    pub name: String,
+    pub kind: CrsKind,
+    pub datum: Datum,
+    pub linear_unit: f64,
+    pub vertical_unit: f64,
+}
+
+impl Helmert {
+    pub fn translation(tx: f64, ty: f64, tz: f64) -> Self {
+        Self {
+            tx,
+            ty,
+            tz,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn apply(&self, x: f64, y: f64, z: f64) -> (f64, f64, f64) {
+        if self.is_identity() {
+            return (x, y, z);
+        }
+
+        let arc_second = (1.0_f64 / 3600.0).to_radians();
+        let rx = self.rx * arc_second;
+        let ry = self.ry * arc_second;
+        let rz = self.rz * arc_second;
+        let scale = 1.0 + self.ds * 1e-6;
+
+        (
+            self.tx + scale * (x - rz * y + ry * z),
+            self.ty + scale * (rz * x + y - rx * z),
+            self.tz + scale * (-ry * x + rx * y + z),
+        )
+    }
+}
+
+impl Datum {
+    pub fn new(ellipsoid: Ellipsoid, to_wgs84: Helmert) -> Self {
+        Self {
+            ellipsoid,
+            to_wgs84,
+        }
+    }
+}
+
+impl Crs {
+    pub fn wgs84() -> Self {
+        epsg::geographic(4326).unwrap()
+    }
+
+    pub fn from_epsg(code: u32) -> Result<Self> {
+        epsg::crs(code).ok_or_else(|| {
+            Error::Crs(format!(
+                "EPSG:{} is not in the built-in CRS table, pass its WKT definition instead",
+                code
+            ))
+        })
+    }
+
+    pub fn from_wkt(wkt: &str) -> Result<Self> {
+        wkt::parse(wkt)
+    }
+
+    pub fn from_las_header(header: &las::Header) -> Result<Option<Self>> {
+        let mut wkt = None;
+        let mut directory = None;
+        let mut doubles = None;
+        let mut ascii = None;
+
+        for vlr in header.vlrs().iter().chain(header.evlrs().iter()) {
+            if vlr.user_id.trim_end_matches('\0') != PROJECTION_USER_ID {
+                continue;
+            }
+
+            match vlr.record_id {
+                OGC_WKT_RECORD_ID => wkt = Some(&vlr.data),
+                GEO_KEY_DIRECTORY_RECORD_ID => directory = Some(&vlr.data),
+                GEO_DOUBLE_PARAMS_RECORD_ID => doubles = Some(&vlr.data),
+                GEO_ASCII_PARAMS_RECORD_ID => ascii = Some(&vlr.data),
+                _ => {}
+            }
+        }
+
+        if let Some(wkt) = wkt {
+            let wkt = String::from_utf8_lossy(wkt);
+            let wkt = wkt.trim_end_matches('\0').trim();
+
+            if !wkt.is_empty() {
+                return Self::from_wkt(wkt).map(Some);
+            }
+        }
+
+        match directory {
+            Some(directory) => geokeys::parse(
+                directory,
+                doubles.map(|d| d.as_slice()),
+                ascii.map(|a| a.as_slice()),
+            ),
+            None => Ok(None),
+        }
+    }
+
+    // heights are ellipsoidal unless a geoid is given, then they are orthometric heights above it;
+    // geoid grids are relative to WGS84, so the undulation is added once the point is on WGS84
+    pub fn to_ecef(
+        &self,
+        x: f64,
+        y: f64,
+        z: f64,
+        geoid: Option<&Geoid>,
+    ) -> Result<(f64, f64, f64)> {
+        let h = z * self.vertical_unit;
+
+        let (x, y, z) = match &self.kind {
+            CrsKind::Geographic => self.datum.ellipsoid.geodetic_to_geocentric(y, x, h),
+            CrsKind::Projected(projection) => {
+                let (lat, lon) = projection.to_geodetic(
+                    &self.datum.ellipsoid,
+                    x * self.linear_unit,
+                    y * self.linear_unit,
+                );
+
+                self.datum.ellipsoid.geodetic_to_geocentric(lat, lon, h)
+            }
+            // geocentric coordinates have no orthometric heights
+            CrsKind::Geocentric => {
+                return Ok(self
+                    .datum
+                    .to_wgs84
+                    .apply(x * self.linear_unit, y * self.linear_unit, h))
+            }
+        };
+
+        let (x, y, z) = self.datum.to_wgs84.apply(x, y, z);
+
+        match geoid {
+            Some(geoid) => {
+                let (lat, lon, h) = WGS84.geocentric_to_geodetic(x, y, z);
+
+                Ok(WGS84.geodetic_to_geocentric(lat, lon, h + geoid.undulation(lat, lon)?))
+            }
+            None => Ok((x, y, z)),
+        }
+    }
+}
+
+impl FromStr for Crs {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+
+        if let Some(code) = s
+            .get(..5)
+            .filter(|prefix| prefix.eq_ignore_ascii_case("EPSG:"))
+            .map(|_| &s[5..])
+        {
+            let code = code
+                .trim()
+                .parse::<u32>()
+                .map_err(|_| Error::Crs(format!("Invalid EPSG code {:?}", s)))?;
+
+            return Self::from_epsg(code);
+        }
+
+        let path = Path::new(s);
+
+        if path.is_file() {
+            return Self::from_wkt(&std::fs::read_to_string(path)?);
+        }
+
+        Self::from_wkt(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crs::ellipsoid::{AIRY_1830, CLARKE_1866};
+    use crate::crs::geoid::tests::gtx;
+
+    fn assert_close(actual: (f64, f64, f64), expected: (f64, f64, f64), tolerance: f64) {
+        assert!(
+            (actual.0 - expected.0).abs() < tolerance
+                && (actual.1 - expected.1).abs() < tolerance
+                && (actual.2 - expected.2).abs() < tolerance,
+            "got {:?}, expected {:?}",
+            actual,
+            expected
+        );
+    }
+
+    // worked example of the EPSG guidance note 7-2, WGS 72 to WGS 84 by position vector
+    #[test]
+    fn helmert_position_vector() {
+        let helmert = Helmert {
+            tz: 4.5,
+            rz: 0.554,
+            ds: 0.219,
+            ..Helmert::default()
+        };
+
+        assert_close(
+            helmert.apply(3657660.66, 255768.55, 5201382.11),
+            (3657660.78, 255778.43, 5201387.75),
+            0.01,
+        );
+    }
+
+    #[test]
+    fn british_national_grid() {
+        let crs = Crs::from_epsg(27700).unwrap();
+
+        let osgb36 = AIRY_1830.geodetic_to_geocentric(50.5, 0.5, 10.0);
+
+        assert_close(
+            crs.to_ecef(577274.99, 69740.50, 10.0, None).unwrap(),
+            crs.datum.to_wgs84.apply(osgb36.0, osgb36.1, osgb36.2),
+            0.01,
+        );
+    }
+
+    // OSGB36 orthometric heights, with a geoid steep enough in longitude that the datum shift of
+    // about 100 m moves the undulation by more than a meter
+    #[test]
+    fn geoid_undulation_at_the_wgs84_position() {
+        let crs = Crs::from_epsg(27700).unwrap();
+
+        // 4 rows from latitude 49 to 52 and 4 columns from longitude -1 to 2
+        let undulation = |lat: f64, lon: f64| 40.0 + 5.0 * (lat - 49.0) + 1000.0 * (lon + 1.0);
+        let values = (0..4)
+            .flat_map(|row| {
+                (0..4).map(move |column| undulation(49.0 + row as f64, column as f64 - 1.0) as f32)
+            })
+            .collect::<Vec<_>>();
+        let geoid = Geoid::from_gtx(
+            "steep.gtx".to_string(),
+            &gtx([49.0, -1.0, 1.0, 1.0], 4, 4, &values),
+        )
+        .unwrap();
+
+        let osgb36 = AIRY_1830.geodetic_to_geocentric(50.5, 0.5, 10.0);
+        let shifted = crs.datum.to_wgs84.apply(osgb36.0, osgb36.1, osgb36.2);
+        let (lat, lon, h) = WGS84.geocentric_to_geodetic(shifted.0, shifted.1, shifted.2);
+
+        // the datum shift moves the point, a sample at the OSGB36 position would be off
+        assert!((undulation(lat, lon) - undulation(50.5, 0.5)).abs() > 1.0);
+
+        let ecef = crs
+            .to_ecef(577274.99, 69740.50, 10.0, Some(&geoid))
+            .unwrap();
+        let geodetic = WGS84.geocentric_to_geodetic(ecef.0, ecef.1, ecef.2);
+
+        assert_close(geodetic, (lat, lon, h + undulation(lat, lon)), 1e-3);
+    }
+
+    #[test]
+    fn wkt_lambert_conformal_conic_in_us_survey_feet() {
+        let crs = Crs::from_wkt(
+            r#"PROJCS["NAD27 / Texas South Central",
+                GEOGCS["NAD27",
+                    DATUM["North_American_Datum_1927",
+                        SPHEROID["Clarke 1866",6378206.4,294.978698213898]],
+                    PRIMEM["Greenwich",0],
+                    UNIT["degree",0.0174532925199433]],
+                PROJECTION["Lambert_Conformal_Conic_2SP"],
+                PARAMETER["standard_parallel_1",28.3833333333333],
+                PARAMETER["standard_parallel_2",30.2833333333333],
+                PARAMETER["latitude_of_origin",27.8333333333333],
+                PARAMETER["central_meridian",-99],
+                PARAMETER["false_easting",2000000],
+                PARAMETER["false_northing",0],
+                UNIT["US survey foot",0.304800609601219],
+                AUTHORITY["EPSG","32040"]]"#,
+        )
+        .unwrap();
+
+        assert_eq!(crs.name, "NAD27 / Texas South Central");
+        assert!((crs.linear_unit - US_SURVEY_FOOT).abs() < 1e-12);
+        assert_eq!(crs.datum.to_wgs84, Helmert::translation(-8.0, 160.0, 176.0));
+
+        let nad27 = CLARKE_1866.geodetic_to_geocentric(28.5, -96.0, 100.0 * US_SURVEY_FOOT);
+
+        assert_close(
+            crs.to_ecef(2963503.91, 254759.80, 100.0, None).unwrap(),
+            crs.datum.to_wgs84.apply(nad27.0, nad27.1, nad27.2),
+            0.01,
+        );
+    }
+}
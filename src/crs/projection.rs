@@ -0,0 +1,311 @@
+use crate::crs::ellipsoid::Ellipsoid;
+use std::f64::consts::FRAC_PI_2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    TransverseMercator {
+        latitude_of_origin: f64,
+        central_meridian: f64,
+        scale_factor: f64,
+        false_easting: f64,
+        false_northing: f64,
+    },
+    LambertConformalConic {
+        latitude_of_origin: f64,
+        central_meridian: f64,
+        standard_parallel_1: f64,
+        standard_parallel_2: f64,
+        scale_factor: f64,
+        false_easting: f64,
+        false_northing: f64,
+    },
+}
+
+impl Projection {
+    pub fn utm(zone: u8, south: bool) -> Self {
+        Projection::TransverseMercator {
+            latitude_of_origin: 0.0,
+            central_meridian: zone as f64 * 6.0 - 183.0,
+            scale_factor: 0.9996,
+            false_easting: 500000.0,
+            false_northing: if south { 10000000.0 } else { 0.0 },
+        }
+    }
+
+    pub fn to_geodetic(&self, ellipsoid: &Ellipsoid, easting: f64, northing: f64) -> (f64, f64) {
+        match *self {
+            Projection::TransverseMercator {
+                latitude_of_origin,
+                central_meridian,
+                scale_factor,
+                false_easting,
+                false_northing,
+            } => transverse_mercator_inverse(
+                ellipsoid,
+                latitude_of_origin,
+                central_meridian,
+                scale_factor,
+                easting - false_easting,
+                northing - false_northing,
+            ),
+            Projection::LambertConformalConic {
+                latitude_of_origin,
+                central_meridian,
+                standard_parallel_1,
+                standard_parallel_2,
+                scale_factor,
+                false_easting,
+                false_northing,
+            } => lambert_conformal_conic_inverse(
+                ellipsoid,
+                latitude_of_origin,
+                central_meridian,
+                standard_parallel_1,
+                standard_parallel_2,
+                scale_factor,
+                easting - false_easting,
+                northing - false_northing,
+            ),
+        }
+    }
+}
+
+// Krüger series to sixth order in the third flattening, see C. F. F. Karney,
+// "Transverse Mercator with an accuracy of a few nanometers", J. Geodesy 85 (2011).
+fn transverse_mercator_inverse(
+    ellipsoid: &Ellipsoid,
+    latitude_of_origin: f64,
+    central_meridian: f64,
+    scale_factor: f64,
+    x: f64,
+    y: f64,
+) -> (f64, f64) {
+    let f = ellipsoid.flattening();
+    let e = ellipsoid.eccentricity();
+    let n = f / (2.0 - f);
+    let n2 = n * n;
+    let n3 = n2 * n;
+    let n4 = n3 * n;
+    let n5 = n4 * n;
+    let n6 = n5 * n;
+
+    let rectifying_radius = ellipsoid.a / (1.0 + n) * (1.0 + n2 / 4.0 + n4 / 64.0 + n6 / 256.0);
+
+    let alpha = [
+        n / 2.0 - 2.0 * n2 / 3.0 + 5.0 * n3 / 16.0 + 41.0 * n4 / 180.0 - 127.0 * n5 / 288.0
+            + 7891.0 * n6 / 37800.0,
+        13.0 * n2 / 48.0 - 3.0 * n3 / 5.0 + 557.0 * n4 / 1440.0 + 281.0 * n5 / 630.0
+            - 1983433.0 * n6 / 1935360.0,
+        61.0 * n3 / 240.0 - 103.0 * n4 / 140.0 + 15061.0 * n5 / 26880.0 + 167603.0 * n6 / 181440.0,
+        49561.0 * n4 / 161280.0 - 179.0 * n5 / 168.0 + 6601661.0 * n6 / 7257600.0,
+        34729.0 * n5 / 80640.0 - 3418889.0 * n6 / 1995840.0,
+        212378941.0 * n6 / 319334400.0,
+    ];
+
+    let beta = [
+        n / 2.0 - 2.0 * n2 / 3.0 + 37.0 * n3 / 96.0 - n4 / 360.0 - 81.0 * n5 / 512.0
+            + 96199.0 * n6 / 604800.0,
+        n2 / 48.0 + n3 / 15.0 - 437.0 * n4 / 1440.0 + 46.0 * n5 / 105.0
+            - 1118711.0 * n6 / 3870720.0,
+        17.0 * n3 / 480.0 - 37.0 * n4 / 840.0 - 209.0 * n5 / 4480.0 + 5569.0 * n6 / 90720.0,
+        4397.0 * n4 / 161280.0 - 11.0 * n5 / 504.0 - 830251.0 * n6 / 7257600.0,
+        4583.0 * n5 / 161280.0 - 108847.0 * n6 / 3991680.0,
+        20648693.0 * n6 / 638668800.0,
+    ];
+
+    // northing of the latitude of origin on the central meridian, in units of the rectifying radius
+    let xi_origin = {
+        let phi = latitude_of_origin.to_radians();
+        let tau_prime = conformal_tau(phi.tan(), e);
+        let xi_prime = tau_prime.atan();
+
+        xi_prime
+            + alpha
+                .iter()
+                .enumerate()
+                .map(|(j, a)| a * (2.0 * (j + 1) as f64 * xi_prime).sin())
+                .sum::<f64>()
+    };
+
+    let xi = y / (scale_factor * rectifying_radius) + xi_origin;
+    let eta = x / (scale_factor * rectifying_radius);
+
+    let mut xi_prime = xi;
+    let mut eta_prime = eta;
+
+    for (j, b) in beta.iter().enumerate() {
+        let k = 2.0 * (j + 1) as f64;
+        xi_prime -= b * (k * xi).sin() * (k * eta).cosh();
+        eta_prime -= b * (k * xi).cos() * (k * eta).sinh();
+    }
+
+    let tau_prime = xi_prime.sin() / (eta_prime.sinh().powi(2) + xi_prime.cos().powi(2)).sqrt();
+    let lambda = eta_prime.sinh().atan2(xi_prime.cos());
+
+    let lat = geodetic_tau(tau_prime, e).atan().to_degrees();
+    let lon = central_meridian + lambda.to_degrees();
+
+    (lat, lon)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn lambert_conformal_conic_inverse(
+    ellipsoid: &Ellipsoid,
+    latitude_of_origin: f64,
+    central_meridian: f64,
+    standard_parallel_1: f64,
+    standard_parallel_2: f64,
+    scale_factor: f64,
+    x: f64,
+    y: f64,
+) -> (f64, f64) {
+    let e = ellipsoid.eccentricity();
+
+    let m = |phi: f64| phi.cos() / (1.0 - e * e * phi.sin().powi(2)).sqrt();
+    let t = |phi: f64| {
+        (std::f64::consts::FRAC_PI_4 - phi / 2.0).tan()
+            / ((1.0 - e * phi.sin()) / (1.0 + e * phi.sin())).powf(e / 2.0)
+    };
+
+    let phi_0 = latitude_of_origin.to_radians();
+    let phi_1 = standard_parallel_1.to_radians();
+    let phi_2 = standard_parallel_2.to_radians();
+
+    let n = if (phi_1 - phi_2).abs() < 1e-12 {
+        phi_1.sin()
+    } else {
+        (m(phi_1).ln() - m(phi_2).ln()) / (t(phi_1).ln() - t(phi_2).ln())
+    };
+
+    let big_f = m(phi_1) / (n * t(phi_1).powf(n));
+    let rho_0 = ellipsoid.a * big_f * scale_factor * t(phi_0).powf(n);
+
+    let dy = rho_0 - y;
+    let rho = n.signum() * (x * x + dy * dy).sqrt();
+    let theta = (n.signum() * x).atan2(n.signum() * dy);
+
+    let t_prime = (rho / (ellipsoid.a * scale_factor * big_f)).powf(1.0 / n);
+
+    let mut phi = FRAC_PI_2 - 2.0 * t_prime.atan();
+
+    for _ in 0..15 {
+        let next = FRAC_PI_2
+            - 2.0
+                * (t_prime * ((1.0 - e * phi.sin()) / (1.0 + e * phi.sin())).powf(e / 2.0)).atan();
+
+        if (next - phi).abs() < 1e-14 {
+            phi = next;
+            break;
+        }
+
+        phi = next;
+    }
+
+    (
+        phi.to_degrees(),
+        central_meridian + (theta / n).to_degrees(),
+    )
+}
+
+fn conformal_tau(tau: f64, e: f64) -> f64 {
+    let sigma = (e * (e * tau / (1.0 + tau * tau).sqrt()).atanh()).sinh();
+
+    tau * (1.0 + sigma * sigma).sqrt() - sigma * (1.0 + tau * tau).sqrt()
+}
+
+fn geodetic_tau(tau_prime: f64, e: f64) -> f64 {
+    let e2 = e * e;
+    let mut tau = tau_prime;
+
+    for _ in 0..10 {
+        let tau_prime_i = conformal_tau(tau, e);
+        let delta = (tau_prime - tau_prime_i) / (1.0 + tau_prime_i * tau_prime_i).sqrt()
+            * (1.0 + (1.0 - e2) * tau * tau)
+            / ((1.0 - e2) * (1.0 + tau * tau).sqrt());
+
+        tau += delta;
+
+        if delta.abs() < 1e-14 {
+            break;
+        }
+    }
+
+    tau
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crs::ellipsoid::{AIRY_1830, CLARKE_1866, WGS84};
+    use crate::crs::US_SURVEY_FOOT;
+
+    fn assert_geodetic((lat, lon): (f64, f64), expected: (f64, f64), tolerance: f64) {
+        assert!(
+            (lat - expected.0).abs() < tolerance && (lon - expected.1).abs() < tolerance,
+            "got {:?}, expected {:?}",
+            (lat, lon),
+            expected
+        );
+    }
+
+    // worked example of the EPSG guidance note 7-2 for the British National Grid, EPSG:27700
+    #[test]
+    fn transverse_mercator() {
+        let projection = Projection::TransverseMercator {
+            latitude_of_origin: 49.0,
+            central_meridian: -2.0,
+            scale_factor: 0.9996012717,
+            false_easting: 400000.0,
+            false_northing: -100000.0,
+        };
+
+        assert_geodetic(
+            projection.to_geodetic(&AIRY_1830, 577274.99, 69740.50),
+            (50.5, 0.5),
+            1e-7,
+        );
+    }
+
+    // the CN Tower in Toronto, UTM zone 17N on WGS 84, EPSG:32617
+    #[test]
+    fn universal_transverse_mercator() {
+        assert_geodetic(
+            Projection::utm(17, false).to_geodetic(&WGS84, 630084.0, 4833438.0),
+            (
+                43.0 + 38.0 / 60.0 + 33.24 / 3600.0,
+                -(79.0 + 23.0 / 60.0 + 13.7 / 3600.0),
+            ),
+            1e-5,
+        );
+
+        assert_geodetic(
+            Projection::utm(33, true).to_geodetic(&WGS84, 500000.0, 10000000.0),
+            (0.0, 15.0),
+            1e-12,
+        );
+    }
+
+    // worked example of the EPSG guidance note 7-2 for NAD27 / Texas South Central, EPSG:32040
+    #[test]
+    fn lambert_conformal_conic_2sp() {
+        let projection = Projection::LambertConformalConic {
+            latitude_of_origin: 27.0 + 50.0 / 60.0,
+            central_meridian: -99.0,
+            standard_parallel_1: 28.0 + 23.0 / 60.0,
+            standard_parallel_2: 30.0 + 17.0 / 60.0,
+            scale_factor: 1.0,
+            false_easting: 2000000.0 * US_SURVEY_FOOT,
+            false_northing: 0.0,
+        };
+
+        assert_geodetic(
+            projection.to_geodetic(
+                &CLARKE_1866,
+                2963503.91 * US_SURVEY_FOOT,
+                254759.80 * US_SURVEY_FOOT,
+            ),
+            (28.5, -96.0),
+            1e-7,
+        );
+    }
+}
@@ -0,0 +1,521 @@
+use crate::crs::epsg;
+use crate::crs::projection::Projection;
+use crate::crs::{Crs, CrsKind, Datum, Helmert, METRE};
+use crate::error::{Error, Result};
+use log::warn;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Node(Node),
+    Text(String),
+    Number(f64),
+    Keyword(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Node {
+    keyword: String,
+    values: Vec<Value>,
+}
+
+pub fn parse(wkt: &str) -> Result<Crs> {
+    let mut parser = Parser {
+        chars: wkt.chars().collect(),
+        position: 0,
+    };
+
+    let node = parser.node()?;
+
+    crs(&node)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn node(&mut self) -> Result<Node> {
+        let keyword = self.word();
+
+        if keyword.is_empty() {
+            return Err(self.error("expected a keyword"));
+        }
+
+        let mut values = vec![];
+
+        self.skip_whitespace();
+
+        if let Some('[') | Some('(') = self.peek() {
+            self.position += 1;
+
+            loop {
+                self.skip_whitespace();
+
+                match self.peek() {
+                    Some(']') | Some(')') => {
+                        self.position += 1;
+                        break;
+                    }
+                    Some(',') => self.position += 1,
+                    Some('"') => values.push(Value::Text(self.text()?)),
+                    Some(c) if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => {
+                        values.push(Value::Number(self.number()?))
+                    }
+                    Some(c) if c.is_ascii_alphabetic() => {
+                        let start = self.position;
+                        let word = self.word();
+
+                        self.skip_whitespace();
+
+                        if let Some('[') | Some('(') = self.peek() {
+                            self.position = start;
+                            values.push(Value::Node(self.node()?));
+                        } else {
+                            values.push(Value::Keyword(word));
+                        }
+                    }
+                    _ => return Err(self.error("unexpected character")),
+                }
+            }
+        }
+
+        Ok(Node { keyword, values })
+    }
+
+    fn word(&mut self) -> String {
+        self.skip_whitespace();
+
+        let start = self.position;
+
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+
+        self.chars[start..self.position].iter().collect()
+    }
+
+    fn text(&mut self) -> Result<String> {
+        let mut text = String::new();
+
+        self.position += 1;
+
+        loop {
+            match self.peek() {
+                Some('"') if self.chars.get(self.position + 1) == Some(&'"') => {
+                    text.push('"');
+                    self.position += 2;
+                }
+                Some('"') => {
+                    self.position += 1;
+                    return Ok(text);
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.position += 1;
+                }
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<f64> {
+        let start = self.position;
+
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' || c == 'e' || c == 'E' {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+
+        let number: String = self.chars[start..self.position].iter().collect();
+
+        number
+            .parse()
+            .map_err(|_| self.error(&format!("invalid number {:?}", number)))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::Crs(format!(
+            "Invalid WKT at character {}: {}",
+            self.position, message
+        ))
+    }
+}
+
+impl Node {
+    fn is(&self, keywords: &[&str]) -> bool {
+        keywords
+            .iter()
+            .any(|keyword| self.keyword.eq_ignore_ascii_case(keyword))
+    }
+
+    fn child(&self, keywords: &[&str]) -> Option<&Node> {
+        self.children().find(|node| node.is(keywords))
+    }
+
+    fn children(&self) -> impl Iterator<Item = &Node> {
+        self.values.iter().filter_map(|value| match value {
+            Value::Node(node) => Some(node),
+            _ => None,
+        })
+    }
+
+    fn name(&self) -> &str {
+        match self.values.first() {
+            Some(Value::Text(name)) => name,
+            _ => "",
+        }
+    }
+
+    fn number(&self, index: usize) -> Option<f64> {
+        self.values
+            .iter()
+            .filter_map(|value| match value {
+                Value::Number(number) => Some(*number),
+                _ => None,
+            })
+            .nth(index)
+    }
+
+    fn numbers(&self) -> Vec<f64> {
+        self.values
+            .iter()
+            .filter_map(|value| match value {
+                Value::Number(number) => Some(*number),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn epsg_code(&self) -> Option<u32> {
+        let id = self.child(&["AUTHORITY", "ID"])?;
+
+        if !id.name().eq_ignore_ascii_case("EPSG") {
+            return None;
+        }
+
+        match id.values.get(1) {
+            Some(Value::Text(code)) => code.parse().ok(),
+            Some(Value::Number(code)) => Some(*code as u32),
+            _ => None,
+        }
+    }
+
+    fn unit_factor(&self, keywords: &[&str]) -> Option<f64> {
+        self.child(keywords).and_then(|unit| unit.number(0))
+    }
+}
+
+fn crs(node: &Node) -> Result<Crs> {
+    if node.is(&["COMPD_CS", "COMPOUNDCRS"]) {
+        let horizontal = node
+            .children()
+            .find(|child| !child.is(&["VERT_CS", "VERTCRS", "VERTICALCRS", "AUTHORITY", "ID"]))
+            .ok_or_else(|| Error::Crs("Compound CRS without a horizontal CRS".to_string()))?;
+
+        let mut crs = crs(horizontal)?;
+
+        if let Some(vertical) = node.child(&["VERT_CS", "VERTCRS", "VERTICALCRS"]) {
+            if let Some(unit) = linear_unit(vertical) {
+                crs.vertical_unit = unit;
+            }
+        }
+
+        return Ok(crs);
+    }
+
+    if node.is(&["BOUNDCRS"]) {
+        let source = node
+            .child(&["SOURCECRS"])
+            .and_then(|source| source.children().next())
+            .ok_or_else(|| Error::Crs("Bound CRS without a source CRS".to_string()))?;
+
+        let mut crs = crs(source)?;
+
+        if let Some(transformation) = node.child(&["ABRIDGEDTRANSFORMATION"]) {
+            crs.datum.to_wgs84 = bound_transformation(transformation);
+        }
+
+        return Ok(crs);
+    }
+
+    let result = if node.is(&["PROJCS", "PROJCRS", "PROJECTEDCRS"]) {
+        projected(node)
+    } else if node.is(&["GEOGCS", "GEOGCRS", "GEOGRAPHICCRS", "BASEGEOGCRS"]) {
+        geographic(node, CrsKind::Geographic)
+    } else if node.is(&["GEOCCS"]) {
+        geographic(node, CrsKind::Geocentric)
+    } else if node.is(&["GEODCRS", "GEODETICCRS", "BASEGEODCRS"]) {
+        let cartesian = node
+            .child(&["CS"])
+            .map(|cs| {
+                matches!(cs.values.first(), Some(Value::Keyword(kind)) if kind.eq_ignore_ascii_case("cartesian"))
+            })
+            .unwrap_or(false);
+
+        geographic(
+            node,
+            if cartesian {
+                CrsKind::Geocentric
+            } else {
+                CrsKind::Geographic
+            },
+        )
+    } else {
+        Err(Error::Crs(format!(
+            "Unsupported WKT CRS type {}",
+            node.keyword
+        )))
+    };
+
+    result.or_else(|e| match node.epsg_code().and_then(epsg::crs) {
+        Some(crs) => Ok(crs),
+        None => Err(e),
+    })
+}
+
+fn geographic(node: &Node, kind: CrsKind) -> Result<Crs> {
+    let datum_node = node
+        .child(&["DATUM", "GEODETICDATUM", "TRF", "ENSEMBLE"])
+        .ok_or_else(|| Error::Crs(format!("{} without a datum", node.keyword)))?;
+
+    let ellipsoid = datum_node
+        .child(&["SPHEROID", "ELLIPSOID"])
+        .ok_or_else(|| {
+            Error::Crs(format!(
+                "Datum {:?} without an ellipsoid",
+                datum_node.name()
+            ))
+        })?;
+
+    let a = ellipsoid.number(0);
+    let inverse_flattening = ellipsoid.number(1);
+
+    let ellipsoid = match (a, inverse_flattening) {
+        (Some(a), Some(inverse_flattening)) => crate::crs::Ellipsoid {
+            a: a * ellipsoid
+                .unit_factor(&["LENGTHUNIT", "UNIT"])
+                .unwrap_or(METRE),
+            inverse_flattening,
+        },
+        _ => epsg::ellipsoid_by_name(ellipsoid.name())
+            .ok_or_else(|| Error::Crs(format!("Unknown ellipsoid {:?}", ellipsoid.name())))?,
+    };
+
+    let to_wgs84 = match datum_node.child(&["TOWGS84"]) {
+        Some(towgs84) => {
+            let p = towgs84.numbers();
+            let p = |i: usize| p.get(i).copied().unwrap_or(0.0);
+
+            Helmert {
+                tx: p(0),
+                ty: p(1),
+                tz: p(2),
+                rx: p(3),
+                ry: p(4),
+                rz: p(5),
+                ds: p(6),
+            }
+        }
+        None => match datum_node
+            .epsg_code()
+            .and_then(epsg::datum)
+            .map(|(_, datum)| datum)
+            .or_else(|| epsg::datum_by_name(datum_node.name()))
+        {
+            Some(datum) => datum.to_wgs84,
+            None => {
+                warn!(
+                    "No WGS84 transformation known for datum {:?}, treating it as WGS84",
+                    datum_node.name()
+                );
+
+                Helmert::default()
+            }
+        },
+    };
+
+    let linear_unit = match kind {
+        CrsKind::Geocentric => linear_unit(node).unwrap_or(METRE),
+        _ => METRE,
+    };
+
+    Ok(Crs {
+        name: node.name().to_string(),
+        kind,
+        datum: Datum::new(ellipsoid, to_wgs84),
+        linear_unit,
+        vertical_unit: linear_unit,
+    })
+}
+
+fn projected(node: &Node) -> Result<Crs> {
+    let base = node
+        .child(&["GEOGCS", "BASEGEOGCRS", "BASEGEODCRS", "GEOGCRS", "GEODCRS"])
+        .ok_or_else(|| Error::Crs(format!("{} without a base CRS", node.keyword)))?;
+
+    let base = geographic(base, CrsKind::Geographic)?;
+
+    let unit = linear_unit(node).unwrap_or(METRE);
+
+    // WKT 1 keeps the method and its parameters on the CRS, WKT 2 nests them in a conversion
+    let (method, parameters) = match node.child(&["CONVERSION"]) {
+        Some(conversion) => (
+            conversion.child(&["METHOD", "PROJECTION"]).map(Node::name),
+            conversion,
+        ),
+        None => (node.child(&["PROJECTION"]).map(Node::name), node),
+    };
+
+    let method = epsg::normalize(
+        method.ok_or_else(|| Error::Crs(format!("{} without a projection", node.keyword)))?,
+    );
+
+    let mut latitude_of_origin = 0.0;
+    let mut central_meridian = 0.0;
+    let mut scale_factor = 1.0;
+    let mut false_easting = 0.0;
+    let mut false_northing = 0.0;
+    let mut standard_parallel_1 = None;
+    let mut standard_parallel_2 = None;
+
+    for parameter in parameters
+        .children()
+        .filter(|child| child.is(&["PARAMETER"]))
+    {
+        let value = match parameter.number(0) {
+            Some(value) => value,
+            None => continue,
+        };
+
+        let angle = || match parameter.unit_factor(&["ANGLEUNIT"]) {
+            Some(factor) => (value * factor).to_degrees(),
+            None => value,
+        };
+
+        let length = || value * parameter.unit_factor(&["LENGTHUNIT"]).unwrap_or(unit);
+
+        match epsg::normalize(parameter.name()).as_str() {
+            "latitudeoforigin"
+            | "latitudeofnaturalorigin"
+            | "latitudeoffalseorigin"
+            | "latitudeofcenter" => latitude_of_origin = angle(),
+            "centralmeridian"
+            | "longitudeoforigin"
+            | "longitudeofnaturalorigin"
+            | "longitudeoffalseorigin"
+            | "longitudeofcenter" => central_meridian = angle(),
+            "scalefactor" | "scalefactoratnaturalorigin" => scale_factor = value,
+            "falseeasting" | "eastingatfalseorigin" => false_easting = length(),
+            "falsenorthing" | "northingatfalseorigin" => false_northing = length(),
+            "standardparallel1" | "latitudeof1ststandardparallel" => {
+                standard_parallel_1 = Some(angle())
+            }
+            "standardparallel2" | "latitudeof2ndstandardparallel" => {
+                standard_parallel_2 = Some(angle())
+            }
+            _ => {}
+        }
+    }
+
+    let projection = if method.contains("transversemercator") && !method.contains("south") {
+        Projection::TransverseMercator {
+            latitude_of_origin,
+            central_meridian,
+            scale_factor,
+            false_easting,
+            false_northing,
+        }
+    } else if method.contains("lambertconformalconic") {
+        let standard_parallel_1 = standard_parallel_1.unwrap_or(latitude_of_origin);
+
+        Projection::LambertConformalConic {
+            latitude_of_origin,
+            central_meridian,
+            standard_parallel_1,
+            standard_parallel_2: standard_parallel_2.unwrap_or(standard_parallel_1),
+            scale_factor,
+            false_easting,
+            false_northing,
+        }
+    } else {
+        return Err(Error::Crs(format!(
+            "Unsupported projection method {:?}",
+            method
+        )));
+    };
+
+    Ok(Crs {
+        name: node.name().to_string(),
+        kind: CrsKind::Projected(projection),
+        linear_unit: unit,
+        vertical_unit: unit,
+        ..base
+    })
+}
+
+fn linear_unit(node: &Node) -> Option<f64> {
+    // WKT 2 puts the unit on the coordinate system axes rather than on the CRS
+    node.unit_factor(&["UNIT", "LENGTHUNIT"]).or_else(|| {
+        node.children()
+            .filter(|child| child.is(&["AXIS"]))
+            .find_map(|axis| axis.unit_factor(&["LENGTHUNIT", "UNIT"]))
+    })
+}
+
+fn bound_transformation(transformation: &Node) -> Helmert {
+    let mut helmert = Helmert::default();
+
+    for parameter in transformation
+        .children()
+        .filter(|child| child.is(&["PARAMETER"]))
+    {
+        let value = parameter.number(0).unwrap_or(0.0);
+
+        match epsg::normalize(parameter.name()).as_str() {
+            "xaxistranslation" => helmert.tx = value,
+            "yaxistranslation" => helmert.ty = value,
+            "zaxistranslation" => helmert.tz = value,
+            "xaxisrotation" => helmert.rx = value,
+            "yaxisrotation" => helmert.ry = value,
+            "zaxisrotation" => helmert.rz = value,
+            "scaledifference" => helmert.ds = value,
+            _ => {}
+        }
+    }
+
+    let method = transformation
+        .child(&["METHOD"])
+        .map(|method| epsg::normalize(method.name()))
+        .unwrap_or_default();
+
+    // coordinate frame rotations have the opposite sign of position vector rotations
+    if method.contains("coordinateframe") {
+        helmert.rx = -helmert.rx;
+        helmert.ry = -helmert.ry;
+        helmert.rz = -helmert.rz;
+    }
+
+    helmert
+}
@@ -0,0 +1,468 @@
+use crate::error::{Error, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::slice;
+
+// globs matched against the path of a file relative to the input directory it was found in,
+// a pattern without a slash matches the file name in any directory
+#[derive(Debug)]
+pub struct InputFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+#[derive(Debug)]
+struct Pattern {
+    segments: Vec<Segment>,
+    file_name_only: bool,
+}
+
+#[derive(Debug)]
+enum Segment {
+    AnyDirectories,
+    Glob(Vec<Token>),
+}
+
+#[derive(Debug)]
+enum Token {
+    Literal(char),
+    AnyChar,
+    AnyString,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl InputFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Self {
+            include: include
+                .iter()
+                .map(|pattern| Pattern::parse(pattern))
+                .collect::<Result<_>>()?,
+            exclude: exclude
+                .iter()
+                .map(|pattern| Pattern::parse(pattern))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    fn accepts(&self, relative_path: &Path) -> bool {
+        let components = relative_path
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().chars().collect()),
+                _ => None,
+            })
+            .collect::<Vec<Vec<char>>>();
+
+        (self.include.is_empty() || self.include.iter().any(|p| p.matches(&components)))
+            && !self.exclude.iter().any(|p| p.matches(&components))
+    }
+}
+
+impl Pattern {
+    fn parse(pattern: &str) -> Result<Self> {
+        let invalid = |reason: &str| {
+            Error::InvalidInput(format!("Invalid glob pattern {:?}: {}", pattern, reason))
+        };
+
+        let trimmed = pattern.trim_start_matches("./").trim_matches('/');
+
+        if trimmed.is_empty() {
+            return Err(invalid("it is empty"));
+        }
+
+        let mut segments = vec![];
+
+        for segment in trimmed.split('/') {
+            if segment == "**" {
+                segments.push(Segment::AnyDirectories);
+                continue;
+            }
+
+            let mut tokens = vec![];
+            let mut chars = segment.chars();
+
+            while let Some(c) = chars.next() {
+                let token = match c {
+                    '*' => Token::AnyString,
+                    '?' => Token::AnyChar,
+                    '[' => {
+                        let mut negated = false;
+                        let mut ranges = vec![];
+                        let mut closed = false;
+                        let mut first = true;
+
+                        while let Some(c) = chars.next() {
+                            if first && (c == '!' || c == '^') {
+                                negated = true;
+                                continue;
+                            }
+
+                            // a closing bracket first in the class is a literal
+                            if c == ']' && !first {
+                                closed = true;
+                                break;
+                            }
+
+                            first = false;
+
+                            let mut lookahead = chars.clone();
+
+                            match (lookahead.next(), lookahead.next()) {
+                                (Some('-'), Some(end)) if end != ']' => {
+                                    chars = lookahead;
+                                    ranges.push((c, end));
+                                }
+                                _ => ranges.push((c, c)),
+                            }
+                        }
+
+                        if !closed {
+                            return Err(invalid("a character class is not closed"));
+                        }
+
+                        Token::Class { negated, ranges }
+                    }
+                    '\\' => Token::Literal(chars.next().unwrap_or('\\')),
+                    c => Token::Literal(c),
+                };
+
+                tokens.push(token);
+            }
+
+            segments.push(Segment::Glob(tokens));
+        }
+
+        Ok(Self {
+            file_name_only: !trimmed.contains('/'),
+            segments,
+        })
+    }
+
+    fn matches(&self, components: &[Vec<char>]) -> bool {
+        if self.file_name_only {
+            return components.last().is_some_and(|file_name| {
+                matches_segments(&self.segments, slice::from_ref(file_name))
+            });
+        }
+
+        matches_segments(&self.segments, components)
+    }
+}
+
+impl Token {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Token::Literal(literal) => *literal == c,
+            Token::AnyChar | Token::AnyString => true,
+            Token::Class { negated, ranges } => {
+                ranges.iter().any(|&(start, end)| start <= c && c <= end) != *negated
+            }
+        }
+    }
+}
+
+fn matches_segments(segments: &[Segment], components: &[Vec<char>]) -> bool {
+    matches_wildcards(
+        segments,
+        components,
+        |segment| matches!(segment, Segment::AnyDirectories),
+        |segment, component| match segment {
+            Segment::AnyDirectories => true,
+            Segment::Glob(tokens) => matches_tokens(tokens, component),
+        },
+    )
+}
+
+fn matches_tokens(tokens: &[Token], name: &[char]) -> bool {
+    matches_wildcards(
+        tokens,
+        name,
+        |token| matches!(token, Token::AnyString),
+        |token, &c| token.matches(c),
+    )
+}
+
+// greedy matching where on a mismatch only the last wildcard takes one more item, a match found
+// by an earlier wildcard taking more is also found by the later one, so this stays quadratic
+fn matches_wildcards<P, T>(
+    pattern: &[P],
+    items: &[T],
+    is_wildcard: impl Fn(&P) -> bool,
+    matches: impl Fn(&P, &T) -> bool,
+) -> bool {
+    let mut position = 0;
+    let mut item = 0;
+    let mut wildcard = None;
+
+    while item < items.len() {
+        match pattern.get(position) {
+            Some(p) if is_wildcard(p) => {
+                wildcard = Some((position, item));
+                position += 1;
+            }
+            Some(p) if matches(p, &items[item]) => {
+                position += 1;
+                item += 1;
+            }
+            _ => match wildcard {
+                Some((wildcard_position, wildcard_item)) => {
+                    wildcard = Some((wildcard_position, wildcard_item + 1));
+                    position = wildcard_position + 1;
+                    item = wildcard_item + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[position..].iter().all(is_wildcard)
+}
+
+// files are taken as given, directories are searched for LAS/LAZ files passing the filter
+pub fn find_las_files(
+    inputs: &[PathBuf],
+    filter: &InputFilter,
+    recursive: bool,
+) -> Result<Vec<PathBuf>> {
+    let mut las_files = vec![];
+    let mut found = HashSet::new();
+
+    for input in inputs {
+        let paths = if input.is_dir() {
+            let mut paths = vec![];
+
+            walk(input, input, filter, recursive, &mut paths)?;
+
+            // sorted so that names given to inputs sharing a stem do not depend on the file system
+            paths.sort();
+
+            paths
+        } else if input.is_file() {
+            vec![input.to_owned()]
+        } else {
+            return Err(Error::InvalidInput(format!(
+                "Input {:?} does not exist",
+                input
+            )));
+        };
+
+        // a file reached through several inputs is tiled once
+        for path in paths {
+            if found.insert(fs::canonicalize(&path)?) {
+                las_files.push(path);
+            }
+        }
+    }
+
+    Ok(las_files)
+}
+
+fn walk(
+    root: &Path,
+    directory: &Path,
+    filter: &InputFilter,
+    recursive: bool,
+    paths: &mut Vec<PathBuf>,
+) -> Result<()> {
+    // a directory that cannot be listed completely fails the search rather than losing inputs
+    let failed = |e: io::Error| Error::Input(directory.to_owned(), Box::new(e.into()));
+
+    for entry in fs::read_dir(directory).map_err(failed)? {
+        let entry = entry.map_err(failed)?;
+        let path = entry.path();
+
+        // symbolic links to directories are not followed, they could form a cycle
+        if entry.file_type().map_err(failed)?.is_dir() {
+            if recursive {
+                walk(root, &path, filter, recursive, paths)?;
+            }
+        } else if path.is_file()
+            && is_las_file(&path)
+            && filter.accepts(path.strip_prefix(root).unwrap_or(&path))
+        {
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+// one path per line, blank lines and lines starting with # are skipped,
+// relative paths are relative to the directory of the list
+pub fn read_input_list(path: &Path) -> Result<Vec<PathBuf>> {
+    let contents =
+        fs::read_to_string(path).map_err(|e| Error::Input(path.to_owned(), Box::new(e.into())))?;
+
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| base_dir.join(line))
+        .collect())
+}
+
+pub fn is_las_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("las") || extension.eq_ignore_ascii_case("laz")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    fn input_filter(include: &[&str], exclude: &[&str]) -> InputFilter {
+        let strings =
+            |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+
+        InputFilter::new(&strings(include), &strings(exclude)).unwrap()
+    }
+
+    fn accepts(filter: &InputFilter, path: &str) -> bool {
+        filter.accepts(Path::new(path))
+    }
+
+    #[test]
+    fn file_name_patterns_match_in_any_directory() {
+        let filter = input_filter(&["*.laz"], &[]);
+
+        assert!(accepts(&filter, "a.laz"));
+        assert!(accepts(&filter, "2021/north/a.laz"));
+        assert!(!accepts(&filter, "a.las"));
+        assert!(!accepts(&filter, "a.laz/b.las"));
+    }
+
+    #[test]
+    fn patterns_with_a_slash_match_the_relative_path() {
+        let filter = input_filter(&["2021/*.laz"], &[]);
+
+        assert!(accepts(&filter, "2021/a.laz"));
+        assert!(!accepts(&filter, "a.laz"));
+        assert!(!accepts(&filter, "2021/north/a.laz"));
+        assert!(!accepts(&filter, "old/2021/a.laz"));
+    }
+
+    #[test]
+    fn any_directories_match_zero_or_more_directories() {
+        let filter = input_filter(&["2021/**/tile_?.la[sz]"], &[]);
+
+        assert!(accepts(&filter, "2021/tile_1.las"));
+        assert!(accepts(&filter, "2021/north/tile_2.laz"));
+        assert!(accepts(&filter, "2021/north/east/tile_3.laz"));
+        assert!(!accepts(&filter, "2022/tile_1.las"));
+        assert!(!accepts(&filter, "2021/north/tile_10.laz"));
+
+        let filter = input_filter(&["**/a.las"], &[]);
+
+        assert!(accepts(&filter, "a.las"));
+        assert!(accepts(&filter, "b/a.las"));
+    }
+
+    #[test]
+    fn character_classes() {
+        let filter = input_filter(&["[!a-c]*.las"], &[]);
+
+        assert!(accepts(&filter, "d.las"));
+        assert!(accepts(&filter, "-.las"));
+        assert!(!accepts(&filter, "a.las"));
+        assert!(!accepts(&filter, "c1.las"));
+
+        let filter = input_filter(&["[]x]-[^0-9].las"], &[]);
+
+        assert!(accepts(&filter, "]-a.las"));
+        assert!(accepts(&filter, "x-b.las"));
+        assert!(!accepts(&filter, "x-1.las"));
+        assert!(!accepts(&filter, "y-b.las"));
+    }
+
+    #[test]
+    fn exclude_overrides_include() {
+        let filter = input_filter(&["*.las", "keep/*"], &["*_draft.las", "keep/old/**"]);
+
+        assert!(accepts(&filter, "a.las"));
+        assert!(accepts(&filter, "keep/b.laz"));
+        assert!(!accepts(&filter, "a_draft.las"));
+        assert!(!accepts(&filter, "keep/a_draft.las"));
+        assert!(!accepts(&filter, "keep/old/a.las"));
+        assert!(!accepts(&filter, "c.laz"));
+    }
+
+    #[test]
+    fn empty_include_accepts_everything_not_excluded() {
+        let filter = input_filter(&[], &["tmp/**"]);
+
+        assert!(accepts(&filter, "a.las"));
+        assert!(!accepts(&filter, "tmp/a.las"));
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        assert!(Pattern::parse("").is_err());
+        assert!(Pattern::parse("/").is_err());
+        assert!(Pattern::parse("[a-z.las").is_err());
+    }
+
+    #[test]
+    fn many_wildcards_do_not_backtrack_exponentially() {
+        let name = format!("{}.las", "a".repeat(200));
+        let directories = format!("{}{}", "d/".repeat(100), name);
+
+        assert!(!accepts(
+            &input_filter(&[&format!("{}b", "*a".repeat(30))], &[]),
+            &name
+        ));
+        assert!(accepts(
+            &input_filter(&[&format!("{}.las", "*a".repeat(30))], &[]),
+            &name
+        ));
+        assert!(!accepts(
+            &input_filter(&[&format!("{}e/*", "**/d/".repeat(30))], &[]),
+            &directories
+        ));
+        assert!(accepts(
+            &input_filter(&[&format!("{}*", "**/d/".repeat(30))], &[]),
+            &directories
+        ));
+    }
+
+    #[test]
+    fn unreadable_directories_fail_with_their_path() {
+        let directory = env::temp_dir().join(format!(
+            "{}-discovery-{}",
+            env!("CARGO_PKG_NAME"),
+            process::id()
+        ));
+        let nested = directory.join("nested");
+        fs::create_dir_all(&nested).unwrap();
+
+        // a file where a directory is expected cannot be listed
+        let file = nested.join("tile.las");
+        fs::write(&file, b"").unwrap();
+
+        let mut paths = vec![];
+        let error = walk(&directory, &file, &input_filter(&[], &[]), true, &mut paths).unwrap_err();
+
+        assert!(
+            matches!(&error, Error::Input(path, _) if *path == file),
+            "{}",
+            error
+        );
+
+        let found =
+            find_las_files(slice::from_ref(&directory), &input_filter(&[], &[]), true).unwrap();
+        assert_eq!(found, [file]);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
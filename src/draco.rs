@@ -0,0 +1,882 @@
+use crate::attributes::{ComponentType, PointAttribute};
+use std::cmp::Reverse;
+use std::convert::TryInto;
+
+// Draco 2.2 point clouds, every attribute encoded sequentially with rANS coded symbols
+const MAGIC: &[u8; 5] = b"DRACO";
+
+const MAJOR_VERSION: u8 = 2;
+
+const MINOR_VERSION: u8 = 2;
+
+const POINT_CLOUD: u8 = 0;
+
+const SEQUENTIAL_ENCODING: u8 = 0;
+
+const INTEGER_ENCODER: u8 = 1;
+
+const QUANTIZATION_ENCODER: u8 = 2;
+
+const PREDICTION_DIFFERENCE: i8 = 0;
+
+const PREDICTION_TRANSFORM_WRAP: i8 = 1;
+
+const SYMBOL_CODING_RAW: u8 = 1;
+
+// raw symbol coding picks the rANS precision from the bit length of the largest symbol
+const MAX_SYMBOL_BIT_LENGTH: u32 = 18;
+
+const ANS_IO_BASE: u32 = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DracoOptions {
+    // quantization of positions and of floating point attributes, colors and integers are lossless
+    pub position_bits: u8,
+    pub generic_bits: u8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DracoAttributeType {
+    Position,
+    Color,
+    Generic,
+}
+
+enum Values {
+    Quantized { values: Vec<f32>, bits: u8 },
+    Integer { data_type: u8, values: Vec<i32> },
+}
+
+struct DracoAttribute {
+    attribute_type: DracoAttributeType,
+    components: usize,
+    normalized: bool,
+    values: Values,
+}
+
+pub struct DracoPointCloud {
+    number_of_points: usize,
+    options: DracoOptions,
+    attributes: Vec<DracoAttribute>,
+}
+
+impl Default for DracoOptions {
+    fn default() -> Self {
+        Self {
+            position_bits: 14,
+            generic_bits: 12,
+        }
+    }
+}
+
+impl DracoAttributeType {
+    fn code(&self) -> u8 {
+        match self {
+            DracoAttributeType::Position => 0,
+            DracoAttributeType::Color => 2,
+            DracoAttributeType::Generic => 4,
+        }
+    }
+}
+
+impl DracoPointCloud {
+    pub fn new(number_of_points: usize, options: DracoOptions) -> Self {
+        Self {
+            number_of_points,
+            options,
+            attributes: vec![],
+        }
+    }
+
+    // returns the unique id of the attribute, which content formats refer to it by
+    pub fn add_positions(&mut self, values: Vec<f32>) -> usize {
+        self.add(DracoAttribute {
+            attribute_type: DracoAttributeType::Position,
+            components: 3,
+            normalized: false,
+            values: Values::Quantized {
+                values,
+                bits: self.options.position_bits,
+            },
+        })
+    }
+
+    pub fn add_colors(&mut self, values: &[u8], components: usize) -> usize {
+        self.add(DracoAttribute {
+            attribute_type: DracoAttributeType::Color,
+            components,
+            normalized: true,
+            values: Values::Integer {
+                data_type: data_type(ComponentType::UnsignedByte),
+                values: values.iter().map(|&value| value as i32).collect(),
+            },
+        })
+    }
+
+    // 32 bit integers and doubles are left out, none is returned for them
+    pub fn add_point_attribute(&mut self, attribute: &PointAttribute) -> Option<usize> {
+        let values = &attribute.values;
+
+        let values = match attribute.component_type {
+            ComponentType::Float => Values::Quantized {
+                values: values
+                    .chunks_exact(4)
+                    .map(|bytes| f32::from_le_bytes(bytes.try_into().expect("4 bytes")))
+                    .collect(),
+                bits: self.options.generic_bits,
+            },
+            ComponentType::UnsignedByte => Values::Integer {
+                data_type: data_type(attribute.component_type),
+                values: values.iter().map(|&value| value as i32).collect(),
+            },
+            ComponentType::Byte => Values::Integer {
+                data_type: data_type(attribute.component_type),
+                values: values.iter().map(|&value| value as i8 as i32).collect(),
+            },
+            ComponentType::UnsignedShort => Values::Integer {
+                data_type: data_type(attribute.component_type),
+                values: values
+                    .chunks_exact(2)
+                    .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as i32)
+                    .collect(),
+            },
+            ComponentType::Short => Values::Integer {
+                data_type: data_type(attribute.component_type),
+                values: values
+                    .chunks_exact(2)
+                    .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as i32)
+                    .collect(),
+            },
+            ComponentType::Int | ComponentType::UnsignedInt | ComponentType::Double => return None,
+        };
+
+        Some(self.add(DracoAttribute {
+            attribute_type: DracoAttributeType::Generic,
+            components: 1,
+            normalized: false,
+            values,
+        }))
+    }
+
+    fn add(&mut self, attribute: DracoAttribute) -> usize {
+        self.attributes.push(attribute);
+        self.attributes.len() - 1
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buffer = MAGIC.to_vec();
+
+        buffer.extend_from_slice(&[
+            MAJOR_VERSION,
+            MINOR_VERSION,
+            POINT_CLOUD,
+            SEQUENTIAL_ENCODING,
+        ]);
+        buffer.extend_from_slice(&0u16.to_le_bytes());
+        buffer.extend_from_slice(&(self.number_of_points as i32).to_le_bytes());
+
+        // one attributes encoder with all attributes in point order
+        buffer.push(1);
+        encode_varint(self.attributes.len() as u64, &mut buffer);
+
+        for (unique_id, attribute) in self.attributes.iter().enumerate() {
+            buffer.push(attribute.attribute_type.code());
+            buffer.push(match &attribute.values {
+                Values::Quantized { .. } => data_type(ComponentType::Float),
+                Values::Integer { data_type, .. } => *data_type,
+            });
+            buffer.push(attribute.components as u8);
+            buffer.push(attribute.normalized as u8);
+            encode_varint(unique_id as u64, &mut buffer);
+        }
+
+        for attribute in &self.attributes {
+            buffer.push(match attribute.values {
+                Values::Quantized { .. } => QUANTIZATION_ENCODER,
+                Values::Integer { .. } => INTEGER_ENCODER,
+            });
+        }
+
+        let quantizations = self
+            .attributes
+            .iter()
+            .map(|attribute| match &attribute.values {
+                Values::Quantized { values, bits } => {
+                    let quantization = Quantization::new(values, attribute.components, *bits);
+
+                    encode_integers(
+                        &quantization.quantize(values, attribute.components),
+                        attribute.components,
+                        &mut buffer,
+                    );
+
+                    Some(quantization)
+                }
+                Values::Integer { values, .. } => {
+                    encode_integers(values, attribute.components, &mut buffer);
+
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // the dequantization parameters follow all attribute values
+        for quantization in quantizations.iter().flatten() {
+            for minimum in &quantization.minimum {
+                buffer.extend_from_slice(&minimum.to_le_bytes());
+            }
+
+            buffer.extend_from_slice(&quantization.range.to_le_bytes());
+            buffer.push(quantization.bits);
+        }
+
+        buffer
+    }
+}
+
+struct Quantization {
+    minimum: Vec<f32>,
+    range: f32,
+    bits: u8,
+}
+
+impl Quantization {
+    // one range for all components, so quantization steps are the same along every axis
+    fn new(values: &[f32], components: usize, bits: u8) -> Self {
+        let mut minimum = vec![f32::MAX; components];
+        let mut maximum = vec![f32::MIN; components];
+
+        for value in values.chunks_exact(components) {
+            for component in 0..components {
+                minimum[component] = minimum[component].min(value[component]);
+                maximum[component] = maximum[component].max(value[component]);
+            }
+        }
+
+        let range = minimum
+            .iter()
+            .zip(&maximum)
+            .map(|(minimum, maximum)| maximum - minimum)
+            .fold(0.0, f32::max);
+
+        Self {
+            minimum,
+            range: if range > 0.0 { range } else { 1.0 },
+            bits,
+        }
+    }
+
+    fn quantize(&self, values: &[f32], components: usize) -> Vec<i32> {
+        let inverse_delta = ((1u32 << self.bits) - 1) as f32 / self.range;
+
+        values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| {
+                ((value - self.minimum[index % components]) * inverse_delta + 0.5).floor() as i32
+            })
+            .collect()
+    }
+}
+
+// differences to the previous point, wrapped into the value range so they stay small
+fn encode_integers(values: &[i32], components: usize, buffer: &mut Vec<u8>) {
+    buffer.push(PREDICTION_DIFFERENCE as u8);
+    buffer.push(PREDICTION_TRANSFORM_WRAP as u8);
+
+    let minimum = values.iter().copied().min().unwrap_or(0);
+    let maximum = values.iter().copied().max().unwrap_or(0);
+
+    let max_difference = 1 + maximum as i64 - minimum as i64;
+    let max_correction = max_difference / 2 - (1 - max_difference % 2);
+    let min_correction = -(max_difference / 2);
+
+    let symbols = values
+        .iter()
+        .enumerate()
+        .map(|(index, &value)| {
+            let predicted = if index >= components {
+                values[index - components]
+            } else {
+                0
+            };
+
+            let mut correction = value as i64 - predicted.clamp(minimum, maximum) as i64;
+
+            if correction < min_correction {
+                correction += max_difference;
+            } else if correction > max_correction {
+                correction -= max_difference;
+            }
+
+            if correction >= 0 {
+                (correction as u32) << 1
+            } else {
+                ((-(correction + 1)) as u32) << 1 | 1
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let max_symbol = symbols.iter().copied().max().unwrap_or(0);
+
+    if 32 - max_symbol.leading_zeros() <= MAX_SYMBOL_BIT_LENGTH {
+        buffer.push(1);
+        encode_symbols(&symbols, max_symbol, buffer);
+    } else {
+        buffer.push(0);
+        buffer.push(4);
+
+        for symbol in &symbols {
+            buffer.extend_from_slice(&symbol.to_le_bytes());
+        }
+    }
+
+    buffer.extend_from_slice(&minimum.to_le_bytes());
+    buffer.extend_from_slice(&maximum.to_le_bytes());
+}
+
+fn encode_symbols(symbols: &[u32], max_symbol: u32, buffer: &mut Vec<u8>) {
+    if symbols.is_empty() {
+        return;
+    }
+
+    let bit_length = (32 - max_symbol.leading_zeros()).max(1);
+    let precision_bits = (3 * bit_length / 2).clamp(12, 20);
+    let precision = 1u32 << precision_bits;
+
+    buffer.push(SYMBOL_CODING_RAW);
+    buffer.push(bit_length as u8);
+
+    let mut frequencies = vec![0u64; max_symbol as usize + 1];
+
+    for &symbol in symbols {
+        frequencies[symbol as usize] += 1;
+    }
+
+    let probabilities = probabilities(&frequencies, symbols.len() as u64, precision);
+
+    encode_varint(probabilities.len() as u64, buffer);
+
+    let mut index = 0;
+
+    while index < probabilities.len() {
+        let probability = probabilities[index];
+
+        if probability == 0 {
+            // a run of up to 64 zero probabilities, the last symbol never has one
+            let mut offset = 0;
+
+            while offset < 63 && probabilities[index + offset + 1] == 0 {
+                offset += 1;
+            }
+
+            buffer.push((offset << 2) as u8 | 3);
+            index += offset + 1;
+            continue;
+        }
+
+        let extra_bytes = if probability >= 1 << 14 {
+            2
+        } else if probability >= 1 << 6 {
+            1
+        } else {
+            0
+        };
+
+        buffer.push((probability << 2) as u8 | extra_bytes as u8);
+
+        for byte in 0..extra_bytes {
+            buffer.push((probability >> (8 * (byte + 1) - 2)) as u8);
+        }
+
+        index += 1;
+    }
+
+    let mut cumulative = Vec::with_capacity(probabilities.len());
+    let mut sum = 0;
+
+    for &probability in &probabilities {
+        cumulative.push(sum);
+        sum += probability;
+    }
+
+    // symbols are written last to first, the decoder reads the bytes back from the end
+    let lower_bound = 4 * precision;
+    let mut state = lower_bound;
+    let mut encoded = vec![];
+
+    for &symbol in symbols.iter().rev() {
+        let probability = probabilities[symbol as usize];
+
+        while state >= lower_bound / precision * ANS_IO_BASE * probability {
+            encoded.push((state % ANS_IO_BASE) as u8);
+            state /= ANS_IO_BASE;
+        }
+
+        state =
+            (state / probability) * precision + state % probability + cumulative[symbol as usize];
+    }
+
+    let state = state - lower_bound;
+
+    if state < 1 << 6 {
+        encoded.push(state as u8);
+    } else if state < 1 << 14 {
+        encoded.extend_from_slice(&((1 << 14) + state as u16).to_le_bytes());
+    } else if state < 1 << 22 {
+        encoded.extend_from_slice(&((2 << 22) + state).to_le_bytes()[..3]);
+    } else {
+        encoded.extend_from_slice(&((3 << 30) + state).to_le_bytes());
+    }
+
+    encode_varint(encoded.len() as u64, buffer);
+    buffer.extend_from_slice(&encoded);
+}
+
+// frequencies scaled to probabilities summing to the precision, every used symbol keeps at least one
+fn probabilities(frequencies: &[u64], total: u64, precision: u32) -> Vec<u32> {
+    let mut probabilities = frequencies
+        .iter()
+        .map(|&frequency| {
+            if frequency == 0 {
+                0
+            } else {
+                ((frequency as f64 / total as f64 * precision as f64).round() as u32).max(1)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut order = (0..probabilities.len())
+        .filter(|&index| probabilities[index] > 0)
+        .collect::<Vec<_>>();
+    order.sort_by_key(|&index| Reverse(probabilities[index]));
+
+    let mut difference = precision as i64 - probabilities.iter().map(|&p| p as i64).sum::<i64>();
+
+    if difference > 0 {
+        probabilities[order[0]] += difference as u32;
+    } else {
+        for &index in &order {
+            let taken = (-difference).min(probabilities[index] as i64 - 1);
+
+            probabilities[index] -= taken as u32;
+            difference += taken;
+
+            if difference == 0 {
+                break;
+            }
+        }
+    }
+
+    probabilities
+}
+
+fn encode_varint(mut value: u64, buffer: &mut Vec<u8>) {
+    while value >= 0x80 {
+        buffer.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+
+    buffer.push(value as u8);
+}
+
+fn data_type(component_type: ComponentType) -> u8 {
+    match component_type {
+        ComponentType::Byte => 1,
+        ComponentType::UnsignedByte => 2,
+        ComponentType::Short => 3,
+        ComponentType::UnsignedShort => 4,
+        ComponentType::Int => 5,
+        ComponentType::UnsignedInt => 6,
+        ComponentType::Float => 9,
+        ComponentType::Double => 10,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a sequential point cloud decoder following the Draco bitstream specification
+    struct Reader<'a> {
+        bytes: &'a [u8],
+        offset: usize,
+    }
+
+    impl<'a> Reader<'a> {
+        fn u8(&mut self) -> u8 {
+            self.offset += 1;
+            self.bytes[self.offset - 1]
+        }
+
+        fn take(&mut self, length: usize) -> &'a [u8] {
+            self.offset += length;
+            &self.bytes[self.offset - length..self.offset]
+        }
+
+        fn u32(&mut self) -> u32 {
+            u32::from_le_bytes(self.take(4).try_into().unwrap())
+        }
+
+        fn varint(&mut self) -> u64 {
+            let mut value = 0;
+            let mut shift = 0;
+
+            loop {
+                let byte = self.u8();
+                value |= ((byte & 0x7f) as u64) << shift;
+                shift += 7;
+
+                if byte & 0x80 == 0 {
+                    return value;
+                }
+            }
+        }
+    }
+
+    struct Decoded {
+        attribute_type: u8,
+        data_type: u8,
+        components: usize,
+        values: Vec<i32>,
+        // minimum of every component and the quantization step of quantized attributes
+        dequantization: Option<(Vec<f32>, f32)>,
+    }
+
+    fn decode_symbols(reader: &mut Reader, count: usize) -> Vec<u32> {
+        assert_eq!(reader.u8(), SYMBOL_CODING_RAW);
+
+        let bit_length = reader.u8() as u32;
+        let precision = 1u32 << (3 * bit_length / 2).clamp(12, 20);
+
+        let number_of_symbols = reader.varint() as usize;
+        let mut probabilities = vec![];
+
+        while probabilities.len() < number_of_symbols {
+            let token = reader.u8();
+
+            if token & 3 == 3 {
+                probabilities.resize(probabilities.len() + (token >> 2) as usize + 1, 0);
+            } else {
+                let mut probability = (token >> 2) as u32;
+
+                for byte in 0..(token & 3) as u32 {
+                    probability |= (reader.u8() as u32) << (8 * (byte + 1) - 2);
+                }
+
+                probabilities.push(probability);
+            }
+        }
+
+        assert_eq!(probabilities.iter().sum::<u32>(), precision);
+
+        let mut cumulative = vec![];
+        let mut lookup = vec![];
+
+        for (symbol, &probability) in probabilities.iter().enumerate() {
+            cumulative.push(lookup.len() as u32);
+            lookup.resize(lookup.len() + probability as usize, symbol as u32);
+        }
+
+        let length = reader.varint() as usize;
+        let encoded = reader.take(length);
+
+        let lower_bound = 4 * precision;
+        let last = length - 1;
+
+        let (mut state, mut offset) = match encoded[last] >> 6 {
+            0 => ((encoded[last] & 0x3f) as u32, last),
+            1 => (
+                (u16::from_le_bytes([encoded[last - 1], encoded[last]]) & 0x3fff) as u32,
+                last - 1,
+            ),
+            2 => {
+                let bytes = [encoded[last - 2], encoded[last - 1], encoded[last], 0];
+                (u32::from_le_bytes(bytes) & 0x3f_ffff, last - 2)
+            }
+            _ => {
+                let bytes = encoded[last - 3..=last].try_into().unwrap();
+                (u32::from_le_bytes(bytes) & 0x3fff_ffff, last - 3)
+            }
+        };
+
+        state += lower_bound;
+
+        (0..count)
+            .map(|_| {
+                while state < lower_bound && offset > 0 {
+                    offset -= 1;
+                    state = state * ANS_IO_BASE + encoded[offset] as u32;
+                }
+
+                let symbol = lookup[(state % precision) as usize];
+                state = state / precision * probabilities[symbol as usize] + state % precision
+                    - cumulative[symbol as usize];
+
+                symbol
+            })
+            .collect()
+    }
+
+    fn decode(bytes: &[u8]) -> (usize, Vec<Decoded>) {
+        let mut reader = Reader { bytes, offset: 0 };
+
+        assert_eq!(reader.take(5), MAGIC);
+        assert_eq!(
+            reader.take(4),
+            [
+                MAJOR_VERSION,
+                MINOR_VERSION,
+                POINT_CLOUD,
+                SEQUENTIAL_ENCODING
+            ]
+        );
+        assert_eq!(reader.take(2), [0, 0]);
+
+        let number_of_points = reader.u32() as usize;
+
+        assert_eq!(reader.u8(), 1);
+
+        let mut attributes = (0..reader.varint())
+            .map(|unique_id| {
+                let attribute_type = reader.u8();
+                let data_type = reader.u8();
+                let components = reader.u8() as usize;
+                reader.u8();
+                assert_eq!(reader.varint(), unique_id);
+
+                Decoded {
+                    attribute_type,
+                    data_type,
+                    components,
+                    values: vec![],
+                    dequantization: None,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let decoders = attributes.iter().map(|_| reader.u8()).collect::<Vec<_>>();
+
+        for attribute in &mut attributes {
+            let count = number_of_points * attribute.components;
+
+            assert_eq!(reader.u8() as i8, PREDICTION_DIFFERENCE);
+            assert_eq!(reader.u8() as i8, PREDICTION_TRANSFORM_WRAP);
+
+            let symbols = if reader.u8() == 1 {
+                decode_symbols(&mut reader, count)
+            } else {
+                assert_eq!(reader.u8(), 4);
+                (0..count).map(|_| reader.u32()).collect()
+            };
+
+            let minimum = reader.u32() as i32;
+            let maximum = reader.u32() as i32;
+            let max_difference = 1 + maximum - minimum;
+
+            for (index, symbol) in symbols.into_iter().enumerate() {
+                let correction = if symbol & 1 == 0 {
+                    (symbol >> 1) as i32
+                } else {
+                    -((symbol >> 1) as i32) - 1
+                };
+
+                let predicted = if index >= attribute.components {
+                    attribute.values[index - attribute.components]
+                } else {
+                    0
+                };
+
+                let mut value = predicted.clamp(minimum, maximum) + correction;
+
+                if value > maximum {
+                    value -= max_difference;
+                } else if value < minimum {
+                    value += max_difference;
+                }
+
+                attribute.values.push(value);
+            }
+        }
+
+        for (attribute, decoder) in attributes.iter_mut().zip(decoders) {
+            if decoder == QUANTIZATION_ENCODER {
+                let minimum = (0..attribute.components)
+                    .map(|_| f32::from_bits(reader.u32()))
+                    .collect();
+                let range = f32::from_bits(reader.u32());
+                let bits = reader.u8();
+
+                attribute.dequantization = Some((minimum, range / ((1u32 << bits) - 1) as f32));
+            }
+        }
+
+        assert_eq!(reader.offset, bytes.len());
+
+        (number_of_points, attributes)
+    }
+
+    fn dequantize(attribute: &Decoded) -> Vec<f32> {
+        let (minimum, delta) = attribute.dequantization.as_ref().unwrap();
+
+        attribute
+            .values
+            .iter()
+            .enumerate()
+            .map(|(index, &value)| minimum[index % attribute.components] + value as f32 * delta)
+            .collect()
+    }
+
+    fn assert_positions(attribute: &Decoded, positions: &[f32]) {
+        let (_, delta) = attribute.dequantization.as_ref().unwrap();
+
+        assert_eq!(
+            attribute.attribute_type,
+            DracoAttributeType::Position.code()
+        );
+
+        for (decoded, position) in dequantize(attribute).iter().zip(positions) {
+            assert!((decoded - position).abs() <= delta / 2.0 + position.abs() * f32::EPSILON);
+        }
+    }
+
+    fn short_attribute(values: &[u16]) -> PointAttribute {
+        PointAttribute {
+            name: "Intensity".to_string(),
+            component_type: ComponentType::UnsignedShort,
+            values: values
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect(),
+            enum_type: None,
+            offset: None,
+            no_data: None,
+            description: None,
+        }
+    }
+
+    // a small generator, so the tests don't depend on a random number crate
+    fn pseudo_random(count: usize) -> Vec<u32> {
+        let mut state = 0x2545_f491u32;
+
+        (0..count)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state
+            })
+            .collect()
+    }
+
+    #[test]
+    fn single_point() {
+        let positions = vec![12.5, -3.25, 100.0];
+
+        let mut point_cloud = DracoPointCloud::new(1, DracoOptions::default());
+        point_cloud.add_positions(positions.clone());
+        point_cloud.add_colors(&[255, 0, 17], 3);
+        point_cloud.add_point_attribute(&short_attribute(&[65535]));
+
+        let (number_of_points, attributes) = decode(&point_cloud.encode());
+
+        assert_eq!(number_of_points, 1);
+        assert_positions(&attributes[0], &positions);
+        assert_eq!(attributes[1].values, [255, 0, 17]);
+        assert_eq!(
+            attributes[1].data_type,
+            data_type(ComponentType::UnsignedByte)
+        );
+        assert_eq!(attributes[2].values, [65535]);
+    }
+
+    #[test]
+    fn constant_values() {
+        let positions = [1.0f32, 2.0, 3.0].repeat(500);
+
+        let mut point_cloud = DracoPointCloud::new(500, DracoOptions::default());
+        point_cloud.add_positions(positions.clone());
+        point_cloud.add_colors(&[7, 7, 7, 255].repeat(500), 4);
+        point_cloud.add_point_attribute(&short_attribute(&[0; 500]));
+
+        let (number_of_points, attributes) = decode(&point_cloud.encode());
+
+        assert_eq!(number_of_points, 500);
+        assert_positions(&attributes[0], &positions);
+        assert_eq!(attributes[1].components, 4);
+        assert_eq!(attributes[1].values, [7, 7, 7, 255].repeat(500));
+        assert_eq!(attributes[2].values, [0; 500]);
+    }
+
+    #[test]
+    fn many_distinct_symbols() {
+        let numbers = pseudo_random(30000);
+
+        let values = numbers
+            .iter()
+            .map(|&number| number as u16)
+            .collect::<Vec<_>>();
+
+        let positions = numbers
+            .chunks_exact(3)
+            .flat_map(|chunk| chunk.iter().map(|&number| (number >> 8) as f32 / 1000.0))
+            .collect::<Vec<_>>();
+
+        let signed = numbers
+            .iter()
+            .map(|&number| (number >> 24) as u8)
+            .collect::<Vec<_>>();
+
+        let mut point_cloud = DracoPointCloud::new(10000, DracoOptions::default());
+        point_cloud.add_positions(positions.clone());
+        point_cloud.add_point_attribute(&short_attribute(&values[..10000]));
+        point_cloud.add_point_attribute(&PointAttribute {
+            component_type: ComponentType::Byte,
+            values: signed[..10000].to_vec(),
+            ..short_attribute(&[])
+        });
+
+        let (_, attributes) = decode(&point_cloud.encode());
+
+        let mut distinct = attributes[1].values.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        assert!(distinct.len() > 1 << 12);
+
+        assert_positions(&attributes[0], &positions);
+        assert_eq!(
+            attributes[1].values,
+            values[..10000]
+                .iter()
+                .map(|&value| value as i32)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            attributes[2].values,
+            signed[..10000]
+                .iter()
+                .map(|&value| value as i8 as i32)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn wide_symbols_are_stored_raw() {
+        let positions = pseudo_random(3000)
+            .into_iter()
+            .map(|number| number as f32)
+            .collect::<Vec<_>>();
+
+        let options = DracoOptions {
+            position_bits: 30,
+            ..DracoOptions::default()
+        };
+
+        let mut point_cloud = DracoPointCloud::new(1000, options);
+        point_cloud.add_positions(positions.clone());
+
+        let (_, attributes) = decode(&point_cloud.encode());
+
+        assert_positions(&attributes[0], &positions);
+    }
+}
@@ -0,0 +1,64 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Las(Box<las::Error>),
+    InvalidBounds(String),
+    InvalidInput(String),
+    Crs(String),
+    Geoid(String),
+    Serialization(serde_json::Error),
+    Input(PathBuf, Box<Error>),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "IO error: {}", e),
+            Error::Las(e) => write!(f, "LAS error: {}", e),
+            Error::InvalidBounds(message) => write!(f, "Invalid bounds: {}", message),
+            Error::InvalidInput(message) => write!(f, "Invalid input: {}", message),
+            Error::Crs(message) => write!(f, "CRS error: {}", message),
+            Error::Geoid(message) => write!(f, "Geoid error: {}", message),
+            Error::Serialization(e) => write!(f, "Serialization error: {}", e),
+            Error::Input(path, e) => write!(f, "Failed to process {:?}: {}", path, e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Las(e) => Some(e.as_ref()),
+            Error::Serialization(e) => Some(e),
+            Error::Input(_, e) => Some(e.as_ref()),
+            Error::InvalidBounds(_) | Error::InvalidInput(_) | Error::Crs(_) | Error::Geoid(_) => {
+                None
+            }
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<las::Error> for Error {
+    fn from(e: las::Error) -> Self {
+        Error::Las(Box::new(e))
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Serialization(e)
+    }
+}
@@ -0,0 +1,523 @@
+use crate::error::Result;
+use crate::quadtree::Point;
+use log::debug;
+use rayon::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// morton key, coordinates, colors, intensity, classification, the flags packed in one byte,
+// returns, scan angle, GPS time, point source id and user data, followed by the extra bytes
+const RECORD_SIZE: usize = 59;
+
+const BUFFER_SIZE: usize = 1 << 16;
+
+static SPILL_DIRECTORIES: AtomicUsize = AtomicUsize::new(0);
+
+// temporary directory for the spilled points of one input, removed with everything in it when dropped
+pub struct SpillDirectory {
+    path: PathBuf,
+    files: AtomicUsize,
+    extra_bytes: usize,
+}
+
+pub struct SpillWriter {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    count: usize,
+    extra_bytes: usize,
+}
+
+pub struct SpillFile {
+    path: PathBuf,
+    count: usize,
+    extra_bytes: usize,
+}
+
+pub struct SpillReader {
+    reader: BufReader<File>,
+    remaining: usize,
+    record: Vec<u8>,
+}
+
+// k-way merge of sorted runs, yielding the points in morton key order
+pub struct Merge {
+    readers: Vec<SpillReader>,
+    heads: Vec<Option<Point>>,
+    heap: BinaryHeap<Reverse<(u64, usize)>>,
+}
+
+impl SpillDirectory {
+    // every spilled point has `extra_bytes` extra bytes
+    pub fn new(parent: &Path, extra_bytes: usize) -> Result<Self> {
+        let path = parent.join(format!(
+            "{}-{}-{}",
+            env!("CARGO_PKG_NAME"),
+            process::id(),
+            SPILL_DIRECTORIES.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        fs::create_dir_all(&path)?;
+
+        Ok(Self {
+            path,
+            files: AtomicUsize::new(0),
+            extra_bytes,
+        })
+    }
+
+    // safe to call from several threads, every writer gets its own file
+    pub fn writer(&self) -> Result<SpillWriter> {
+        let path = self.path.join(format!(
+            "{}.points",
+            self.files.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        Ok(SpillWriter {
+            writer: BufWriter::with_capacity(BUFFER_SIZE, File::create(&path)?),
+            path,
+            count: 0,
+            extra_bytes: self.extra_bytes,
+        })
+    }
+}
+
+impl Drop for SpillDirectory {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.path) {
+            debug!("Failed to remove {:?}: {}", self.path, e);
+        }
+    }
+}
+
+impl SpillWriter {
+    pub fn push(&mut self, point: &Point) -> Result<()> {
+        debug_assert_eq!(point.extra_bytes.len(), self.extra_bytes);
+
+        self.writer.write_all(&encode(point))?;
+        self.writer.write_all(&point.extra_bytes)?;
+        self.count += 1;
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<SpillFile> {
+        self.writer.flush()?;
+
+        Ok(SpillFile {
+            path: self.path,
+            count: self.count,
+            extra_bytes: self.extra_bytes,
+        })
+    }
+}
+
+impl SpillFile {
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn reader(&self, buffer_size: usize) -> Result<SpillReader> {
+        Ok(SpillReader {
+            reader: BufReader::with_capacity(buffer_size, File::open(&self.path)?),
+            remaining: self.count,
+            record: vec![0; RECORD_SIZE + self.extra_bytes],
+        })
+    }
+
+    fn remove(self) -> Result<()> {
+        Ok(fs::remove_file(self.path)?)
+    }
+}
+
+impl Iterator for SpillReader {
+    type Item = Result<Point>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+
+        Some(
+            self.reader
+                .read_exact(&mut self.record)
+                .map(|_| decode(&self.record))
+                .map_err(Into::into),
+        )
+    }
+}
+
+impl Merge {
+    fn new(mut readers: Vec<SpillReader>) -> Result<Self> {
+        let mut heads = Vec::with_capacity(readers.len());
+        let mut heap = BinaryHeap::with_capacity(readers.len());
+
+        for (index, reader) in readers.iter_mut().enumerate() {
+            let head = reader.next().transpose()?;
+
+            if let Some(point) = &head {
+                heap.push(Reverse((point.morton, index)));
+            }
+
+            heads.push(head);
+        }
+
+        Ok(Self {
+            readers,
+            heads,
+            heap,
+        })
+    }
+}
+
+impl Iterator for Merge {
+    type Item = Result<Point>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((_, index)) = self.heap.pop()?;
+
+        let next = match self.readers[index].next().transpose() {
+            Ok(next) => next,
+            Err(e) => return Some(Err(e)),
+        };
+
+        if let Some(point) = &next {
+            self.heap.push(Reverse((point.morton, index)));
+        }
+
+        std::mem::replace(&mut self.heads[index], next).map(Ok)
+    }
+}
+
+// sorts by the morton field, `key` assigns it, in runs of `run_length` points merged at most `fan_in` at a time;
+// points with equal keys stay in input order, runs are merged in order and ties go to the earlier run
+pub fn sort<F>(
+    directory: &SpillDirectory,
+    inputs: Vec<SpillFile>,
+    run_length: usize,
+    fan_in: usize,
+    key: F,
+) -> Result<Merge>
+where
+    F: Fn(&Point) -> u64 + Sync,
+{
+    let run_length = run_length.max(1);
+    let fan_in = fan_in.max(2);
+
+    let mut runs = vec![];
+
+    for input in inputs {
+        write_runs(directory, &input, run_length, &key, &mut runs)?;
+
+        input.remove()?;
+    }
+
+    debug!("Sorted {} runs of up to {} points", runs.len(), run_length);
+
+    // more runs than open files fit in the budget are merged in passes
+    while runs.len() > fan_in {
+        let mut merged = vec![];
+
+        for group in runs.chunks(fan_in) {
+            let readers = group
+                .iter()
+                .map(|run| run.reader(BUFFER_SIZE))
+                .collect::<Result<Vec<_>>>()?;
+
+            let mut writer = directory.writer()?;
+
+            for point in Merge::new(readers)? {
+                writer.push(&point?)?;
+            }
+
+            merged.push(writer.finish()?);
+        }
+
+        for run in runs {
+            run.remove()?;
+        }
+
+        debug!("Merged into {} runs", merged.len());
+
+        runs = merged;
+    }
+
+    let readers = runs
+        .iter()
+        .map(|run| run.reader(BUFFER_SIZE))
+        .collect::<Result<Vec<_>>>()?;
+
+    Merge::new(readers)
+}
+
+fn write_runs<F>(
+    directory: &SpillDirectory,
+    input: &SpillFile,
+    run_length: usize,
+    key: &F,
+    runs: &mut Vec<SpillFile>,
+) -> Result<()>
+where
+    F: Fn(&Point) -> u64 + Sync,
+{
+    let mut reader = input.reader(BUFFER_SIZE)?;
+
+    loop {
+        let mut points = reader
+            .by_ref()
+            .take(run_length)
+            .collect::<Result<Vec<_>>>()?;
+
+        if points.is_empty() {
+            return Ok(());
+        }
+
+        points
+            .par_iter_mut()
+            .for_each(|point| point.morton = key(point));
+
+        // stable like the in-memory sort, so both sample the same points among equal keys
+        points.par_sort_by_key(|point| point.morton);
+
+        let mut writer = directory.writer()?;
+
+        for point in &points {
+            writer.push(point)?;
+        }
+
+        runs.push(writer.finish()?);
+    }
+}
+
+// the largest number of runs whose read buffers fit in the memory budget
+pub fn fan_in(memory_budget: usize) -> usize {
+    memory_budget / BUFFER_SIZE
+}
+
+fn encode(point: &Point) -> [u8; RECORD_SIZE] {
+    let mut record = [0u8; RECORD_SIZE];
+
+    record[0..8].copy_from_slice(&point.morton.to_le_bytes());
+    record[8..16].copy_from_slice(&point.x.to_le_bytes());
+    record[16..24].copy_from_slice(&point.y.to_le_bytes());
+    record[24..32].copy_from_slice(&point.z.to_le_bytes());
+    record[32..34].copy_from_slice(&point.r.to_le_bytes());
+    record[34..36].copy_from_slice(&point.g.to_le_bytes());
+    record[36..38].copy_from_slice(&point.b.to_le_bytes());
+    record[38..40].copy_from_slice(&point.intensity.to_le_bytes());
+    record[40] = point.classification;
+    record[41] = point.is_edge_of_flight_line as u8
+        | (point.is_synthetic as u8) << 1
+        | (point.is_key_point as u8) << 2
+        | (point.is_withheld as u8) << 3
+        | (point.is_overlap as u8) << 4
+        | (point.scan_direction_flag as u8) << 5
+        | (point.has_color as u8) << 6;
+    record[42] = point.return_number;
+    record[43] = point.number_of_returns;
+    record[44..48].copy_from_slice(&point.scan_angle.to_le_bytes());
+    record[48..56].copy_from_slice(&point.gps_time.to_le_bytes());
+    record[56..58].copy_from_slice(&point.point_source_id.to_le_bytes());
+    record[58] = point.user_data;
+
+    record
+}
+
+fn decode(record: &[u8]) -> Point {
+    let u64_at = |offset: usize| {
+        let mut buffer = [0u8; 8];
+        buffer.copy_from_slice(&record[offset..offset + 8]);
+        u64::from_le_bytes(buffer)
+    };
+
+    let u16_at = |offset: usize| u16::from_le_bytes([record[offset], record[offset + 1]]);
+
+    Point {
+        morton: u64_at(0),
+        x: f64::from_bits(u64_at(8)),
+        y: f64::from_bits(u64_at(16)),
+        z: f64::from_bits(u64_at(24)),
+        r: u16_at(32),
+        g: u16_at(34),
+        b: u16_at(36),
+        has_color: record[41] & 1 << 6 != 0,
+        intensity: u16_at(38),
+        classification: record[40],
+        is_edge_of_flight_line: record[41] & 1 != 0,
+        is_synthetic: record[41] & 1 << 1 != 0,
+        is_key_point: record[41] & 1 << 2 != 0,
+        is_withheld: record[41] & 1 << 3 != 0,
+        is_overlap: record[41] & 1 << 4 != 0,
+        return_number: record[42],
+        number_of_returns: record[43],
+        scan_angle: f32::from_le_bytes([record[44], record[45], record[46], record[47]]),
+        scan_direction_flag: record[41] & 1 << 5 != 0,
+        gps_time: f64::from_bits(u64_at(48)),
+        point_source_id: u16_at(56),
+        user_data: record[58],
+        extra_bytes: record[RECORD_SIZE..].to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn point(id: u16, extra_bytes: Vec<u8>) -> Point {
+        Point {
+            r: id,
+            g: id.wrapping_mul(7),
+            b: u16::MAX - id,
+            has_color: id & 1 == 0,
+            intensity: id.wrapping_mul(13),
+            classification: id as u8,
+            is_edge_of_flight_line: id & 1 != 0,
+            is_synthetic: id & 2 != 0,
+            is_key_point: id & 4 != 0,
+            is_withheld: id & 8 != 0,
+            is_overlap: id & 16 != 0,
+            return_number: (id % 5) as u8,
+            number_of_returns: 5,
+            scan_angle: id as f32 - 90.5,
+            scan_direction_flag: id & 32 != 0,
+            gps_time: 3e8 + id as f64 / 7.0,
+            point_source_id: id,
+            user_data: (id >> 8) as u8,
+            extra_bytes,
+            ..Point::at(id as f64 * 0.1, -(id as f64) / 3.0, 1e6 + id as f64)
+        }
+    }
+
+    // the same point, bit for bit
+    fn assert_same(point: &Point, expected: &Point) {
+        assert_eq!(format!("{:?}", point), format!("{:?}", expected));
+    }
+
+    #[test]
+    fn records_round_trip() {
+        for id in [0, 1, 42, 63, 1000, u16::MAX] {
+            let mut expected = point(id, vec![]);
+            expected.morton = u64::MAX - id as u64;
+
+            let record = encode(&expected);
+
+            assert_eq!(record.len(), 59);
+            assert_same(&decode(&record), &expected);
+        }
+    }
+
+    #[test]
+    fn spill_files_keep_the_extra_bytes() {
+        let directory = SpillDirectory::new(&env::temp_dir(), 3).unwrap();
+
+        let points = (0..100)
+            .map(|id| point(id, vec![id as u8, 0xaa, (id >> 8) as u8]))
+            .collect::<Vec<_>>();
+
+        let mut writer = directory.writer().unwrap();
+
+        for point in &points {
+            writer.push(point).unwrap();
+        }
+
+        let file = writer.finish().unwrap();
+
+        assert_eq!(file.len(), 100);
+
+        let read = file
+            .reader(BUFFER_SIZE)
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(read.len(), points.len());
+
+        for (point, expected) in read.iter().zip(&points) {
+            assert_same(point, expected);
+        }
+    }
+
+    #[test]
+    fn merges_in_several_passes() {
+        let directory = SpillDirectory::new(&env::temp_dir(), 1).unwrap();
+        let key = |point: &Point| (point.point_source_id as u64 * 7919) % 1009;
+
+        let mut inputs = vec![];
+
+        for input in 0..3 {
+            let mut writer = directory.writer().unwrap();
+
+            for id in (0..1000).filter(|id| id % 3 == input) {
+                writer.push(&point(id, vec![id as u8])).unwrap();
+            }
+
+            inputs.push(writer.finish().unwrap());
+        }
+
+        // 102 runs of up to 10 points, merged 3 at a time in 4 passes before the final merge
+        let merged = sort(&directory, inputs, 10, 3, key)
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(merged.len(), 1000);
+        assert!(merged
+            .windows(2)
+            .all(|pair| pair[0].morton <= pair[1].morton));
+
+        let mut ids = merged
+            .iter()
+            .map(|point| point.point_source_id)
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+
+        assert!(ids.iter().copied().eq(0..1000));
+
+        for merged in &merged {
+            let mut expected = point(merged.point_source_id, vec![merged.point_source_id as u8]);
+            expected.morton = key(&expected);
+
+            assert_same(merged, &expected);
+        }
+    }
+
+    #[test]
+    fn equal_keys_keep_the_input_order() {
+        let directory = SpillDirectory::new(&env::temp_dir(), 1).unwrap();
+        let key = |point: &Point| (point.point_source_id % 4) as u64;
+
+        let mut inputs = vec![];
+
+        for input in 0..2 {
+            let mut writer = directory.writer().unwrap();
+
+            for id in input * 500..(input + 1) * 500 {
+                writer.push(&point(id, vec![0])).unwrap();
+            }
+
+            inputs.push(writer.finish().unwrap());
+        }
+
+        // runs of 50 points with many equal keys, merged 2 at a time over several passes
+        let ids = sort(&directory, inputs, 50, 2, key)
+            .unwrap()
+            .map(|point| point.unwrap().point_source_id)
+            .collect::<Vec<_>>();
+
+        let mut expected = (0..1000).collect::<Vec<_>>();
+        expected.sort_by_key(|id| id % 4);
+
+        assert_eq!(ids, expected);
+    }
+}
@@ -0,0 +1,773 @@
+use crate::attributes::{ComponentType, PointAttribute};
+use crate::quadtree::Point;
+use crate::tiles::ContentFormat;
+use las::{Header, Vlr};
+use log::warn;
+use std::convert::TryInto;
+use std::mem;
+
+const USER_ID: &str = "LASF_Spec";
+
+const RECORD_ID: u16 = 4;
+
+const DESCRIPTOR_SIZE: usize = 192;
+
+// fixed point record fields also exported as attributes, extra dimensions cannot take their names
+const RECORD_FIELD_NAMES: [&str; 16] = [
+    "Classification",
+    "Intensity",
+    "KeyPoint",
+    "EdgeOfFlightLine",
+    "Overlap",
+    "Withheld",
+    "Synthetic",
+    "ReturnNumber",
+    "NumberOfReturns",
+    "ScanAngle",
+    "ScanDirectionFlag",
+    "GpsTime",
+    "PointSourceId",
+    "UserData",
+    "Position",
+    "Color",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtraBytesType {
+    UnsignedChar,
+    Char,
+    UnsignedShort,
+    Short,
+    UnsignedLong,
+    Long,
+    UnsignedLongLong,
+    LongLong,
+    Float,
+    Double,
+}
+
+// a dimension described in the Extra Bytes VLR, no data is a raw value like the stored ones
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtraDimension {
+    pub name: String,
+    pub description: String,
+    pub data_type: ExtraBytesType,
+    pub no_data: Option<f64>,
+    pub scale: Option<f64>,
+    pub offset: Option<f64>,
+}
+
+#[derive(Clone, Debug)]
+struct ExtraField {
+    dimension: ExtraDimension,
+    position: usize,
+    target: usize,
+}
+
+// the extra dimensions of one input and where they are in the extra bytes of its point records
+#[derive(Clone, Debug, Default)]
+pub struct ExtraBytesLayout {
+    fields: Vec<ExtraField>,
+}
+
+// the extra dimensions of a job, stored one after the other in the extra bytes of every point
+#[derive(Clone, Debug, Default)]
+pub struct ExtraBytesSchema {
+    pub dimensions: Vec<ExtraDimension>,
+    // the names of the dimensions in the inputs, before they are made valid property names
+    source_names: Vec<String>,
+    positions: Vec<usize>,
+    defaults: Vec<u8>,
+}
+
+impl ExtraBytesType {
+    fn from_code(code: u8) -> Option<Self> {
+        match code {
+            1 => Some(ExtraBytesType::UnsignedChar),
+            2 => Some(ExtraBytesType::Char),
+            3 => Some(ExtraBytesType::UnsignedShort),
+            4 => Some(ExtraBytesType::Short),
+            5 => Some(ExtraBytesType::UnsignedLong),
+            6 => Some(ExtraBytesType::Long),
+            7 => Some(ExtraBytesType::UnsignedLongLong),
+            8 => Some(ExtraBytesType::LongLong),
+            9 => Some(ExtraBytesType::Float),
+            10 => Some(ExtraBytesType::Double),
+            _ => None,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            ExtraBytesType::UnsignedChar | ExtraBytesType::Char => 1,
+            ExtraBytesType::UnsignedShort | ExtraBytesType::Short => 2,
+            ExtraBytesType::UnsignedLong | ExtraBytesType::Long | ExtraBytesType::Float => 4,
+            ExtraBytesType::UnsignedLongLong
+            | ExtraBytesType::LongLong
+            | ExtraBytesType::Double => 8,
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> f64 {
+        match self {
+            ExtraBytesType::UnsignedChar => bytes[0] as f64,
+            ExtraBytesType::Char => bytes[0] as i8 as f64,
+            ExtraBytesType::UnsignedShort => u16::from_le_bytes(array(bytes)) as f64,
+            ExtraBytesType::Short => i16::from_le_bytes(array(bytes)) as f64,
+            ExtraBytesType::UnsignedLong => u32::from_le_bytes(array(bytes)) as f64,
+            ExtraBytesType::Long => i32::from_le_bytes(array(bytes)) as f64,
+            ExtraBytesType::UnsignedLongLong => u64::from_le_bytes(array(bytes)) as f64,
+            ExtraBytesType::LongLong => i64::from_le_bytes(array(bytes)) as f64,
+            ExtraBytesType::Float => f32::from_le_bytes(array(bytes)) as f64,
+            ExtraBytesType::Double => f64::from_le_bytes(array(bytes)),
+        }
+    }
+
+    // integers are rounded and saturate at the limits of the type
+    fn encode(&self, value: f64, bytes: &mut [u8]) {
+        match self {
+            ExtraBytesType::UnsignedChar => bytes[0] = value.round() as u8,
+            ExtraBytesType::Char => bytes[0] = value.round() as i8 as u8,
+            ExtraBytesType::UnsignedShort => {
+                bytes.copy_from_slice(&(value.round() as u16).to_le_bytes())
+            }
+            ExtraBytesType::Short => bytes.copy_from_slice(&(value.round() as i16).to_le_bytes()),
+            ExtraBytesType::UnsignedLong => {
+                bytes.copy_from_slice(&(value.round() as u32).to_le_bytes())
+            }
+            ExtraBytesType::Long => bytes.copy_from_slice(&(value.round() as i32).to_le_bytes()),
+            ExtraBytesType::UnsignedLongLong => {
+                bytes.copy_from_slice(&(value.round() as u64).to_le_bytes())
+            }
+            ExtraBytesType::LongLong => {
+                bytes.copy_from_slice(&(value.round() as i64).to_le_bytes())
+            }
+            ExtraBytesType::Float => bytes.copy_from_slice(&(value as f32).to_le_bytes()),
+            ExtraBytesType::Double => bytes.copy_from_slice(&value.to_le_bytes()),
+        }
+    }
+
+    // the 8 byte no data, min and max fields of a descriptor hold the widest type of the same kind
+    fn decode_widest(&self, bytes: &[u8]) -> f64 {
+        match self {
+            ExtraBytesType::UnsignedChar
+            | ExtraBytesType::UnsignedShort
+            | ExtraBytesType::UnsignedLong
+            | ExtraBytesType::UnsignedLongLong => u64::from_le_bytes(array(bytes)) as f64,
+            ExtraBytesType::Char
+            | ExtraBytesType::Short
+            | ExtraBytesType::Long
+            | ExtraBytesType::LongLong => i64::from_le_bytes(array(bytes)) as f64,
+            ExtraBytesType::Float | ExtraBytesType::Double => f64::from_le_bytes(array(bytes)),
+        }
+    }
+}
+
+impl ExtraDimension {
+    fn is_scaled(&self) -> bool {
+        self.scale.is_some() || self.offset.is_some()
+    }
+
+    fn value(&self, raw: f64) -> f64 {
+        raw * self.scale.unwrap_or(1.0) + self.offset.unwrap_or(0.0)
+    }
+
+    fn raw(&self, value: f64) -> f64 {
+        (value - self.offset.unwrap_or(0.0)) / self.scale.unwrap_or(1.0)
+    }
+
+    fn same_encoding(&self, other: &ExtraDimension) -> bool {
+        self.data_type == other.data_type
+            && self.scale == other.scale
+            && self.offset == other.offset
+    }
+
+    // scaled values are exported as floats, glTF vertex attributes are at most 16 bit integers or
+    // floats and batch tables have no 64 bit integers
+    fn component_type(&self, content_format: ContentFormat) -> ComponentType {
+        if self.is_scaled() {
+            return ComponentType::Float;
+        }
+
+        match (self.data_type, content_format) {
+            (ExtraBytesType::UnsignedChar, _) => ComponentType::UnsignedByte,
+            (ExtraBytesType::Char, _) => ComponentType::Byte,
+            (ExtraBytesType::UnsignedShort, _) => ComponentType::UnsignedShort,
+            (ExtraBytesType::Short, _) => ComponentType::Short,
+            (ExtraBytesType::Float, _) => ComponentType::Float,
+            (_, ContentFormat::Glb) => ComponentType::Float,
+            (ExtraBytesType::UnsignedLong, _) => ComponentType::UnsignedInt,
+            (ExtraBytesType::Long, _) => ComponentType::Int,
+            _ => ComponentType::Double,
+        }
+    }
+
+    // 32 and 64 bit values exported as 32 bit floats lose the digits beyond their 24 bit mantissa
+    fn is_narrowed(&self, content_format: ContentFormat) -> bool {
+        self.component_type(content_format) == ComponentType::Float
+            && self.data_type != ExtraBytesType::Float
+            && self.data_type.size() >= 4
+    }
+
+    // whether the stored bytes can be exported as they are
+    fn is_native(&self, component_type: ComponentType) -> bool {
+        if self.is_scaled() {
+            return false;
+        }
+
+        matches!(
+            (self.data_type, component_type),
+            (ExtraBytesType::UnsignedChar, ComponentType::UnsignedByte)
+                | (ExtraBytesType::Char, ComponentType::Byte)
+                | (ExtraBytesType::UnsignedShort, ComponentType::UnsignedShort)
+                | (ExtraBytesType::Short, ComponentType::Short)
+                | (ExtraBytesType::UnsignedLong, ComponentType::UnsignedInt)
+                | (ExtraBytesType::Long, ComponentType::Int)
+                | (ExtraBytesType::Float, ComponentType::Float)
+                | (ExtraBytesType::Double, ComponentType::Double)
+        )
+    }
+}
+
+impl ExtraBytesLayout {
+    pub fn from_header(header: &Header) -> Self {
+        header
+            .vlrs()
+            .iter()
+            .chain(header.evlrs().iter())
+            .find(|vlr| is_extra_bytes(vlr))
+            .map(|vlr| Self::from_descriptors(&vlr.data))
+            .unwrap_or_default()
+    }
+
+    fn from_descriptors(descriptors: &[u8]) -> Self {
+        let mut layout = Self::default();
+
+        let mut position = 0;
+
+        for descriptor in descriptors.chunks_exact(DESCRIPTOR_SIZE) {
+            let code = descriptor[2];
+            let options = descriptor[3];
+            let name = c_string(&descriptor[4..36]);
+
+            let data_type = match ExtraBytesType::from_code(code) {
+                Some(data_type) => data_type,
+                // undocumented extra bytes have their size in the options
+                None if code == 0 => {
+                    position += options as usize;
+                    continue;
+                }
+                // deprecated arrays of two or three values
+                None if (11..=30).contains(&code) => {
+                    let data_type = ExtraBytesType::from_code((code - 1) % 10 + 1)
+                        .expect("array of a valid data type");
+
+                    warn!("Skipping extra bytes array {:?}", name);
+
+                    position += data_type.size() * ((code as usize - 1) / 10 + 1);
+                    continue;
+                }
+                None => {
+                    warn!(
+                        "Skipping extra bytes from {:?} on, its data type {} is unknown",
+                        name, code
+                    );
+
+                    break;
+                }
+            };
+
+            let field = |offset: usize| f64::from_le_bytes(array(&descriptor[offset..]));
+
+            let dimension = ExtraDimension {
+                name,
+                description: c_string(&descriptor[160..192]),
+                data_type,
+                no_data: (options & 1 != 0).then(|| data_type.decode_widest(&descriptor[40..])),
+                scale: (options & 1 << 3 != 0).then(|| field(112)),
+                offset: (options & 1 << 4 != 0).then(|| field(136)),
+            };
+
+            layout.fields.push(ExtraField {
+                dimension,
+                position,
+                target: 0,
+            });
+
+            position += data_type.size();
+        }
+
+        layout
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+impl ExtraBytesSchema {
+    pub fn record_size(&self) -> usize {
+        self.defaults.len()
+    }
+
+    // memory a point takes with its extra bytes, which live in a heap block of at least 16 bytes
+    pub fn point_size(&self) -> usize {
+        match self.record_size() {
+            0 => mem::size_of::<Point>(),
+            record_size => mem::size_of::<Point>() + record_size.div_ceil(16) * 16,
+        }
+    }
+
+    pub fn narrowed(
+        &self,
+        content_format: ContentFormat,
+    ) -> impl Iterator<Item = &ExtraDimension> + '_ {
+        self.dimensions
+            .iter()
+            .filter(move |dimension| dimension.is_narrowed(content_format))
+    }
+
+    // the dimensions of an input are matched to the ones of the job by their name in the inputs, each
+    // one at most once, the others are added
+    pub fn add(&mut self, layout: &mut ExtraBytesLayout) {
+        let mut targets = vec![];
+
+        for field in &mut layout.fields {
+            let matched = (0..self.source_names.len()).find(|index| {
+                self.source_names[*index] == field.dimension.name && !targets.contains(index)
+            });
+
+            field.target = match matched {
+                Some(index) => {
+                    let name = &self.dimensions[index].name;
+
+                    if !self.dimensions[index].same_encoding(&field.dimension) {
+                        warn!(
+                            "Extra dimension {:?} is stored differently across inputs, converting its values",
+                            name
+                        );
+                    }
+
+                    index
+                }
+                None => {
+                    let name = self.unique_name(&field.dimension.name);
+                    let position = self.defaults.len();
+                    let size = field.dimension.data_type.size();
+
+                    self.defaults.resize(position + size, 0);
+
+                    if let Some(no_data) = field.dimension.no_data {
+                        field
+                            .dimension
+                            .data_type
+                            .encode(no_data, &mut self.defaults[position..position + size]);
+                    }
+
+                    self.positions.push(position);
+                    self.source_names.push(field.dimension.name.clone());
+                    self.dimensions.push(ExtraDimension {
+                        name,
+                        ..field.dimension.clone()
+                    });
+
+                    self.dimensions.len() - 1
+                }
+            };
+
+            targets.push(field.target);
+        }
+    }
+
+    // distinct dimensions whose names become the same property name are numbered from the second on
+    fn unique_name(&self, source_name: &str) -> String {
+        let name = property_name(source_name);
+        let taken = |name: &str| self.dimensions.iter().any(|d| d.name == name);
+
+        if !taken(&name) {
+            return name;
+        }
+
+        let unique = (2..)
+            .map(|suffix| format!("{}_{}", name, suffix))
+            .find(|name| !taken(name))
+            .expect("a free suffix");
+
+        warn!(
+            "Extra dimension {:?} is exported as {:?}, {:?} is taken by another dimension",
+            source_name, unique, name
+        );
+
+        unique
+    }
+
+    // the extra bytes of a point record in the layout of the job, dimensions an input lacks are no data
+    pub fn convert(&self, layout: &ExtraBytesLayout, extra_bytes: &[u8]) -> Vec<u8> {
+        if self.defaults.is_empty() {
+            return vec![];
+        }
+
+        let mut record = self.defaults.clone();
+
+        for field in &layout.fields {
+            let source_size = field.dimension.data_type.size();
+
+            let source = match extra_bytes.get(field.position..field.position + source_size) {
+                Some(source) => source,
+                None => continue,
+            };
+
+            let target = &self.dimensions[field.target];
+            let position = self.positions[field.target];
+            let destination = &mut record[position..position + target.data_type.size()];
+
+            if target.same_encoding(&field.dimension) {
+                destination.copy_from_slice(source);
+            } else {
+                let value = field
+                    .dimension
+                    .value(field.dimension.data_type.decode(source));
+
+                target.data_type.encode(target.raw(value), destination);
+            }
+        }
+
+        record
+    }
+
+    pub fn attributes(
+        &self,
+        points: &[Point],
+        content_format: ContentFormat,
+    ) -> Vec<PointAttribute> {
+        let mut attributes = vec![];
+
+        for (dimension, &position) in self.dimensions.iter().zip(&self.positions) {
+            let size = dimension.data_type.size();
+            let component_type = dimension.component_type(content_format);
+            let native = dimension.is_native(component_type);
+
+            let mut attribute =
+                PointAttribute::new(dimension.name.clone(), component_type, points.len());
+
+            attribute.no_data = match dimension.no_data {
+                Some(no_data) if native => Some(no_data),
+                Some(no_data) => Some(dimension.value(no_data)),
+                None => None,
+            };
+
+            if !dimension.description.is_empty() {
+                attribute.description = Some(dimension.description.clone());
+            }
+
+            for point in points {
+                let bytes = &point.extra_bytes[position..position + size];
+
+                if native {
+                    attribute.values.extend_from_slice(bytes);
+                    continue;
+                }
+
+                let value = dimension.value(dimension.data_type.decode(bytes));
+
+                match component_type {
+                    ComponentType::Double => {
+                        attribute.values.extend_from_slice(&value.to_le_bytes())
+                    }
+                    _ => attribute
+                        .values
+                        .extend_from_slice(&(value as f32).to_le_bytes()),
+                }
+            }
+
+            attributes.push(attribute);
+        }
+
+        attributes
+    }
+}
+
+// user ids are NUL padded to 16 bytes, some writers keep the padding
+fn is_extra_bytes(vlr: &Vlr) -> bool {
+    vlr.user_id.trim_end_matches('\0') == USER_ID && vlr.record_id == RECORD_ID
+}
+
+// property names are identifiers in glTF metadata
+fn property_name(name: &str) -> String {
+    let mut property_name = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+
+    if property_name.is_empty() {
+        property_name = "ExtraBytes".to_string();
+    }
+
+    if property_name.starts_with(|c: char| c.is_ascii_digit())
+        || RECORD_FIELD_NAMES.contains(&property_name.as_str())
+    {
+        property_name = format!("Extra{}", property_name);
+    }
+
+    property_name
+}
+
+fn c_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+
+    String::from_utf8_lossy(&bytes[..end]).trim().to_string()
+}
+
+fn array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    bytes[..N].try_into().expect("enough bytes for the value")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // an Extra Bytes VLR descriptor, the options bits are set for the given no data, scale and offset
+    fn descriptor(
+        code: u8,
+        name: &str,
+        no_data: Option<[u8; 8]>,
+        scale: Option<f64>,
+        offset: Option<f64>,
+    ) -> Vec<u8> {
+        let mut descriptor = vec![0; DESCRIPTOR_SIZE];
+
+        descriptor[2] = code;
+        descriptor[4..4 + name.len()].copy_from_slice(name.as_bytes());
+
+        if let Some(no_data) = no_data {
+            descriptor[3] |= 1;
+            descriptor[40..48].copy_from_slice(&no_data);
+        }
+
+        if let Some(scale) = scale {
+            descriptor[3] |= 1 << 3;
+            descriptor[112..120].copy_from_slice(&scale.to_le_bytes());
+        }
+
+        if let Some(offset) = offset {
+            descriptor[3] |= 1 << 4;
+            descriptor[136..144].copy_from_slice(&offset.to_le_bytes());
+        }
+
+        descriptor
+    }
+
+    fn fields(layout: &ExtraBytesLayout) -> Vec<(&str, ExtraBytesType, usize)> {
+        layout
+            .fields
+            .iter()
+            .map(|field| {
+                (
+                    field.dimension.name.as_str(),
+                    field.dimension.data_type,
+                    field.position,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn data_types_and_positions() {
+        let descriptors = (1..=10)
+            .flat_map(|code| descriptor(code, &format!("type{}", code), None, None, None))
+            .collect::<Vec<_>>();
+
+        let layout = ExtraBytesLayout::from_descriptors(&descriptors);
+
+        assert_eq!(
+            fields(&layout),
+            [
+                ("type1", ExtraBytesType::UnsignedChar, 0),
+                ("type2", ExtraBytesType::Char, 1),
+                ("type3", ExtraBytesType::UnsignedShort, 2),
+                ("type4", ExtraBytesType::Short, 4),
+                ("type5", ExtraBytesType::UnsignedLong, 6),
+                ("type6", ExtraBytesType::Long, 10),
+                ("type7", ExtraBytesType::UnsignedLongLong, 14),
+                ("type8", ExtraBytesType::LongLong, 22),
+                ("type9", ExtraBytesType::Float, 30),
+                ("type10", ExtraBytesType::Double, 34),
+            ]
+        );
+    }
+
+    #[test]
+    fn skipped_descriptors() {
+        let mut undocumented = descriptor(0, "padding", None, None, None);
+        undocumented[3] = 3;
+
+        let descriptors = [
+            undocumented,
+            // an array of three shorts
+            descriptor(24, "vector", None, None, None),
+            descriptor(1, "kept", None, None, None),
+            descriptor(31, "unknown", None, None, None),
+            descriptor(1, "after", None, None, None),
+        ]
+        .concat();
+
+        let layout = ExtraBytesLayout::from_descriptors(&descriptors);
+
+        assert_eq!(fields(&layout), [("kept", ExtraBytesType::UnsignedChar, 9)]);
+    }
+
+    #[test]
+    fn options() {
+        let descriptors = [
+            descriptor(1, "plain", None, Some(2.0), Some(3.0)),
+            descriptor(
+                4,
+                "signed",
+                Some((-9999i64).to_le_bytes()),
+                Some(0.01),
+                None,
+            ),
+            descriptor(
+                5,
+                "unsigned",
+                Some(u64::MAX.to_le_bytes()),
+                None,
+                Some(-5.0),
+            ),
+            descriptor(9, "float", Some((-1.5f64).to_le_bytes()), None, None),
+        ]
+        .concat();
+
+        let layout = ExtraBytesLayout::from_descriptors(&descriptors);
+
+        let dimensions = layout
+            .fields
+            .iter()
+            .map(|field| {
+                let dimension = &field.dimension;
+
+                (dimension.no_data, dimension.scale, dimension.offset)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            dimensions,
+            [
+                (None, Some(2.0), Some(3.0)),
+                (Some(-9999.0), Some(0.01), None),
+                (Some(u64::MAX as f64), None, Some(-5.0)),
+                (Some(-1.5), None, None),
+            ]
+        );
+
+        // the scale and offset fields only count with their option bits
+        let plain = ExtraBytesLayout::from_descriptors(&descriptor(1, "plain", None, None, None));
+        let mut unscaled = descriptor(1, "plain", None, None, None);
+        unscaled[112..120].copy_from_slice(&2.0f64.to_le_bytes());
+        unscaled[136..144].copy_from_slice(&3.0f64.to_le_bytes());
+
+        assert_eq!(
+            ExtraBytesLayout::from_descriptors(&unscaled).fields[0].dimension,
+            plain.fields[0].dimension
+        );
+    }
+
+    #[test]
+    fn names_and_descriptions() {
+        let mut descriptor = descriptor(1, "Point Source", None, None, None);
+        descriptor[160..170].copy_from_slice(b"origin id\0");
+
+        let mut layout = ExtraBytesLayout::from_descriptors(&descriptor);
+        let mut schema = ExtraBytesSchema::default();
+        schema.add(&mut layout);
+
+        assert_eq!(schema.dimensions[0].name, "Point_Source");
+        assert_eq!(schema.dimensions[0].description, "origin id");
+        assert_eq!(property_name("Intensity"), "ExtraIntensity");
+        assert_eq!(property_name("2nd"), "Extra2nd");
+    }
+
+    #[test]
+    fn user_ids_with_padding() {
+        let vlr = |user_id: &str, record_id| Vlr {
+            user_id: user_id.to_string(),
+            record_id,
+            description: String::new(),
+            data: vec![],
+        };
+
+        assert!(is_extra_bytes(&vlr("LASF_Spec", 4)));
+        assert!(is_extra_bytes(&vlr("LASF_Spec\0\0\0\0\0\0\0", 4)));
+        assert!(!is_extra_bytes(&vlr("LASF_Spec", 3)));
+        assert!(!is_extra_bytes(&vlr("LASF_Projection", 4)));
+    }
+
+    #[test]
+    fn narrowed_dimensions() {
+        let descriptors = [
+            descriptor(3, "short", None, None, None),
+            descriptor(5, "long", None, None, None),
+            descriptor(9, "float", None, None, None),
+            descriptor(10, "double", None, None, None),
+            descriptor(8, "scaled", None, Some(0.001), None),
+        ]
+        .concat();
+
+        let mut layout = ExtraBytesLayout::from_descriptors(&descriptors);
+        let mut schema = ExtraBytesSchema::default();
+        schema.add(&mut layout);
+
+        let narrowed = |content_format| {
+            schema
+                .narrowed(content_format)
+                .map(|dimension| dimension.name.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(narrowed(ContentFormat::Glb), ["long", "double", "scaled"]);
+        assert_eq!(narrowed(ContentFormat::Pnts), ["scaled"]);
+    }
+
+    #[test]
+    fn colliding_names() {
+        let descriptors = [
+            descriptor(1, "a b", None, None, None),
+            descriptor(3, "a_b", None, None, None),
+            descriptor(1, "a-b", None, None, None),
+            descriptor(1, "a_b_2", None, None, None),
+        ]
+        .concat();
+
+        let mut layout = ExtraBytesLayout::from_descriptors(&descriptors);
+        let mut schema = ExtraBytesSchema::default();
+        schema.add(&mut layout);
+
+        let names = |schema: &ExtraBytesSchema| {
+            schema
+                .dimensions
+                .iter()
+                .map(|dimension| dimension.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(&schema), ["a_b", "a_b_2", "a_b_3", "a_b_2_2"]);
+
+        // every dimension keeps its own values
+        let record = schema.convert(&layout, &[1, 2, 3, 4, 5]);
+        assert_eq!(record, [1, 2, 3, 4, 5]);
+
+        // another input is matched by the names it stores, not by the property names
+        let mut other = ExtraBytesLayout::from_descriptors(
+            &[
+                descriptor(1, "a-b", None, None, None),
+                descriptor(3, "a_b", None, None, None),
+            ]
+            .concat(),
+        );
+        schema.add(&mut other);
+
+        assert_eq!(names(&schema).len(), 4);
+        assert_eq!(schema.convert(&other, &[9, 7, 8]), [0, 7, 8, 9, 0]);
+    }
+}
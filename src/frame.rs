@@ -0,0 +1,155 @@
+use crate::crs::WGS84;
+
+// local east-north-up frame tangent to the ellipsoid at its origin
+#[derive(Clone, Debug, PartialEq)]
+pub struct LocalFrame {
+    pub origin: [f64; 3],
+    pub east: [f64; 3],
+    pub north: [f64; 3],
+    pub up: [f64; 3],
+}
+
+impl LocalFrame {
+    pub fn new(x: f64, y: f64, z: f64) -> Self {
+        let (lat, lon, _) = WGS84.geocentric_to_geodetic(x, y, z);
+
+        let (sin_lat, cos_lat) = lat.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = lon.to_radians().sin_cos();
+
+        Self {
+            origin: [x, y, z],
+            east: [-sin_lon, cos_lon, 0.0],
+            north: [-sin_lat * cos_lon, -sin_lat * sin_lon, cos_lat],
+            up: [cos_lat * cos_lon, cos_lat * sin_lon, sin_lat],
+        }
+    }
+
+    pub fn to_local(&self, x: f64, y: f64, z: f64) -> (f64, f64, f64) {
+        let d = [x - self.origin[0], y - self.origin[1], z - self.origin[2]];
+
+        let dot = |axis: &[f64; 3]| axis[0] * d[0] + axis[1] * d[1] + axis[2] * d[2];
+
+        (dot(&self.east), dot(&self.north), dot(&self.up))
+    }
+
+    pub fn to_ecef(&self, east: f64, north: f64, up: f64) -> (f64, f64, f64) {
+        let axis = |i: usize| {
+            self.origin[i] + self.east[i] * east + self.north[i] * north + self.up[i] * up
+        };
+
+        (axis(0), axis(1), axis(2))
+    }
+
+    // column-major matrix from the local frame to ECEF, as used by the tile transform
+    pub fn transform(&self) -> [f64; 16] {
+        [
+            self.east[0],
+            self.east[1],
+            self.east[2],
+            0.0,
+            self.north[0],
+            self.north[1],
+            self.north[2],
+            0.0,
+            self.up[0],
+            self.up[1],
+            self.up[2],
+            0.0,
+            self.origin[0],
+            self.origin[1],
+            self.origin[2],
+            1.0,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_at(lat: f64, lon: f64, h: f64) -> LocalFrame {
+        let (x, y, z) = WGS84.geodetic_to_geocentric(lat, lon, h);
+
+        LocalFrame::new(x, y, z)
+    }
+
+    #[test]
+    fn round_trip() {
+        let origins = [
+            (0.0, 0.0, 0.0),
+            (52.37, 4.9, 12.0),
+            (-33.87, 151.21, 250.0),
+            (64.1, -179.99, -30.0),
+            (89.99, 45.0, 2_800.0),
+        ];
+
+        let offsets = [
+            (0.0, 0.0, 0.0),
+            (1.5, -2.25, 0.125),
+            (-9_876.5, 4_321.0, -150.0),
+            (25_000.0, 25_000.0, 8_000.0),
+        ];
+
+        for (lat, lon, h) in origins {
+            let frame = frame_at(lat, lon, h);
+
+            for (x, y, z) in offsets {
+                let (x, y, z) = (
+                    frame.origin[0] + x,
+                    frame.origin[1] + y,
+                    frame.origin[2] + z,
+                );
+
+                let (east, north, up) = frame.to_local(x, y, z);
+                let ecef = frame.to_ecef(east, north, up);
+
+                for (axis, (decoded, expected)) in
+                    [(ecef.0, x), (ecef.1, y), (ecef.2, z)].iter().enumerate()
+                {
+                    assert!(
+                        (decoded - expected).abs() < 1e-6,
+                        "axis {} of {:?} at {:?}",
+                        axis,
+                        (x, y, z),
+                        (lat, lon, h)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn east_north_up() {
+        let frame = frame_at(30.0, 60.0, 0.0);
+
+        let above = frame_at(30.0, 60.0, 100.0).origin;
+        let (east, north, up) = frame.to_local(above[0], above[1], above[2]);
+
+        assert!(east.abs() < 1e-6 && north.abs() < 1e-6);
+        assert!((up - 100.0).abs() < 1e-6);
+
+        // a thousandth of a degree further north and east
+        let northern = frame_at(30.001, 60.0, 0.0).origin;
+        let (_, north, _) = frame.to_local(northern[0], northern[1], northern[2]);
+        assert!(north > 110.0 && north < 111.0);
+
+        let eastern = frame_at(30.0, 60.001, 0.0).origin;
+        let (east, _, _) = frame.to_local(eastern[0], eastern[1], eastern[2]);
+        assert!(east > 96.0 && east < 97.0);
+
+        // the transform moves local positions like to_ecef
+        let matrix = frame.transform();
+        let local = [12.0, -34.0, 56.0];
+        let transformed = [0, 1, 2].map(|row| {
+            (0..3)
+                .map(|column| matrix[column * 4 + row] * local[column])
+                .sum::<f64>()
+                + matrix[12 + row]
+        });
+        let (x, y, z) = frame.to_ecef(local[0], local[1], local[2]);
+
+        for (transformed, expected) in transformed.iter().zip([x, y, z]) {
+            assert!((transformed - expected).abs() < 1e-6);
+        }
+    }
+}
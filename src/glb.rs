@@ -0,0 +1,1132 @@
+use crate::attributes::{
+    classification_name, point_attributes, ComponentType, PointAttribute, CLASSIFICATION_ENUM,
+};
+use crate::draco::DracoPointCloud;
+use crate::error::{Error, Result};
+use crate::meshopt::encode_vertex_buffer;
+use crate::tiles::TileContext;
+use crate::tree::Tree;
+use log::debug;
+use serde::Serialize;
+use serde_json::json;
+use std::borrow::Cow;
+
+const MAGIC: &[u8; 4] = b"glTF";
+
+const VERSION: u32 = 2;
+
+const CHUNK_JSON: u32 = 0x4e4f_534a;
+
+const CHUNK_BIN: u32 = 0x004e_4942;
+
+const ARRAY_BUFFER: u32 = 34962;
+
+const BYTE: u32 = 5120;
+
+const UNSIGNED_BYTE: u32 = 5121;
+
+const SHORT: u32 = 5122;
+
+const UNSIGNED_SHORT: u32 = 5123;
+
+const FLOAT: u32 = 5126;
+
+const POINTS: u32 = 0;
+
+const DRACO_EXTENSION: &str = "KHR_draco_mesh_compression";
+
+const MESHOPT_EXTENSION: &str = "EXT_meshopt_compression";
+
+const QUANTIZATION_EXTENSION: &str = "KHR_mesh_quantization";
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Gltf {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub extensions_used: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub extensions_required: Vec<String>,
+    #[serde(skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extensions: serde_json::Map<String, serde_json::Value>,
+    pub asset: Asset,
+    pub scene: usize,
+    pub scenes: Vec<Scene>,
+    pub nodes: Vec<Node>,
+    pub meshes: Vec<Mesh>,
+    pub accessors: Vec<Accessor>,
+    pub buffer_views: Vec<BufferView>,
+    pub buffers: Vec<Buffer>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Asset {
+    pub version: String,
+    pub generator: String,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct Scene {
+    pub nodes: Vec<usize>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct Node {
+    pub mesh: usize,
+    pub translation: [f64; 3],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<[f64; 3]>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct Mesh {
+    pub primitives: Vec<Primitive>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct Primitive {
+    pub attributes: serde_json::Map<String, serde_json::Value>,
+    pub mode: u32,
+    #[serde(skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extensions: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Accessor {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buffer_view: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub byte_offset: Option<usize>,
+    pub component_type: u32,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub normalized: bool,
+    pub count: usize,
+    #[serde(rename = "type")]
+    pub ty: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<Vec<f64>>,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BufferView {
+    pub buffer: usize,
+    pub byte_offset: usize,
+    pub byte_length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub byte_stride: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<u32>,
+    #[serde(skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extensions: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Buffer {
+    pub byte_length: usize,
+    #[serde(skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extensions: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Default for Asset {
+    fn default() -> Self {
+        Self {
+            version: "2.0".to_string(),
+            generator: env!("CARGO_PKG_NAME").to_string(),
+        }
+    }
+}
+
+// a Draco compressed primitive, with the ids of its compressed attributes by semantic
+pub struct DracoMesh {
+    pub point_cloud: DracoPointCloud,
+    pub attributes: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Default)]
+pub struct GlbBuilder {
+    pub gltf: Gltf,
+    pub binary: Vec<u8>,
+    pub meshopt: bool,
+    // size of the buffer the meshopt compressed views decode into, it has no data in the file
+    fallback_length: usize,
+}
+
+impl GlbBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_buffer_view(
+        &mut self,
+        data: &[u8],
+        byte_stride: Option<usize>,
+        target: Option<u32>,
+    ) -> usize {
+        pad(&mut self.binary, 0);
+
+        self.gltf.buffer_views.push(BufferView {
+            buffer: 0,
+            byte_offset: self.binary.len(),
+            byte_length: data.len(),
+            byte_stride,
+            target,
+            ..BufferView::default()
+        });
+
+        self.binary.extend_from_slice(data);
+
+        self.gltf.buffer_views.len() - 1
+    }
+
+    // meshopt compresses vertices of up to 256 bytes
+    pub fn add_vertex_buffer_view(
+        &mut self,
+        data: &[u8],
+        vertex_size: usize,
+        byte_stride: Option<usize>,
+    ) -> usize {
+        if !self.meshopt || vertex_size > 256 {
+            return self.add_buffer_view(data, byte_stride, Some(ARRAY_BUFFER));
+        }
+
+        let encoded = encode_vertex_buffer(data, vertex_size);
+
+        pad(&mut self.binary, 0);
+
+        let mut extensions = serde_json::Map::new();
+        extensions.insert(
+            MESHOPT_EXTENSION.to_string(),
+            json!({
+                "buffer": 0,
+                "byteOffset": self.binary.len(),
+                "byteLength": encoded.len(),
+                "byteStride": vertex_size,
+                "count": data.len() / vertex_size,
+                "mode": "ATTRIBUTES",
+            }),
+        );
+
+        self.gltf.buffer_views.push(BufferView {
+            buffer: 1,
+            byte_offset: self.fallback_length,
+            byte_length: data.len(),
+            byte_stride: Some(vertex_size),
+            target: Some(ARRAY_BUFFER),
+            extensions,
+        });
+
+        self.binary.extend_from_slice(&encoded);
+        self.fallback_length += data.len();
+
+        self.require_extension(MESHOPT_EXTENSION);
+
+        self.gltf.buffer_views.len() - 1
+    }
+
+    pub fn require_extension(&mut self, name: &str) {
+        if !self
+            .gltf
+            .extensions_required
+            .iter()
+            .any(|used| used == name)
+        {
+            self.gltf.extensions_used.push(name.to_string());
+            self.gltf.extensions_required.push(name.to_string());
+        }
+    }
+
+    pub fn add_accessor(&mut self, accessor: Accessor) -> usize {
+        self.gltf.accessors.push(accessor);
+        self.gltf.accessors.len() - 1
+    }
+
+    pub fn finish(mut self) -> Result<Vec<u8>> {
+        pad(&mut self.binary, 0);
+
+        self.gltf.buffers.push(Buffer {
+            byte_length: self.binary.len(),
+            ..Buffer::default()
+        });
+
+        if self.fallback_length > 0 {
+            let mut extensions = serde_json::Map::new();
+            extensions.insert(MESHOPT_EXTENSION.to_string(), json!({ "fallback": true }));
+
+            self.gltf.buffers.push(Buffer {
+                byte_length: self.fallback_length,
+                extensions,
+            });
+        }
+
+        let mut json = serde_json::to_vec(&self.gltf)?;
+        pad(&mut json, 0x20);
+
+        let byte_length = 12 + 8 + json.len() + 8 + self.binary.len();
+
+        let mut glb = Vec::with_capacity(byte_length);
+        glb.extend_from_slice(MAGIC);
+        glb.extend_from_slice(&VERSION.to_le_bytes());
+        glb.extend_from_slice(&(byte_length as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&CHUNK_JSON.to_le_bytes());
+        glb.extend_from_slice(&json);
+        glb.extend_from_slice(&(self.binary.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&CHUNK_BIN.to_le_bytes());
+        glb.extend_from_slice(&self.binary);
+
+        Ok(glb)
+    }
+}
+
+impl DracoMesh {
+    pub fn add_attribute(&mut self, semantic: &str, attribute: &PointAttribute) {
+        if let Some(id) = self.point_cloud.add_point_attribute(attribute) {
+            self.attributes.insert(semantic.to_string(), id.into());
+        }
+    }
+}
+
+// glTF chunks and buffer views are aligned to four bytes
+fn pad(data: &mut Vec<u8>, value: u8) {
+    data.resize(data.len() + (4 - data.len() % 4) % 4, value);
+}
+
+// glTF is y-up, 3D Tiles rotates it to the z-up tile frame when the content is loaded
+fn to_y_up(x: f64, y: f64, z: f64) -> [f64; 3] {
+    [x, z, -y]
+}
+
+pub fn package_glb<T: Tree>(tree: &T, context: &TileContext) -> Result<Vec<u8>> {
+    let points = tree.points();
+    let bounds = tree.bounds();
+
+    // glTF accessors and buffers cannot be empty, and positions without points have no bounds
+    if points.is_empty() {
+        return Err(Error::InvalidBounds(
+            "glb content needs at least one point".to_string(),
+        ));
+    }
+
+    let mut builder = GlbBuilder::new();
+
+    let mut draco = context.options.draco.map(|options| DracoMesh {
+        point_cloud: DracoPointCloud::new(points.len(), options),
+        attributes: serde_json::Map::new(),
+    });
+
+    let mut positions = Vec::with_capacity(points.len() * 3);
+    let mut colors = Vec::with_capacity(points.len() * 3);
+    let mut min = [f64::MAX; 3];
+    let mut max = [f64::MIN; 3];
+
+    for point in points {
+        let position = to_y_up(
+            point.x - bounds.x_center,
+            point.y - bounds.y_center,
+            point.z - bounds.z_center,
+        );
+
+        for (axis, value) in position.iter().enumerate() {
+            let value = *value as f32;
+
+            positions.push(value);
+            min[axis] = min[axis].min(value as f64);
+            max[axis] = max[axis].max(value as f64);
+        }
+
+        colors.extend_from_slice(&[
+            (point.r >> 8) as u8,
+            (point.g >> 8) as u8,
+            (point.b >> 8) as u8,
+        ]);
+    }
+
+    let mut position = Accessor {
+        component_type: FLOAT,
+        count: points.len(),
+        ty: "VEC3".to_string(),
+        min: Some(min.to_vec()),
+        max: Some(max.to_vec()),
+        ..Accessor::default()
+    };
+
+    let mut color = Accessor {
+        component_type: UNSIGNED_BYTE,
+        normalized: true,
+        count: points.len(),
+        ty: "VEC3".to_string(),
+        ..Accessor::default()
+    };
+
+    let mut node = Node {
+        mesh: 0,
+        translation: to_y_up(bounds.x_center, bounds.y_center, bounds.z_center),
+        scale: None,
+    };
+
+    builder.meshopt = context.options.meshopt.is_some();
+
+    match &mut draco {
+        Some(draco) => {
+            let position = draco.point_cloud.add_positions(positions);
+            draco
+                .attributes
+                .insert("POSITION".to_string(), position.into());
+
+            let color = draco.point_cloud.add_colors(&colors, 3);
+            draco.attributes.insert("COLOR_0".to_string(), color.into());
+        }
+        None => {
+            let position_bits = context
+                .options
+                .meshopt
+                .and_then(|meshopt| meshopt.position_bits);
+
+            position.buffer_view = Some(match position_bits {
+                // integer steps from the minimum, the node scales and moves them back
+                Some(bits) => {
+                    let (quantized, scale, steps) = quantize_positions(&positions, min, max, bits);
+
+                    for (translation, min) in node.translation.iter_mut().zip(min) {
+                        *translation += min;
+                    }
+
+                    node.scale = Some(scale);
+
+                    position.component_type = UNSIGNED_SHORT;
+                    position.min = Some(vec![0.0; 3]);
+                    position.max = Some(steps.to_vec());
+
+                    builder.require_extension(QUANTIZATION_EXTENSION);
+                    builder.add_vertex_buffer_view(&quantized, 8, Some(8))
+                }
+                None => {
+                    let positions: Vec<u8> = positions
+                        .iter()
+                        .flat_map(|value| value.to_le_bytes())
+                        .collect();
+
+                    builder.add_vertex_buffer_view(&positions, 12, None)
+                }
+            });
+
+            // vertex attributes are aligned to four bytes, so every color is padded
+            let colors: Vec<u8> = colors
+                .chunks_exact(3)
+                .flat_map(|color| [color[0], color[1], color[2], 0])
+                .collect();
+
+            color.buffer_view = Some(builder.add_vertex_buffer_view(&colors, 4, Some(4)));
+        }
+    }
+
+    let position = builder.add_accessor(position);
+    let color = builder.add_accessor(color);
+
+    let mut primitive = Primitive {
+        mode: POINTS,
+        ..Primitive::default()
+    };
+
+    primitive
+        .attributes
+        .insert("POSITION".to_string(), position.into());
+    primitive
+        .attributes
+        .insert("COLOR_0".to_string(), color.into());
+
+    add_metadata(
+        &mut builder,
+        &mut primitive,
+        draco.as_mut(),
+        &point_attributes(points, context),
+    );
+
+    if let Some(draco) = draco {
+        let buffer_view = builder.add_buffer_view(&draco.point_cloud.encode(), None, None);
+
+        primitive.extensions.insert(
+            DRACO_EXTENSION.to_string(),
+            json!({ "bufferView": buffer_view, "attributes": draco.attributes }),
+        );
+
+        builder.require_extension(DRACO_EXTENSION);
+    }
+
+    builder.gltf.meshes.push(Mesh {
+        primitives: vec![primitive],
+    });
+
+    builder.gltf.nodes.push(node);
+
+    builder.gltf.scenes.push(Scene { nodes: vec![0] });
+
+    builder.finish()
+}
+
+// positions padded to 8 bytes per point, with the scale of a step and the largest step per axis
+fn quantize_positions(
+    positions: &[f32],
+    min: [f64; 3],
+    max: [f64; 3],
+    bits: u8,
+) -> (Vec<u8>, [f64; 3], [f64; 3]) {
+    let max_step = ((1u32 << bits) - 1) as f64;
+
+    let mut scale = [1.0; 3];
+    let mut steps = [0.0; 3];
+
+    for axis in 0..3 {
+        if max[axis] > min[axis] {
+            scale[axis] = (max[axis] - min[axis]) / max_step;
+            steps[axis] = max_step;
+        }
+    }
+
+    let mut quantized = Vec::with_capacity(positions.len() / 3 * 8);
+
+    for position in positions.chunks_exact(3) {
+        for axis in 0..3 {
+            let step = ((position[axis] as f64 - min[axis]) / scale[axis]).round() as u16;
+
+            quantized.extend_from_slice(&step.to_le_bytes());
+        }
+
+        quantized.extend_from_slice(&[0, 0]);
+    }
+
+    (quantized, scale, steps)
+}
+
+// the component types glTF allows for vertex attributes
+fn gltf_component_type(component_type: ComponentType) -> Option<u32> {
+    match component_type {
+        ComponentType::Byte => Some(BYTE),
+        ComponentType::UnsignedByte => Some(UNSIGNED_BYTE),
+        ComponentType::Short => Some(SHORT),
+        ComponentType::UnsignedShort => Some(UNSIGNED_SHORT),
+        ComponentType::Float => Some(FLOAT),
+        ComponentType::Int | ComponentType::UnsignedInt | ComponentType::Double => None,
+    }
+}
+
+// attributes of other types are narrowed to floats, the job warns about the extra dimensions it narrows
+fn vertex_attribute(attribute: &PointAttribute) -> (Cow<'_, PointAttribute>, u32) {
+    match gltf_component_type(attribute.component_type) {
+        Some(gltf_type) => (Cow::Borrowed(attribute), gltf_type),
+        None => {
+            debug!(
+                "Narrowing {:?} attribute {:?} to floats",
+                attribute.component_type, attribute.name
+            );
+
+            (Cow::Owned(attribute.to_float()), FLOAT)
+        }
+    }
+}
+
+// "EdgeOfFlightLine" becomes "_EDGE_OF_FLIGHT_LINE", application specific attributes start with an underscore
+fn attribute_semantic(name: &str) -> String {
+    let mut semantic = String::new();
+    let mut previous: Option<char> = None;
+
+    for c in name.chars() {
+        if c.is_uppercase() && previous.is_some_and(|p| p.is_lowercase() || p.is_ascii_digit()) {
+            semantic.push('_');
+        }
+
+        previous = Some(c);
+
+        semantic.push(c.to_ascii_uppercase());
+    }
+
+    format!("_{}", semantic)
+}
+
+// per-point attributes as EXT_structural_metadata property attributes, interleaved in one buffer view
+fn add_metadata(
+    builder: &mut GlbBuilder,
+    primitive: &mut Primitive,
+    mut draco: Option<&mut DracoMesh>,
+    attributes: &[PointAttribute],
+) {
+    let attributes: Vec<(Cow<PointAttribute>, u32)> =
+        attributes.iter().map(vertex_attribute).collect();
+
+    let count = match attributes.first() {
+        Some((attribute, _)) => attribute.count(),
+        None => return,
+    };
+
+    if count == 0 {
+        return;
+    }
+
+    // accessor offsets of vertex attributes are multiples of four, so every property gets a 4 byte slot
+    let mut offsets = vec![];
+    let mut stride = 0;
+
+    for (attribute, _) in &attributes {
+        offsets.push(stride);
+        stride += attribute.component_type.size().max(4);
+    }
+
+    // compressed attributes have no buffer view, their accessors are filled by the decoder
+    let buffer_view = match draco {
+        Some(_) => None,
+        None => {
+            let mut interleaved = vec![0; stride * count];
+
+            for ((attribute, _), offset) in attributes.iter().zip(&offsets) {
+                let size = attribute.component_type.size();
+
+                for (index, value) in attribute.values.chunks_exact(size).enumerate() {
+                    let start = index * stride + offset;
+                    interleaved[start..start + size].copy_from_slice(value);
+                }
+            }
+
+            Some(builder.add_vertex_buffer_view(&interleaved, stride, Some(stride)))
+        }
+    };
+
+    let mut properties = serde_json::Map::new();
+    let mut property_attributes = serde_json::Map::new();
+    let mut enums = serde_json::Map::new();
+    let mut feature_ids = vec![];
+
+    for ((attribute, gltf_type), offset) in attributes.iter().zip(&offsets) {
+        let accessor = builder.add_accessor(Accessor {
+            buffer_view,
+            byte_offset: buffer_view.map(|_| *offset),
+            component_type: *gltf_type,
+            count,
+            ty: "SCALAR".to_string(),
+            ..Accessor::default()
+        });
+
+        let semantic = attribute_semantic(&attribute.name);
+
+        if let Some(draco) = draco.as_deref_mut() {
+            draco.add_attribute(&semantic, attribute);
+        }
+
+        primitive
+            .attributes
+            .insert(semantic.clone(), accessor.into());
+
+        let mut property_attribute = json!({ "attribute": semantic });
+
+        if let Some(offset) = attribute.offset {
+            property_attribute["offset"] = json!(offset);
+        }
+
+        property_attributes.insert(attribute.name.to_string(), property_attribute);
+
+        match attribute.enum_type {
+            Some(CLASSIFICATION_ENUM) => {
+                let max_code = attribute.values.iter().copied().max().unwrap_or(0);
+
+                // every code, so all tiles of a tile set declare the same enum whatever classes they hold
+                let values: Vec<serde_json::Value> = (0..=255u8)
+                    .map(|code| json!({ "name": classification_name(code), "value": code }))
+                    .collect();
+
+                enums.insert(
+                    CLASSIFICATION_ENUM.to_string(),
+                    json!({ "valueType": "UINT8", "values": values }),
+                );
+
+                properties.insert(
+                    attribute.name.to_string(),
+                    json!({ "type": "ENUM", "enumType": CLASSIFICATION_ENUM }),
+                );
+
+                // classification codes double as feature ids, so picking and styling can address a class
+                let feature_id = match draco.as_deref_mut() {
+                    // every compressed attribute decodes into its own accessor
+                    Some(draco) => {
+                        draco.add_attribute("_FEATURE_ID_0", attribute);
+
+                        builder.add_accessor(Accessor {
+                            component_type: *gltf_type,
+                            count,
+                            ty: "SCALAR".to_string(),
+                            ..Accessor::default()
+                        })
+                    }
+                    None => accessor,
+                };
+
+                primitive
+                    .attributes
+                    .insert("_FEATURE_ID_0".to_string(), feature_id.into());
+
+                // feature ids are the codes themselves, so the count spans every code up to the largest
+                // one present, absent codes included, which keeps every id below it
+                feature_ids.push(json!({
+                    "featureCount": max_code as u32 + 1,
+                    "attribute": 0,
+                    "label": "classification",
+                }));
+            }
+            _ => {
+                let mut property = json!({
+                    "type": "SCALAR",
+                    "componentType": attribute.component_type.metadata_name(),
+                });
+
+                if let Some(no_data) = attribute.no_data {
+                    property["noData"] = match attribute.component_type {
+                        ComponentType::Float | ComponentType::Double => json!(no_data),
+                        _ => json!(no_data as i64),
+                    };
+                }
+
+                if let Some(description) = &attribute.description {
+                    property["description"] = json!(description);
+                }
+
+                properties.insert(attribute.name.to_string(), property);
+            }
+        }
+    }
+
+    builder.gltf.extensions.insert(
+        "EXT_structural_metadata".to_string(),
+        json!({
+            "schema": {
+                "id": "las_point_cloud",
+                "enums": enums,
+                "classes": { "point": { "properties": properties } },
+            },
+            "propertyAttributes": [{ "class": "point", "properties": property_attributes }],
+        }),
+    );
+
+    primitive.extensions.insert(
+        "EXT_structural_metadata".to_string(),
+        json!({ "propertyAttributes": [0] }),
+    );
+
+    builder
+        .gltf
+        .extensions_used
+        .push("EXT_structural_metadata".to_string());
+
+    if !feature_ids.is_empty() {
+        primitive.extensions.insert(
+            "EXT_mesh_features".to_string(),
+            json!({ "featureIds": feature_ids }),
+        );
+
+        builder
+            .gltf
+            .extensions_used
+            .push("EXT_mesh_features".to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attributes::OptionalAttribute;
+    use crate::extra_bytes::ExtraBytesSchema;
+    use crate::frame::LocalFrame;
+    use crate::job::TilingOptions;
+    use crate::meshopt::tests::decode_vertex_buffer;
+    use crate::meshopt::MeshoptOptions;
+    use crate::quadtree::{Aabb, Point, QuadTree};
+    use crate::sink::DirectorySink;
+    use crate::tiles::ContentFormat;
+    use serde_json::Value;
+    use std::convert::TryInto;
+
+    fn classified(x: f64, classification: u8) -> Point {
+        Point {
+            classification,
+            ..Point::at(x, 0.0, 0.0)
+        }
+    }
+
+    fn glb_tile(points: Vec<Point>, options: TilingOptions) -> Vec<u8> {
+        package_tile(points, options).unwrap()
+    }
+
+    // a glb tile of the points in a 20 m cube around the origin
+    fn package_tile(points: Vec<Point>, options: TilingOptions) -> Result<Vec<u8>> {
+        let bounds = Aabb {
+            x_center: 0.0,
+            y_center: 0.0,
+            z_center: 0.0,
+            half_width: 10.0,
+            half_length: 10.0,
+            half_height: 10.0,
+        };
+
+        let sink = DirectorySink::new("unused");
+        let frame = LocalFrame::new(6378137.0, 0.0, 0.0);
+        let options = TilingOptions {
+            content_format: ContentFormat::Glb,
+            ..options
+        };
+        let extra_bytes = ExtraBytesSchema::default();
+        let context = TileContext {
+            sink: &sink,
+            frame: &frame,
+            options: &options,
+            extra_bytes: &extra_bytes,
+            transform: None,
+        };
+
+        package_glb(&QuadTree::leaf(bounds, 1, 100, points), &context)
+    }
+
+    // the JSON and binary chunks of a glb
+    fn decode(glb: &[u8]) -> (Value, &[u8]) {
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes([
+                glb[offset],
+                glb[offset + 1],
+                glb[offset + 2],
+                glb[offset + 3],
+            ]) as usize
+        };
+
+        assert_eq!(&glb[0..4], MAGIC);
+        assert_eq!(u32_at(8), glb.len());
+
+        let json_length = u32_at(12);
+        let gltf = serde_json::from_slice(&glb[20..20 + json_length]).unwrap();
+
+        (gltf, &glb[20 + json_length + 8..])
+    }
+
+    // the values of an uncompressed scalar or vector accessor, component after component
+    fn accessor_values(gltf: &Value, binary: &[u8], accessor: &Value) -> Vec<f64> {
+        let accessor = &gltf["accessors"][accessor.as_u64().unwrap() as usize];
+        let view = &gltf["bufferViews"][accessor["bufferView"].as_u64().unwrap() as usize];
+
+        let components = match accessor["type"].as_str().unwrap() {
+            "SCALAR" => 1,
+            "VEC3" => 3,
+            ty => panic!("unexpected accessor type {}", ty),
+        };
+
+        let component_type = accessor["componentType"].as_u64().unwrap() as u32;
+        let size = match component_type {
+            BYTE | UNSIGNED_BYTE => 1,
+            SHORT | UNSIGNED_SHORT => 2,
+            _ => 4,
+        };
+
+        let stride = view["byteStride"]
+            .as_u64()
+            .map_or(size * components, |stride| stride as usize);
+
+        // meshopt compressed views are decoded, their offset is in the fallback buffer
+        let (binary, start) = match view["extensions"][MESHOPT_EXTENSION].as_object() {
+            Some(meshopt) => {
+                let field = |name: &str| meshopt[name].as_u64().unwrap() as usize;
+                let encoded =
+                    &binary[field("byteOffset")..field("byteOffset") + field("byteLength")];
+
+                (
+                    Cow::Owned(decode_vertex_buffer(
+                        encoded,
+                        field("count"),
+                        field("byteStride"),
+                        &mut [0; 4],
+                    )),
+                    0,
+                )
+            }
+            None => (
+                Cow::Borrowed(binary),
+                view["byteOffset"].as_u64().unwrap() as usize,
+            ),
+        };
+        let start = start + accessor["byteOffset"].as_u64().unwrap_or(0) as usize;
+
+        let mut values = vec![];
+
+        for index in 0..accessor["count"].as_u64().unwrap() as usize {
+            for component in 0..components {
+                let offset = start + index * stride + component * size;
+                let bytes = &binary[offset..offset + size];
+
+                values.push(match component_type {
+                    BYTE => bytes[0] as i8 as f64,
+                    UNSIGNED_BYTE => bytes[0] as f64,
+                    SHORT => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    UNSIGNED_SHORT => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    FLOAT => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+                    _ => panic!("unexpected component type {}", component_type),
+                });
+            }
+        }
+
+        values
+    }
+
+    fn metadata(gltf: &Value) -> &Value {
+        &gltf["extensions"]["EXT_structural_metadata"]
+    }
+
+    #[test]
+    fn classification_property_attribute() {
+        let glb = glb_tile(
+            vec![
+                classified(-5.0, 2),
+                classified(0.0, 6),
+                classified(5.0, 200),
+            ],
+            TilingOptions::default(),
+        );
+
+        let (gltf, binary) = decode(&glb);
+        let primitive = &gltf["meshes"][0]["primitives"][0];
+
+        let property_attributes = &metadata(&gltf)["propertyAttributes"][0];
+        assert_eq!(property_attributes["class"], "point");
+        assert_eq!(
+            property_attributes["properties"]["Classification"]["attribute"],
+            "_CLASSIFICATION"
+        );
+
+        let classes = accessor_values(&gltf, binary, &primitive["attributes"]["_CLASSIFICATION"]);
+        assert_eq!(classes, [2.0, 6.0, 200.0]);
+
+        let positions = accessor_values(&gltf, binary, &primitive["attributes"]["POSITION"]);
+        assert_eq!(positions, [-5.0, 0.0, 0.0, 0.0, 0.0, 0.0, 5.0, 0.0, 0.0]);
+
+        let values = metadata(&gltf)["schema"]["enums"][CLASSIFICATION_ENUM]["values"]
+            .as_array()
+            .unwrap()
+            .clone();
+
+        assert_eq!(values.len(), 256);
+        assert_eq!(values[2], json!({ "name": "Ground", "value": 2 }));
+        assert_eq!(values[6], json!({ "name": "Building", "value": 6 }));
+        assert_eq!(values[40], json!({ "name": "Reserved 40", "value": 40 }));
+        assert_eq!(
+            values[200],
+            json!({ "name": "User Definable 200", "value": 200 })
+        );
+    }
+
+    #[test]
+    fn same_schema_in_every_tile() {
+        let ground = glb_tile(vec![classified(0.0, 2)], TilingOptions::default());
+        let user_defined = glb_tile(
+            vec![classified(0.0, 1), classified(1.0, 150)],
+            TilingOptions::default(),
+        );
+
+        let (ground, _) = decode(&ground);
+        let (user_defined, _) = decode(&user_defined);
+
+        assert_eq!(
+            metadata(&ground)["schema"],
+            metadata(&user_defined)["schema"]
+        );
+    }
+
+    fn attribute(name: &str, component_type: ComponentType, values: &[&[u8]]) -> PointAttribute {
+        let mut attribute = PointAttribute::new(name, component_type, values.len());
+        attribute.values = values.concat();
+        attribute
+    }
+
+    #[test]
+    fn narrowed_attributes_and_feature_count() {
+        let mut classification = attribute(
+            "Classification",
+            ComponentType::UnsignedByte,
+            &[&[1], &[7], &[3]],
+        );
+        classification.enum_type = Some(CLASSIFICATION_ENUM);
+
+        let attributes = [
+            classification,
+            attribute(
+                "Long",
+                ComponentType::Int,
+                &[
+                    &(-70_000i32).to_le_bytes(),
+                    &0i32.to_le_bytes(),
+                    &i32::MAX.to_le_bytes(),
+                ],
+            ),
+            attribute(
+                "UnsignedLong",
+                ComponentType::UnsignedInt,
+                &[
+                    &0u32.to_le_bytes(),
+                    &123_456u32.to_le_bytes(),
+                    &u32::MAX.to_le_bytes(),
+                ],
+            ),
+            attribute(
+                "Double",
+                ComponentType::Double,
+                &[
+                    &0.5f64.to_le_bytes(),
+                    &(-1.25f64).to_le_bytes(),
+                    &1e10f64.to_le_bytes(),
+                ],
+            ),
+        ];
+
+        let mut builder = GlbBuilder::new();
+        let mut primitive = Primitive::default();
+
+        add_metadata(&mut builder, &mut primitive, None, &attributes);
+
+        builder.gltf.meshes.push(Mesh {
+            primitives: vec![primitive],
+        });
+
+        let glb = builder.finish().unwrap();
+        let (gltf, binary) = decode(&glb);
+        let primitive = &gltf["meshes"][0]["primitives"][0];
+        let properties = &metadata(&gltf)["schema"]["classes"]["point"]["properties"];
+
+        let narrowed = [
+            ("Long", "_LONG", [-70_000.0, 0.0, i32::MAX as f32 as f64]),
+            (
+                "UnsignedLong",
+                "_UNSIGNED_LONG",
+                [0.0, 123_456.0, u32::MAX as f32 as f64],
+            ),
+            ("Double", "_DOUBLE", [0.5, -1.25, 1e10f32 as f64]),
+        ];
+
+        for (name, semantic, values) in narrowed {
+            let accessor = &primitive["attributes"][semantic];
+
+            assert_eq!(
+                gltf["accessors"][accessor.as_u64().unwrap() as usize]["componentType"],
+                FLOAT
+            );
+            assert_eq!(properties[name]["componentType"], "FLOAT32");
+            assert_eq!(accessor_values(&gltf, binary, accessor), values);
+        }
+
+        // ids are the codes, so the count covers the largest one
+        let feature_ids = &primitive["extensions"]["EXT_mesh_features"]["featureIds"];
+        assert_eq!(feature_ids[0]["featureCount"], 8);
+        assert_eq!(
+            accessor_values(&gltf, binary, &primitive["attributes"]["_FEATURE_ID_0"]),
+            [1.0, 7.0, 3.0]
+        );
+    }
+
+    #[test]
+    fn quantized_positions() {
+        let points = vec![
+            Point::at(-8.0, -6.0, -1.0),
+            Point::at(7.5, 9.0, 3.0),
+            Point::at(1.234_567, -2.345_678, 0.456_789),
+            Point::at(-3.3, 4.4, 2.2),
+        ];
+
+        let bits = 12;
+
+        let glb = glb_tile(
+            points.clone(),
+            TilingOptions {
+                meshopt: Some(MeshoptOptions {
+                    position_bits: Some(bits),
+                }),
+                ..TilingOptions::default()
+            },
+        );
+
+        let (gltf, binary) = decode(&glb);
+        let primitive = &gltf["meshes"][0]["primitives"][0];
+
+        for extension in [MESHOPT_EXTENSION, QUANTIZATION_EXTENSION] {
+            assert!(gltf["extensionsRequired"]
+                .as_array()
+                .unwrap()
+                .contains(&json!(extension)));
+        }
+
+        let position =
+            &gltf["accessors"][primitive["attributes"]["POSITION"].as_u64().unwrap() as usize];
+        let max_step = ((1 << bits) - 1) as f64;
+
+        assert_eq!(position["componentType"], UNSIGNED_SHORT);
+        assert_eq!(position["min"], json!([0.0, 0.0, 0.0]));
+        assert_eq!(position["max"], json!([max_step, max_step, max_step]));
+
+        let node = &gltf["nodes"][0];
+        let vector = |value: &Value| [0, 1, 2].map(|axis| value[axis].as_f64().unwrap());
+        let translation = vector(&node["translation"]);
+        let scale = vector(&node["scale"]);
+
+        // the steps span the extent of the tile points, y up
+        assert_eq!(translation, [-8.0, -1.0, -9.0]);
+        assert_eq!(scale.map(|scale| scale * max_step), [15.5, 4.0, 15.0]);
+
+        let steps = accessor_values(&gltf, binary, &primitive["attributes"]["POSITION"]);
+
+        for (point, steps) in points.iter().zip(steps.chunks_exact(3)) {
+            let expected = to_y_up(point.x, point.y, point.z);
+
+            for axis in 0..3 {
+                let decoded = translation[axis] + scale[axis] * steps[axis];
+
+                assert!((decoded - expected[axis]).abs() <= scale[axis] / 2.0 + 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn gps_time_offset() {
+        let points = [1_300_000_010.25, 1_300_000_000.125]
+            .iter()
+            .map(|&gps_time| Point {
+                gps_time,
+                ..Point::at(0.0, 0.0, 0.0)
+            })
+            .collect();
+
+        let glb = glb_tile(
+            points,
+            TilingOptions {
+                attributes: vec![OptionalAttribute::GpsTime],
+                ..TilingOptions::default()
+            },
+        );
+
+        let (gltf, binary) = decode(&glb);
+        let primitive = &gltf["meshes"][0]["primitives"][0];
+
+        assert_eq!(
+            metadata(&gltf)["propertyAttributes"][0]["properties"]["GpsTime"],
+            json!({ "attribute": "_GPS_TIME", "offset": 1_300_000_000.125 })
+        );
+        assert_eq!(
+            metadata(&gltf)["schema"]["classes"]["point"]["properties"]["GpsTime"]["componentType"],
+            "FLOAT32"
+        );
+        assert_eq!(
+            accessor_values(&gltf, binary, &primitive["attributes"]["_GPS_TIME"]),
+            [10.125, 0.0]
+        );
+    }
+
+    #[test]
+    fn no_content_without_points() {
+        assert!(matches!(
+            package_tile(vec![], TilingOptions::default()),
+            Err(Error::InvalidBounds(_))
+        ));
+    }
+}
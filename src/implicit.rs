@@ -0,0 +1,619 @@
+use crate::error::Result;
+use crate::glb::{Buffer, BufferView};
+use crate::tiles::{
+    package_content, ImplicitTiling, TileContext, TileSet, TileSetAsset, TileSetRoot,
+    TileSetRootBoundingVolume, TileSetRootContent,
+};
+use crate::tree::Tree;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const MAGIC: &str = "subt";
+
+const VERSION: u32 = 1;
+
+const SUBTREE_LEVELS: u32 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TileCoordinates {
+    level: u32,
+    x: u32,
+    y: u32,
+    z: u32,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Availability {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bitstream: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    constant: Option<u8>,
+    available_count: usize,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct SubtreeJson {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    buffers: Vec<Buffer>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    buffer_views: Vec<BufferView>,
+    tile_availability: Option<Availability>,
+    content_availability: Vec<Availability>,
+    child_subtree_availability: Option<Availability>,
+}
+
+// availability bits in morton order, least significant bit first
+struct Bitstream {
+    bytes: Vec<u8>,
+    length: usize,
+    count: usize,
+}
+
+struct Subtree {
+    tile_availability: Bitstream,
+    content_availability: Bitstream,
+    child_subtree_availability: Bitstream,
+}
+
+impl TileCoordinates {
+    pub fn root() -> Self {
+        Self {
+            level: 0,
+            x: 0,
+            y: 0,
+            z: 0,
+        }
+    }
+
+    pub fn child(&self, offset: [u32; 3]) -> Self {
+        Self {
+            level: self.level + 1,
+            x: 2 * self.x + offset[0],
+            y: 2 * self.y + offset[1],
+            z: 2 * self.z + offset[2],
+        }
+    }
+
+    fn ancestor(&self, levels: u32) -> Self {
+        Self {
+            level: self.level - levels,
+            x: self.x >> levels,
+            y: self.y >> levels,
+            z: self.z >> levels,
+        }
+    }
+
+    // index among the tiles of the same level below the ancestor `levels` up, x is the lowest bit
+    fn morton_index(&self, levels: u32, dimensions: u32) -> usize {
+        let mut index = 0;
+
+        for bit in 0..levels {
+            for (axis, coordinate) in [self.x, self.y, self.z]
+                .iter()
+                .take(dimensions as usize)
+                .enumerate()
+            {
+                index |= ((*coordinate as usize >> bit) & 1) << (bit * dimensions + axis as u32);
+            }
+        }
+
+        index
+    }
+
+    fn path(&self, dimensions: u32) -> String {
+        match dimensions {
+            2 => format!("{}/{}/{}", self.level, self.x, self.y),
+            _ => format!("{}/{}/{}/{}", self.level, self.x, self.y, self.z),
+        }
+    }
+}
+
+impl Bitstream {
+    fn new(length: usize) -> Self {
+        Self {
+            bytes: vec![0; length.div_ceil(8)],
+            length,
+            count: 0,
+        }
+    }
+
+    fn set(&mut self, index: usize) {
+        if self.bytes[index / 8] & (1 << (index % 8)) == 0 {
+            self.bytes[index / 8] |= 1 << (index % 8);
+            self.count += 1;
+        }
+    }
+
+    // bitstreams with all bits equal are written as constants
+    fn availability(&self, json: &mut SubtreeJson, binary: &mut Vec<u8>) -> Availability {
+        if self.count == 0 || self.count == self.length {
+            return Availability {
+                bitstream: None,
+                constant: Some((self.count > 0) as u8),
+                available_count: self.count,
+            };
+        }
+
+        json.buffer_views.push(BufferView {
+            buffer: 0,
+            byte_offset: binary.len(),
+            byte_length: self.bytes.len(),
+            ..BufferView::default()
+        });
+
+        binary.extend_from_slice(&self.bytes);
+        binary.resize(binary.len() + (8 - binary.len() % 8) % 8, 0);
+
+        Availability {
+            bitstream: Some(json.buffer_views.len() - 1),
+            constant: None,
+            available_count: self.count,
+        }
+    }
+}
+
+impl Subtree {
+    fn new(subtree_levels: u32, dimensions: u32) -> Self {
+        let children = 1 << dimensions;
+        let child_subtrees = 1 << (dimensions * subtree_levels);
+        let tiles = (child_subtrees - 1) / (children - 1);
+
+        Self {
+            tile_availability: Bitstream::new(tiles),
+            content_availability: Bitstream::new(tiles),
+            child_subtree_availability: Bitstream::new(child_subtrees),
+        }
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut json = SubtreeJson::default();
+        let mut binary = vec![];
+
+        json.tile_availability = Some(self.tile_availability.availability(&mut json, &mut binary));
+
+        let content_availability = self
+            .content_availability
+            .availability(&mut json, &mut binary);
+        json.content_availability.push(content_availability);
+
+        json.child_subtree_availability = Some(
+            self.child_subtree_availability
+                .availability(&mut json, &mut binary),
+        );
+
+        if !binary.is_empty() {
+            json.buffers.push(Buffer {
+                byte_length: binary.len(),
+                ..Buffer::default()
+            });
+        }
+
+        let mut json_bytes = serde_json::to_string(&json)?.into_bytes();
+        json_bytes.resize(json_bytes.len() + (8 - json_bytes.len() % 8) % 8, 0x20);
+
+        let mut bytes = Vec::with_capacity(24 + json_bytes.len() + binary.len());
+        bytes.extend_from_slice(MAGIC.as_bytes());
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(json_bytes.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&(binary.len() as u64).to_le_bytes());
+        bytes.append(&mut json_bytes);
+        bytes.append(&mut binary);
+
+        Ok(bytes)
+    }
+}
+
+// a single tileset.json with implicit tiling, content and subtree files are addressed by tile coordinates
+pub fn create_implicit_tileset<T: Tree>(
+    context: &TileContext,
+    base_dir: &Path,
+    tree: &T,
+) -> Result<TileSet> {
+    let mut tiles = BTreeMap::new();
+
+    write_contents(context, base_dir, tree, TileCoordinates::root(), &mut tiles)?;
+
+    write_implicit_tileset(context, base_dir, tree, tree.children().is_some(), &tiles)
+}
+
+// subtree files and the tileset.json, once the content of every tile is written
+pub fn write_implicit_tileset<T: Tree>(
+    context: &TileContext,
+    base_dir: &Path,
+    root: &T,
+    subdivided: bool,
+    tiles: &BTreeMap<TileCoordinates, bool>,
+) -> Result<TileSet> {
+    let dimensions = T::DIMENSIONS;
+    let extension = context.options.content_format.extension();
+
+    let available_levels = tiles.keys().map(|tile| tile.level + 1).max().unwrap_or(1);
+
+    let subtree_levels = SUBTREE_LEVELS.min(available_levels);
+
+    let mut subtrees = BTreeMap::new();
+
+    for (tile, has_content) in tiles {
+        let relative_level = tile.level % subtree_levels;
+        let subtree_root = tile.ancestor(relative_level);
+
+        let subtree = subtrees
+            .entry(subtree_root)
+            .or_insert_with(|| Subtree::new(subtree_levels, dimensions));
+
+        let index = ((1 << (dimensions * relative_level)) - 1) / ((1 << dimensions) - 1)
+            + tile.morton_index(relative_level, dimensions);
+
+        subtree.tile_availability.set(index);
+
+        if *has_content {
+            subtree.content_availability.set(index);
+        }
+
+        if relative_level == 0 && tile.level > 0 {
+            subtrees
+                .entry(tile.ancestor(subtree_levels))
+                .or_insert_with(|| Subtree::new(subtree_levels, dimensions))
+                .child_subtree_availability
+                .set(tile.morton_index(subtree_levels, dimensions));
+        }
+    }
+
+    for (subtree_root, subtree) in &subtrees {
+        context.sink.write(
+            &base_dir.join(format!(
+                "subtrees/{}.subtree",
+                subtree_root.path(dimensions)
+            )),
+            subtree.to_bytes()?.as_slice(),
+        )?;
+    }
+
+    let template = match dimensions {
+        2 => "{level}/{x}/{y}",
+        _ => "{level}/{x}/{y}/{z}",
+    };
+
+    let geometric_error = if subdivided {
+        root.geometric_error()
+    } else {
+        0.0
+    };
+
+    let tile_set = TileSet {
+        asset: TileSetAsset {
+            version: context.options.tileset_version().to_string(),
+        },
+        extensions_used: context.options.tileset_extensions(),
+        extensions_required: context.options.tileset_extensions(),
+        geometric_error,
+        root: TileSetRoot {
+            content: Some(TileSetRootContent {
+                uri: format!("content/{}.{}", template, extension),
+            }),
+            bounding_volume: TileSetRootBoundingVolume::from_bounds(root.bounds()),
+            geometric_error: root.geometric_error(),
+            refine: "ADD".to_string(),
+            children: None,
+            transform: context.transform,
+            implicit_tiling: Some(ImplicitTiling {
+                subdivision_scheme: match dimensions {
+                    2 => "QUADTREE",
+                    _ => "OCTREE",
+                }
+                .to_string(),
+                subtree_levels,
+                available_levels,
+                subtrees: TileSetRootContent {
+                    uri: format!("subtrees/{}.subtree", template),
+                },
+            }),
+            extras: None,
+        },
+    };
+
+    context.sink.write(
+        &base_dir.join("tileset.json"),
+        serde_json::to_string(&tile_set)?.into_bytes().as_slice(),
+    )?;
+
+    Ok(tile_set)
+}
+
+// every node of the tree is an available tile, the ones with points also have content
+fn write_contents<T: Tree>(
+    context: &TileContext,
+    base_dir: &Path,
+    tree: &T,
+    coordinates: TileCoordinates,
+    tiles: &mut BTreeMap<TileCoordinates, bool>,
+) -> Result<()> {
+    write_content(context, base_dir, tree, coordinates, tiles)?;
+
+    if let Some(children) = tree.children() {
+        for (index, child) in children.iter().enumerate() {
+            write_contents(
+                context,
+                base_dir,
+                child.as_ref(),
+                coordinates.child(T::child_offset(index)),
+                tiles,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn write_content<T: Tree>(
+    context: &TileContext,
+    base_dir: &Path,
+    tree: &T,
+    coordinates: TileCoordinates,
+    tiles: &mut BTreeMap<TileCoordinates, bool>,
+) -> Result<()> {
+    let has_content = !tree.points().is_empty();
+
+    if has_content {
+        context.sink.write(
+            &base_dir.join(format!(
+                "content/{}.{}",
+                coordinates.path(T::DIMENSIONS),
+                context.options.content_format.extension()
+            )),
+            package_content(tree, context)?.as_slice(),
+        )?;
+    }
+
+    tiles.insert(coordinates, has_content);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extra_bytes::ExtraBytesSchema;
+    use crate::frame::LocalFrame;
+    use crate::job::TilingOptions;
+    use crate::quadtree::{Aabb, QuadTree};
+    use crate::sink::OutputSink;
+    use serde_json::Value;
+    use std::convert::TryInto;
+    use std::io;
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemorySink {
+        files: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+    }
+
+    impl OutputSink for MemorySink {
+        fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+            self.files
+                .lock()
+                .unwrap()
+                .insert(path.to_owned(), contents.to_vec());
+
+            Ok(())
+        }
+    }
+
+    fn tile(level: u32, x: u32, y: u32) -> TileCoordinates {
+        TileCoordinates { level, x, y, z: 0 }
+    }
+
+    // checks the header and splits a subtree file into its JSON and binary chunks
+    fn parse(bytes: &[u8]) -> (Value, &[u8]) {
+        let u64_at = |offset: usize| {
+            u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap()) as usize
+        };
+
+        assert_eq!(&bytes[0..4], b"subt");
+        assert_eq!(&bytes[4..8], &1u32.to_le_bytes());
+
+        let json_length = u64_at(8);
+        let binary_length = u64_at(16);
+
+        assert_eq!(json_length % 8, 0);
+        assert_eq!(binary_length % 8, 0);
+        assert_eq!(bytes.len(), 24 + json_length + binary_length);
+
+        let json = serde_json::from_slice(&bytes[24..24 + json_length]).unwrap();
+
+        (json, &bytes[24 + json_length..])
+    }
+
+    // the indices of the available bits, the length of constant availability is given
+    fn available(json: &Value, binary: &[u8], availability: &Value, length: usize) -> Vec<usize> {
+        let indices = match availability["bitstream"].as_u64() {
+            Some(view) => {
+                let view = &json["bufferViews"][view as usize];
+                let offset = view["byteOffset"].as_u64().unwrap() as usize;
+                let bytes = &binary[offset..offset + view["byteLength"].as_u64().unwrap() as usize];
+
+                assert_eq!(offset % 8, 0);
+                assert_eq!(bytes.len(), length.div_ceil(8));
+
+                (0..length)
+                    .filter(|index| bytes[index / 8] & 1 << (index % 8) != 0)
+                    .collect::<Vec<_>>()
+            }
+            None => match availability["constant"].as_u64() {
+                Some(0) => vec![],
+                Some(1) => (0..length).collect(),
+                constant => panic!("invalid constant availability {:?}", constant),
+            },
+        };
+
+        assert_eq!(
+            availability["availableCount"].as_u64(),
+            Some(indices.len() as u64)
+        );
+
+        indices
+    }
+
+    #[test]
+    fn morton_index() {
+        assert_eq!(tile(1, 0, 0).morton_index(1, 2), 0);
+        assert_eq!(tile(1, 1, 0).morton_index(1, 2), 1);
+        assert_eq!(tile(1, 0, 1).morton_index(1, 2), 2);
+        assert_eq!(tile(1, 1, 1).morton_index(1, 2), 3);
+        assert_eq!(tile(2, 2, 1).morton_index(2, 2), 0b0110);
+        assert_eq!(tile(5, 31, 1).morton_index(5, 2), 0b01_0101_0111);
+
+        // only the levels below the ancestor count
+        assert_eq!(tile(3, 7, 0).morton_index(1, 2), 1);
+
+        let tile = TileCoordinates {
+            level: 2,
+            x: 1,
+            y: 2,
+            z: 3,
+        };
+
+        assert_eq!(tile.morton_index(2, 3), 0b110_101);
+        assert_eq!(tile.morton_index(1, 3), 0b101);
+    }
+
+    #[test]
+    fn constant_and_bitstream_availability() {
+        let mut json = SubtreeJson::default();
+        let mut binary = vec![];
+
+        let empty = Bitstream::new(21).availability(&mut json, &mut binary);
+
+        assert_eq!((empty.bitstream, empty.constant), (None, Some(0)));
+
+        let mut full = Bitstream::new(5);
+
+        for index in (0..5).chain(0..5) {
+            full.set(index);
+        }
+
+        let full = full.availability(&mut json, &mut binary);
+
+        assert_eq!((full.bitstream, full.constant), (None, Some(1)));
+        assert_eq!(full.available_count, 5);
+        assert!(binary.is_empty());
+
+        let mut partial = Bitstream::new(21);
+        partial.set(0);
+        partial.set(20);
+
+        let partial = partial.availability(&mut json, &mut binary);
+
+        assert_eq!((partial.bitstream, partial.constant), (Some(0), None));
+        assert_eq!(partial.available_count, 2);
+
+        // 3 bytes of bits, padded so the next buffer view starts on 8 bytes
+        assert_eq!(binary, [1, 0, 0x10, 0, 0, 0, 0, 0]);
+
+        let mut next = Bitstream::new(4);
+        next.set(1);
+
+        assert_eq!(next.availability(&mut json, &mut binary).bitstream, Some(1));
+        assert_eq!(json.buffer_views[1].byte_offset, 8);
+        assert_eq!(binary.len(), 16);
+    }
+
+    #[test]
+    fn subtree_files() {
+        let sink = MemorySink::default();
+        let frame = LocalFrame::new(6378137.0, 0.0, 0.0);
+        let options = TilingOptions::default();
+        let extra_bytes = ExtraBytesSchema::default();
+        let context = TileContext {
+            sink: &sink,
+            frame: &frame,
+            options: &options,
+            extra_bytes: &extra_bytes,
+            transform: None,
+        };
+
+        let root = QuadTree::new(
+            Aabb {
+                x_center: 0.0,
+                y_center: 0.0,
+                z_center: 0.0,
+                half_width: 100.0,
+                half_length: 100.0,
+                half_height: 10.0,
+            },
+            1,
+            100,
+        );
+
+        // a branch down to level 5, which starts a child subtree of the 5 levels of the root subtree
+        let tiles = [
+            (tile(0, 0, 0), true),
+            (tile(1, 0, 0), true),
+            (tile(1, 1, 1), false),
+            (tile(2, 1, 0), true),
+            (tile(3, 3, 0), true),
+            (tile(4, 7, 0), true),
+            (tile(5, 15, 1), true),
+        ]
+        .iter()
+        .copied()
+        .collect::<BTreeMap<_, _>>();
+
+        let tile_set =
+            write_implicit_tileset(&context, Path::new(""), &root, true, &tiles).unwrap();
+        let implicit_tiling = tile_set.root.implicit_tiling.unwrap();
+
+        assert_eq!(implicit_tiling.subtree_levels, 5);
+        assert_eq!(implicit_tiling.available_levels, 6);
+
+        let files = sink.files.into_inner().unwrap();
+
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            [
+                Path::new("subtrees/0/0/0.subtree"),
+                Path::new("subtrees/5/15/1.subtree"),
+                Path::new("tileset.json"),
+            ]
+        );
+
+        // the first tile of level 1 to 4 is at 1, 5, 21 and 85 of the 341 tiles of a subtree
+        let (json, binary) = parse(&files[Path::new("subtrees/0/0/0.subtree")]);
+
+        assert_eq!(
+            json["buffers"][0]["byteLength"].as_u64(),
+            Some(binary.len() as u64)
+        );
+        assert_eq!(
+            available(&json, binary, &json["tileAvailability"], 341),
+            [0, 1, 4, 5 + 1, 21 + 5, 85 + 21]
+        );
+        assert_eq!(
+            available(&json, binary, &json["contentAvailability"][0], 341),
+            [0, 1, 5 + 1, 21 + 5, 85 + 21]
+        );
+        assert_eq!(
+            available(&json, binary, &json["childSubtreeAvailability"], 1024),
+            [0b0101_0111]
+        );
+
+        let (json, binary) = parse(&files[Path::new("subtrees/5/15/1.subtree")]);
+
+        assert_eq!(
+            available(&json, binary, &json["tileAvailability"], 341),
+            [0]
+        );
+        assert_eq!(
+            available(&json, binary, &json["contentAvailability"][0], 341),
+            [0]
+        );
+        assert_eq!(
+            json["childSubtreeAvailability"]["constant"].as_u64(),
+            Some(0)
+        );
+    }
+}
@@ -0,0 +1,1794 @@
+use crate::attributes::OptionalAttribute;
+use crate::crs::{Crs, CrsKind, Geoid};
+use crate::discovery::{find_las_files, read_input_list, InputFilter};
+use crate::draco::DracoOptions;
+use crate::error::{Error, Result};
+use crate::extra_bytes::{ExtraBytesLayout, ExtraBytesSchema};
+use crate::frame::LocalFrame;
+use crate::implicit::create_implicit_tileset;
+use crate::meshopt::MeshoptOptions;
+use crate::octree::OcTree;
+use crate::quadtree::{Aabb, Point, QuadTree};
+use crate::sink::OutputSink;
+use crate::spatial_extent::SpatialExtent;
+use crate::streaming::{build_out_of_core, create_tileset_out_of_core, spill, spill_directory};
+use crate::tiles::{
+    create_tile, package_content, BoundingVolumeType, ColorEncoding, ContentFormat, TileContext,
+    TileExtras, TileSet, TileSetAsset, TileSetRoot, TileSetRootBoundingVolume, TileSetRootChild,
+    TileSetRootContent,
+};
+use crate::tree::{Tree, TreeType};
+use las::point::ScanDirection;
+use las::{Bounds, Color, Read, Reader};
+use log::{info, warn};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+const DEFAULT_CAPACITY: usize = 100000;
+
+const MANIFEST_PATH: &str = "inputs.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FailurePolicy {
+    Abort,
+    Skip,
+}
+
+#[derive(Clone, Debug)]
+pub struct TilingOptions {
+    pub capacity: usize,
+    pub failure_policy: FailurePolicy,
+    pub source_crs: Option<Crs>,
+    pub geoid: Option<Arc<Geoid>>,
+    pub bounding_volume: BoundingVolumeType,
+    pub tree_type: TreeType,
+    pub content_format: ContentFormat,
+    pub implicit_tiling: bool,
+    pub memory_budget: Option<usize>,
+    pub temp_dir: Option<PathBuf>,
+    pub unified: bool,
+    pub normalize_intensity: bool,
+    pub attributes: Vec<OptionalAttribute>,
+    pub extra_bytes: bool,
+    // pnts positions are quantized to 16 bits when the error stays below it, in meters
+    pub quantization_tolerance: Option<f64>,
+    pub color_encoding: ColorEncoding,
+    pub draco: Option<DracoOptions>,
+    pub meshopt: Option<MeshoptOptions>,
+}
+
+impl Default for TilingOptions {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_CAPACITY,
+            failure_policy: FailurePolicy::Abort,
+            source_crs: None,
+            geoid: None,
+            bounding_volume: BoundingVolumeType::Box,
+            tree_type: TreeType::QuadTree,
+            content_format: ContentFormat::Pnts,
+            implicit_tiling: false,
+            memory_budget: None,
+            temp_dir: None,
+            unified: false,
+            normalize_intensity: false,
+            attributes: vec![],
+            extra_bytes: false,
+            quantization_tolerance: None,
+            color_encoding: ColorEncoding::Rgb,
+            draco: None,
+            meshopt: None,
+        }
+    }
+}
+
+impl TilingOptions {
+    // glTF content and implicit tiling are 3D Tiles 1.1 features
+    pub fn tileset_version(&self) -> &'static str {
+        if self.content_format == ContentFormat::Glb || self.implicit_tiling {
+            "1.1"
+        } else {
+            "1.0"
+        }
+    }
+
+    // Draco compressed pnts need the 3D Tiles extension, glTF declares its own in the content
+    pub fn tileset_extensions(&self) -> Option<Vec<String>> {
+        if self.draco.is_some() && self.content_format == ContentFormat::Pnts {
+            Some(vec!["3DTILES_draco_point_compression".to_string()])
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FailedInput {
+    pub path: PathBuf,
+    pub error: Error,
+}
+
+#[derive(Debug, Default)]
+pub struct TilingStats {
+    pub input_files: usize,
+    pub failed_inputs: Vec<FailedInput>,
+    pub points: u64,
+    pub root_points: usize,
+}
+
+#[derive(Debug)]
+pub struct TilingResult {
+    pub tileset: TileSet,
+    pub stats: TilingStats,
+}
+
+// the tile set made from one input, with the points promoted from it to the global root
+struct InputTileSet {
+    child: TileSetRootChild,
+    points_to_promote: Vec<Point>,
+    number_of_points: usize,
+    extent: SpatialExtent,
+}
+
+#[derive(Serialize, Debug)]
+struct InputManifest {
+    inputs: Vec<InputManifestEntry>,
+}
+
+#[derive(Serialize, Debug)]
+struct InputManifestEntry {
+    path: String,
+    tileset: String,
+    points: usize,
+}
+
+// what a job tiles, worked out from the LAS headers before any point is read
+#[derive(Debug)]
+pub struct TilingPlan {
+    pub frame: LocalFrame,
+    pub inputs: Vec<InputPlan>,
+    pub failed_inputs: Vec<FailedInput>,
+    pub number_of_points: u64,
+    pub extent: SpatialExtent,
+    // set when the inputs are tiled as one point cloud that does not fit in the memory budget
+    pub memory_budget: Option<usize>,
+    // extra dimensions of all inputs, empty unless they are exported
+    pub extra_bytes: ExtraBytesSchema,
+}
+
+#[derive(Debug)]
+pub struct InputPlan {
+    pub path: PathBuf,
+    pub crs: Crs,
+    pub number_of_points: u64,
+    // directory of the tile set of the input, unique among the inputs of the job
+    pub name: String,
+    // header bounds in the local frame of the job
+    pub extent: SpatialExtent,
+    // set when the input is tiled out of core, to its share of the memory budget
+    pub memory_budget: Option<usize>,
+    center: (f64, f64, f64),
+    bounds: Bounds,
+    extra_bytes: ExtraBytesLayout,
+}
+
+impl TilingPlan {
+    // memory the points take when tiled in memory
+    pub fn memory_estimate(&self) -> usize {
+        (self.number_of_points as usize).saturating_mul(self.extra_bytes.point_size())
+    }
+}
+
+#[derive(Default)]
+pub struct TilerBuilder {
+    inputs: Vec<PathBuf>,
+    input_lists: Vec<PathBuf>,
+    recursive: bool,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    options: TilingOptions,
+    sink: Option<Box<dyn OutputSink>>,
+}
+
+impl TilerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn input<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.inputs.push(path.into());
+        self
+    }
+
+    pub fn inputs<I, P>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.inputs.extend(paths.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn input_list<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.input_lists.push(path.into());
+        self
+    }
+
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    pub fn include<S: Into<String>>(mut self, pattern: S) -> Self {
+        self.include.push(pattern.into());
+        self
+    }
+
+    pub fn exclude<S: Into<String>>(mut self, pattern: S) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.options.capacity = capacity;
+        self
+    }
+
+    pub fn failure_policy(mut self, failure_policy: FailurePolicy) -> Self {
+        self.options.failure_policy = failure_policy;
+        self
+    }
+
+    pub fn source_crs(mut self, crs: Crs) -> Self {
+        self.options.source_crs = Some(crs);
+        self
+    }
+
+    pub fn geoid(mut self, geoid: Geoid) -> Self {
+        self.options.geoid = Some(Arc::new(geoid));
+        self
+    }
+
+    pub fn bounding_volume(mut self, bounding_volume: BoundingVolumeType) -> Self {
+        self.options.bounding_volume = bounding_volume;
+        self
+    }
+
+    pub fn tree_type(mut self, tree_type: TreeType) -> Self {
+        self.options.tree_type = tree_type;
+        self
+    }
+
+    pub fn content_format(mut self, content_format: ContentFormat) -> Self {
+        self.options.content_format = content_format;
+        self
+    }
+
+    pub fn implicit_tiling(mut self, implicit_tiling: bool) -> Self {
+        self.options.implicit_tiling = implicit_tiling;
+        self
+    }
+
+    pub fn memory_budget(mut self, memory_budget: usize) -> Self {
+        self.options.memory_budget = Some(memory_budget);
+        self
+    }
+
+    pub fn temp_dir<P: Into<PathBuf>>(mut self, temp_dir: P) -> Self {
+        self.options.temp_dir = Some(temp_dir.into());
+        self
+    }
+
+    pub fn unified(mut self, unified: bool) -> Self {
+        self.options.unified = unified;
+        self
+    }
+
+    pub fn normalize_intensity(mut self, normalize_intensity: bool) -> Self {
+        self.options.normalize_intensity = normalize_intensity;
+        self
+    }
+
+    pub fn attributes<I>(mut self, attributes: I) -> Self
+    where
+        I: IntoIterator<Item = OptionalAttribute>,
+    {
+        for attribute in attributes {
+            if !self.options.attributes.contains(&attribute) {
+                self.options.attributes.push(attribute);
+            }
+        }
+
+        self
+    }
+
+    pub fn extra_bytes(mut self, extra_bytes: bool) -> Self {
+        self.options.extra_bytes = extra_bytes;
+        self
+    }
+
+    pub fn quantization_tolerance(mut self, tolerance: f64) -> Self {
+        self.options.quantization_tolerance = Some(tolerance);
+        self
+    }
+
+    pub fn color_encoding(mut self, color_encoding: ColorEncoding) -> Self {
+        self.options.color_encoding = color_encoding;
+        self
+    }
+
+    pub fn draco(mut self, draco: DracoOptions) -> Self {
+        self.options.draco = Some(draco);
+        self
+    }
+
+    pub fn meshopt(mut self, meshopt: MeshoptOptions) -> Self {
+        self.options.meshopt = Some(meshopt);
+        self
+    }
+
+    pub fn options(mut self, options: TilingOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn sink<S: OutputSink + 'static>(mut self, sink: S) -> Self {
+        self.sink = Some(Box::new(sink));
+        self
+    }
+
+    pub fn build(self) -> Result<TilingJob> {
+        if self.options.capacity == 0 {
+            return Err(Error::InvalidInput(
+                "Tile capacity must be greater than zero".to_string(),
+            ));
+        }
+
+        // implicit tiling subdivides the root region in longitude and latitude, the trees split
+        // the box of the local frame, so the content would not match the implied tiles
+        if self.options.implicit_tiling
+            && self.options.bounding_volume == BoundingVolumeType::Region
+        {
+            return Err(Error::InvalidInput(
+                "Implicit tiling needs box bounding volumes, region volumes are not supported"
+                    .to_string(),
+            ));
+        }
+
+        if let Some(tolerance) = self.options.quantization_tolerance {
+            if !(tolerance > 0.0 && tolerance.is_finite()) {
+                return Err(Error::InvalidInput(format!(
+                    "Quantization tolerance must be a positive number of meters, got {}",
+                    tolerance
+                )));
+            }
+
+            if self.options.content_format == ContentFormat::Glb {
+                warn!("Positions are only quantized in pnts content, writing float positions");
+            }
+        }
+
+        if self.options.color_encoding != ColorEncoding::Rgb
+            && self.options.content_format == ContentFormat::Glb
+        {
+            warn!("Color encodings only apply to pnts content, writing RGB colors");
+        }
+
+        if let Some(draco) = self.options.draco {
+            for (name, bits) in [
+                ("position", draco.position_bits),
+                ("generic", draco.generic_bits),
+            ] {
+                if !(1..=30).contains(&bits) {
+                    return Err(Error::InvalidInput(format!(
+                        "Draco {} quantization must be between 1 and 30 bits, got {}",
+                        name, bits
+                    )));
+                }
+            }
+
+            if self.options.quantization_tolerance.is_some() {
+                warn!("Draco quantizes positions itself, ignoring the quantization tolerance");
+            }
+
+            if self.options.color_encoding == ColorEncoding::Rgb565 {
+                warn!("RGB565 colors are not Draco compressed, writing RGB colors");
+            }
+        }
+
+        if let Some(meshopt) = self.options.meshopt {
+            if let Some(bits) = meshopt.position_bits {
+                if !(1..=16).contains(&bits) {
+                    return Err(Error::InvalidInput(format!(
+                        "Meshopt position quantization must be between 1 and 16 bits, got {}",
+                        bits
+                    )));
+                }
+            }
+
+            match self.options.content_format {
+                ContentFormat::Pnts => {
+                    warn!("Meshopt compression only applies to glb content, writing uncompressed pnts")
+                }
+                ContentFormat::Glb if self.options.draco.is_some() => {
+                    return Err(Error::InvalidInput(
+                        "Draco and meshopt compression of glb content are exclusive".to_string(),
+                    ));
+                }
+                ContentFormat::Glb => {}
+            }
+        }
+
+        let sink = self
+            .sink
+            .ok_or_else(|| Error::InvalidInput("No output sink configured".to_string()))?;
+
+        let filter = InputFilter::new(&self.include, &self.exclude)?;
+
+        let mut inputs = self.inputs;
+
+        for input_list in &self.input_lists {
+            inputs.extend(read_input_list(input_list)?);
+        }
+
+        let las_files = find_las_files(&inputs, &filter, self.recursive)?;
+
+        if las_files.is_empty() {
+            return Err(Error::InvalidInput(
+                "No LAS/LAZ files found in the inputs".to_string(),
+            ));
+        }
+
+        Ok(TilingJob {
+            inputs: las_files,
+            options: self.options,
+            sink,
+        })
+    }
+}
+
+pub struct TilingJob {
+    inputs: Vec<PathBuf>,
+    options: TilingOptions,
+    sink: Box<dyn OutputSink>,
+}
+
+impl TilingJob {
+    pub fn inputs(&self) -> &[PathBuf] {
+        &self.inputs
+    }
+
+    pub fn run(&self) -> Result<TilingResult> {
+        let plan = self.plan()?;
+
+        self.execute(plan)
+    }
+
+    // header pre-pass, the point records of the inputs are not read
+    pub fn plan(&self) -> Result<TilingPlan> {
+        let mut failed_inputs = vec![];
+
+        let plans = self.map_inputs(&self.inputs, &mut failed_inputs, |path| {
+            plan_input(path, &self.options)
+        })?;
+
+        let frame = local_frame(&plans);
+
+        let mut inputs = vec![];
+        let mut extent = SpatialExtent::default();
+
+        for mut input in plans {
+            match header_extent(&input, &frame, &self.options) {
+                Ok(input_extent) => {
+                    extent.union(&input_extent);
+                    input.extent = input_extent;
+                    inputs.push(input);
+                }
+                Err(error) => self.fail_input(&input.path, error, &mut failed_inputs)?,
+            }
+        }
+
+        if inputs.is_empty() {
+            return Err(Error::InvalidInput(
+                "None of the LAS/LAZ files could be processed".to_string(),
+            ));
+        }
+
+        assign_names(&mut inputs);
+
+        let mut extra_bytes = ExtraBytesSchema::default();
+
+        for input in &mut inputs {
+            extra_bytes.add(&mut input.extra_bytes);
+        }
+
+        if !extra_bytes.dimensions.is_empty() {
+            info!(
+                "Exporting extra dimensions {}",
+                extra_bytes
+                    .dimensions
+                    .iter()
+                    .map(|dimension| dimension.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        for dimension in extra_bytes.narrowed(self.options.content_format) {
+            warn!(
+                "Extra dimension {:?} holds {:?} values, exported as 32 bit floats they may lose precision",
+                dimension.name, dimension.data_type
+            );
+        }
+
+        let number_of_points = inputs.iter().map(|input| input.number_of_points).sum();
+        let point_size = extra_bytes.point_size();
+
+        let memory_budget = if self.options.unified {
+            out_of_core_budget(number_of_points, point_size, self.options.memory_budget)
+        } else {
+            for input in &mut inputs {
+                input.memory_budget = out_of_core_budget(
+                    input.number_of_points,
+                    point_size,
+                    file_memory_budget(&self.options),
+                );
+            }
+
+            None
+        };
+
+        info!(
+            "Planned {} points from {} LAS/LAZ files",
+            number_of_points,
+            inputs.len()
+        );
+
+        Ok(TilingPlan {
+            frame,
+            inputs,
+            failed_inputs,
+            number_of_points,
+            extent,
+            memory_budget,
+            extra_bytes,
+        })
+    }
+
+    pub fn execute(&self, mut plan: TilingPlan) -> Result<TilingResult> {
+        let mut stats = TilingStats {
+            input_files: plan.inputs.len() + plan.failed_inputs.len(),
+            failed_inputs: mem::take(&mut plan.failed_inputs),
+            ..TilingStats::default()
+        };
+
+        let frame = &plan.frame;
+
+        if self.options.unified {
+            return self.run_unified(&plan, stats);
+        }
+
+        let mut global_tileset = TileSet {
+            asset: TileSetAsset {
+                version: self.options.tileset_version().to_string(),
+            },
+            extensions_used: self.options.tileset_extensions(),
+            extensions_required: self.options.tileset_extensions(),
+            geometric_error: 5000.0,
+            root: TileSetRoot {
+                content: None,
+                bounding_volume: TileSetRootBoundingVolume {
+                    bbox: None,
+                    region: None,
+                },
+                geometric_error: 2000.0,
+                refine: "ADD".to_string(),
+                children: Some(vec![]),
+                transform: Some(frame.transform()),
+                implicit_tiling: None,
+                extras: None,
+            },
+        };
+
+        let progress = Progress::new(plan.number_of_points);
+
+        let children = self.map_inputs(&plan.inputs, &mut stats.failed_inputs, |input| {
+            let child = self.create_child(input, &plan)?;
+
+            progress.advance(input);
+
+            Ok((child, input.path.to_string_lossy().into_owned()))
+        })?;
+
+        info!("All LAS/LAZ files are processed");
+
+        info!("Creating root tile set");
+
+        let mut bbox = SpatialExtent::default();
+
+        let mut global_tileset_points = vec![];
+
+        let mut global_tileset_root_children = vec![];
+
+        let mut manifest = InputManifest { inputs: vec![] };
+
+        for (tileset, path) in children {
+            manifest.inputs.push(InputManifestEntry {
+                path,
+                tileset: tileset.child.content.uri.clone(),
+                points: tileset.number_of_points,
+            });
+
+            global_tileset_root_children.push(tileset.child);
+            stats.points += tileset.number_of_points as u64;
+            // the root tile encloses the tile sets below it, not only the promoted points
+            bbox.union(&tileset.extent);
+            for point in tileset.points_to_promote {
+                bbox.update(&point);
+
+                global_tileset_points.push(point);
+            }
+        }
+
+        global_tileset.root.children = Some(global_tileset_root_children);
+
+        // which input each tile set was made from
+        self.sink.write(
+            Path::new(MANIFEST_PATH),
+            serde_json::to_string(&manifest)?.into_bytes().as_slice(),
+        )?;
+
+        stats.root_points = global_tileset_points.len();
+
+        let bounds = bbox.bounds();
+
+        let root_content = match self.options.tree_type {
+            TreeType::QuadTree => self.create_root::<QuadTree>(
+                &mut global_tileset,
+                bounds,
+                global_tileset_points,
+                &plan,
+            )?,
+            TreeType::OcTree => self.create_root::<OcTree>(
+                &mut global_tileset,
+                bounds,
+                global_tileset_points,
+                &plan,
+            )?,
+        };
+
+        global_tileset.geometric_error = global_tileset.root.geometric_error * 5.0;
+
+        info!("Saving root tile set");
+
+        if let Some(root_content) = root_content {
+            self.sink.write(
+                Path::new(self.options.content_format.content_uri()),
+                root_content.as_slice(),
+            )?;
+        }
+
+        self.sink.write(
+            Path::new("tileset.json"),
+            serde_json::to_string(&global_tileset)?
+                .into_bytes()
+                .as_slice(),
+        )?;
+
+        info!("Point cloud 3D tiles created successfully");
+
+        Ok(TilingResult {
+            tileset: global_tileset,
+            stats,
+        })
+    }
+
+    // the global root is a single tile holding every point promoted from the file tile sets, without
+    // content when inputs are too small for any point to be promoted
+    fn create_root<T: Tree>(
+        &self,
+        tileset: &mut TileSet,
+        bounds: Aabb,
+        points: Vec<Point>,
+        plan: &TilingPlan,
+    ) -> Result<Option<Vec<u8>>> {
+        let context = self.context(plan);
+
+        let number_of_points = points.len();
+        let mut tree = T::new(bounds, 1, number_of_points);
+
+        for (index, point) in points.into_iter().enumerate() {
+            tree.insert(point, index, number_of_points);
+        }
+
+        tileset.root.geometric_error = tree.geometric_error();
+
+        tileset.root.bounding_volume = TileSetRootBoundingVolume::new(&tree, &context);
+
+        tileset.root.extras = TileExtras::new(&tree, &context);
+
+        if tree.points().is_empty() {
+            return Ok(None);
+        }
+
+        tileset.root.content = Some(TileSetRootContent {
+            uri: self.options.content_format.content_uri().to_string(),
+        });
+
+        package_content(&tree, &context).map(Some)
+    }
+
+    // all inputs are tiled as one point cloud, so tiles follow space instead of the input files
+    fn run_unified(&self, plan: &TilingPlan, mut stats: TilingStats) -> Result<TilingResult> {
+        let context = TileContext {
+            transform: Some(plan.frame.transform()),
+            ..self.context(plan)
+        };
+
+        info!(
+            "Tiling {} points from {} LAS/LAZ files as one point cloud",
+            plan.number_of_points,
+            plan.inputs.len()
+        );
+
+        let (tileset, points) = match self.options.tree_type {
+            TreeType::QuadTree => self.create_unified::<QuadTree>(plan, &context, &mut stats)?,
+            TreeType::OcTree => self.create_unified::<OcTree>(plan, &context, &mut stats)?,
+        };
+
+        stats.points = points as u64;
+
+        info!("Point cloud 3D tiles created successfully");
+
+        Ok(TilingResult { tileset, stats })
+    }
+
+    // the header extent bounds the tree, grown by any point outside of it
+    fn create_unified<T: Tree>(
+        &self,
+        plan: &TilingPlan,
+        context: &TileContext,
+        stats: &mut TilingStats,
+    ) -> Result<(TileSet, usize)> {
+        let target_path = Path::new("");
+        let progress = Progress::new(plan.number_of_points);
+
+        if let Some(memory_budget) = plan.memory_budget {
+            info!("The point cloud does not fit in the memory budget, tiling it out of core");
+
+            let directory = spill_directory(context)?;
+
+            let spilled = self.map_inputs(&plan.inputs, &mut stats.failed_inputs, |input| {
+                let mut reader = Reader::from_path(&input.path)?;
+
+                let spilled = spill(
+                    &directory,
+                    reader
+                        .points()
+                        .map(|point| to_local_point(point?, input, context)),
+                )?;
+
+                progress.advance(input);
+
+                Ok(spilled)
+            })?;
+
+            let mut bbox = SpatialExtent::default();
+            bbox.union(&plan.extent);
+
+            let mut inputs = vec![];
+
+            for (input, input_bbox) in spilled {
+                bbox.union(&input_bbox);
+                inputs.push(input);
+            }
+
+            if !bbox.is_valid() {
+                return Err(Error::InvalidBounds(
+                    "The inputs contain no points with finite coordinates".to_string(),
+                ));
+            }
+
+            let (tileset, _, points) = build_out_of_core::<T>(
+                &directory,
+                inputs,
+                bbox.bounds(),
+                target_path,
+                context,
+                memory_budget,
+                false,
+            )?;
+
+            return Ok((tileset, points));
+        }
+
+        let points = self.map_inputs(&plan.inputs, &mut stats.failed_inputs, |input| {
+            let mut reader = Reader::from_path(&input.path)?;
+
+            let points = reader
+                .points()
+                .map(|point| to_local_point(point?, input, context))
+                .collect::<Result<Vec<_>>>()?;
+
+            progress.advance(input);
+
+            Ok(points)
+        })?;
+
+        let points = points.into_iter().flatten().collect::<Vec<_>>();
+
+        let mut bbox = SpatialExtent::default();
+        bbox.union(&plan.extent);
+
+        for point in &points {
+            bbox.update(point);
+        }
+
+        if !bbox.is_valid() {
+            return Err(Error::InvalidBounds(
+                "The inputs contain no points with finite coordinates".to_string(),
+            ));
+        }
+
+        let number_of_points = points.len();
+
+        let (tree, _) = build_tree::<T>(points, &bbox, self.options.capacity, false);
+
+        let tileset = write_tileset(context, target_path, &tree)?;
+
+        Ok((tileset, number_of_points))
+    }
+
+    fn context<'a>(&'a self, plan: &'a TilingPlan) -> TileContext<'a> {
+        TileContext {
+            sink: self.sink.as_ref(),
+            frame: &plan.frame,
+            options: &self.options,
+            extra_bytes: &plan.extra_bytes,
+            transform: None,
+        }
+    }
+
+    fn map_inputs<I, T, F>(
+        &self,
+        items: &[I],
+        failed_inputs: &mut Vec<FailedInput>,
+        f: F,
+    ) -> Result<Vec<T>>
+    where
+        I: AsRef<Path> + Sync,
+        T: Send,
+        F: Fn(&I) -> Result<T> + Sync + Send,
+    {
+        match self.options.failure_policy {
+            FailurePolicy::Abort => items
+                .par_iter()
+                .map(|item| {
+                    f(item).map_err(|e| Error::Input(item.as_ref().to_owned(), Box::new(e)))
+                })
+                .collect(),
+            FailurePolicy::Skip => {
+                let results = items
+                    .par_iter()
+                    .map(|item| (item.as_ref(), f(item)))
+                    .collect::<Vec<_>>();
+
+                let mut values = vec![];
+
+                for (path, result) in results {
+                    match result {
+                        Ok(value) => values.push(value),
+                        Err(error) => self.fail_input(path, error, failed_inputs)?,
+                    }
+                }
+
+                if values.is_empty() {
+                    return Err(Error::InvalidInput(
+                        "None of the LAS/LAZ files could be processed".to_string(),
+                    ));
+                }
+
+                Ok(values)
+            }
+        }
+    }
+
+    // aborts the job or records the input as skipped, following the failure policy
+    fn fail_input(
+        &self,
+        path: &Path,
+        error: Error,
+        failed_inputs: &mut Vec<FailedInput>,
+    ) -> Result<()> {
+        match self.options.failure_policy {
+            FailurePolicy::Abort => Err(Error::Input(path.to_owned(), Box::new(error))),
+            FailurePolicy::Skip => {
+                warn!("Skipping {:?}: {}", path, error);
+
+                failed_inputs.push(FailedInput {
+                    path: path.to_owned(),
+                    error,
+                });
+
+                Ok(())
+            }
+        }
+    }
+
+    fn create_child(&self, input: &InputPlan, plan: &TilingPlan) -> Result<InputTileSet> {
+        let target_path = Path::new(&input.name);
+        let context = self.context(plan);
+
+        let (child_tileset, points_to_promote, number_of_points, extent) = match self
+            .options
+            .tree_type
+        {
+            TreeType::QuadTree => {
+                create_tileset_for_file::<QuadTree>(input, target_path, &context)?
+            }
+            TreeType::OcTree => create_tileset_for_file::<OcTree>(input, target_path, &context)?,
+        };
+
+        // the tile set is written even when every point of the input was promoted, so the link holds
+        Ok(InputTileSet {
+            child: TileSetRootChild {
+                content: TileSetRootContent {
+                    uri: format!("{}/tileset.json", input.name),
+                },
+                bounding_volume: child_tileset.root.bounding_volume,
+                geometric_error: child_tileset.root.geometric_error,
+                refine: "ADD".to_string(),
+                extras: child_tileset.root.extras,
+            },
+            points_to_promote,
+            number_of_points,
+            extent,
+        })
+    }
+}
+
+// file stems reduced to characters safe in a URI, numbered when they would share a directory,
+// compared ignoring case for case-insensitive file systems
+fn assign_names(inputs: &mut [InputPlan]) {
+    let mut names = HashSet::new();
+
+    for input in inputs {
+        let stem = input
+            .path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+
+        let stem = if stem.is_empty() {
+            "input".to_string()
+        } else {
+            stem
+        };
+
+        let mut name = stem.clone();
+        let mut number = 1;
+
+        while !names.insert(name.to_ascii_lowercase()) {
+            number += 1;
+            name = format!("{}_{}", stem, number);
+        }
+
+        if number > 1 {
+            warn!(
+                "{:?} shares its name with another input, its tile set is written to {:?}",
+                input.path.file_name().unwrap_or_default(),
+                name
+            );
+        }
+
+        input.name = name;
+    }
+}
+
+impl AsRef<Path> for InputPlan {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+fn plan_input(path: &Path, options: &TilingOptions) -> Result<InputPlan> {
+    let reader = Reader::from_path(path)?;
+    let header = reader.header();
+
+    // the header bounds of an empty file are infinite, they would make the frame of the job NaN
+    if header.number_of_points() == 0 {
+        return Err(Error::InvalidInput("It holds no points".to_string()));
+    }
+
+    // the attribute is job wide, so an input without GPS times fails rather than exporting zeros
+    if options.attributes.contains(&OptionalAttribute::GpsTime)
+        && !header.point_format().has_gps_time
+    {
+        return Err(Error::InvalidInput(
+            "Its point format has no GPS time to export as the GpsTime attribute".to_string(),
+        ));
+    }
+
+    let crs = match &options.source_crs {
+        Some(crs) => crs.clone(),
+        None => match Crs::from_las_header(header)? {
+            Some(crs) => crs,
+            None => {
+                warn!(
+                    "{:?} has no CRS in its header, assuming WGS84 longitude/latitude",
+                    path.file_name().unwrap_or_default()
+                );
+
+                Crs::wgs84()
+            }
+        },
+    };
+
+    info!(
+        "Using CRS {:?} for {:?}",
+        crs.name,
+        path.file_name().unwrap_or_default()
+    );
+
+    if let (Some(geoid), CrsKind::Geocentric) = (&options.geoid, &crs.kind) {
+        warn!(
+            "Ignoring geoid {} for {:?}, its CRS is geocentric",
+            geoid.name(),
+            path.file_name().unwrap_or_default()
+        );
+    }
+
+    let bounds = header.bounds();
+
+    let extra_bytes = if options.extra_bytes {
+        ExtraBytesLayout::from_header(header)
+    } else {
+        ExtraBytesLayout::default()
+    };
+
+    // the frame origin only needs to be close to the data, so the geoid is not applied here
+    let center = crs.to_ecef(
+        (bounds.min.x + bounds.max.x) / 2.0,
+        (bounds.min.y + bounds.max.y) / 2.0,
+        (bounds.min.z + bounds.max.z) / 2.0,
+        None,
+    )?;
+
+    Ok(InputPlan {
+        path: path.to_owned(),
+        crs,
+        center,
+        bounds,
+        number_of_points: header.number_of_points(),
+        name: String::new(),
+        extent: SpatialExtent::default(),
+        memory_budget: None,
+        extra_bytes,
+    })
+}
+
+// one frame for the whole job, so every tile set and the root share the root transform
+fn local_frame(plans: &[InputPlan]) -> LocalFrame {
+    let count = plans.len().max(1) as f64;
+
+    let (x, y, z) = plans.iter().fold((0.0, 0.0, 0.0), |sum, plan| {
+        (
+            sum.0 + plan.center.0 / count,
+            sum.1 + plan.center.1 / count,
+            sum.2 + plan.center.2 / count,
+        )
+    });
+
+    LocalFrame::new(x, y, z)
+}
+
+fn create_tileset_for_file<T: Tree>(
+    input: &InputPlan,
+    target_path: &Path,
+    context: &TileContext,
+) -> Result<(TileSet, Vec<Point>, usize, SpatialExtent)> {
+    let options = context.options;
+    let source_path = input.path.as_path();
+
+    let mut reader = Reader::from_path(source_path)?;
+
+    info!(
+        "Processing LAS file {:?} with {} points",
+        source_path.file_name().unwrap_or_default(),
+        input.number_of_points
+    );
+
+    let points = reader
+        .points()
+        .map(|point| to_local_point(point?, input, context));
+
+    if let Some(memory_budget) = input.memory_budget {
+        info!(
+            "{:?} does not fit in the memory budget, tiling it out of core",
+            source_path.file_name().unwrap_or_default()
+        );
+
+        return create_tileset_out_of_core::<T, _>(
+            points,
+            source_path,
+            target_path,
+            context,
+            memory_budget,
+        );
+    }
+
+    let points = points.collect::<Result<Vec<_>>>()?;
+
+    let mut bbox = SpatialExtent::default();
+
+    for point in &points {
+        bbox.update(point);
+    }
+
+    if !bbox.is_valid() {
+        return Err(Error::InvalidBounds(format!(
+            "{:?} contains no points with finite coordinates",
+            source_path.file_name().unwrap_or_default()
+        )));
+    }
+
+    let number_of_points = points.len();
+
+    let (tree, points_to_promote) = build_tree::<T>(points, &bbox, options.capacity, true);
+
+    info!(
+        "Creating tile set {:?}",
+        target_path.file_name().unwrap_or_default()
+    );
+
+    let tile_set = write_tileset(context, target_path, &tree)?;
+
+    info!(
+        "Tile set {:?} created",
+        target_path.file_name().unwrap_or_default()
+    );
+
+    Ok((tile_set, points_to_promote, number_of_points, bbox))
+}
+
+fn to_local_point(
+    las_point: las::Point,
+    input: &InputPlan,
+    context: &TileContext,
+) -> Result<Point> {
+    let has_color = las_point.color.is_some();
+
+    let color = if let Some(color) = las_point.color {
+        color
+    } else {
+        Color::new(0xffff, 0xffff, 0x0000)
+    };
+
+    let (x, y, z) = input.crs.to_ecef(
+        las_point.x,
+        las_point.y,
+        las_point.z,
+        context.options.geoid.as_deref(),
+    )?;
+
+    let (x, y, z) = context.frame.to_local(x, y, z);
+
+    Ok(Point {
+        morton: 0,
+        x,
+        y,
+        z,
+        r: color.red,
+        g: color.green,
+        b: color.blue,
+        has_color,
+        intensity: las_point.intensity,
+        classification: u8::from(las_point.classification),
+        is_edge_of_flight_line: las_point.is_edge_of_flight_line,
+        is_synthetic: las_point.is_synthetic,
+        is_key_point: las_point.is_key_point,
+        is_withheld: las_point.is_withheld,
+        is_overlap: las_point.is_overlap,
+        return_number: las_point.return_number,
+        number_of_returns: las_point.number_of_returns,
+        scan_angle: las_point.scan_angle,
+        scan_direction_flag: las_point.scan_direction == ScanDirection::LeftToRight,
+        gps_time: las_point.gps_time.unwrap_or_default(),
+        point_source_id: las_point.point_source_id,
+        user_data: las_point.user_data,
+        extra_bytes: context
+            .extra_bytes
+            .convert(&input.extra_bytes, &las_point.extra_bytes),
+    })
+}
+
+// input files are tiled in parallel, each worker thread gets an equal share of the budget
+fn file_memory_budget(options: &TilingOptions) -> Option<usize> {
+    options
+        .memory_budget
+        .map(|memory_budget| memory_budget / rayon::current_num_threads())
+}
+
+// the budget a point cloud is tiled out of core with, none when it fits in memory; points are held
+// twice in memory, in the list read from the input and in the tree they are moved to
+fn out_of_core_budget(
+    number_of_points: u64,
+    point_size: usize,
+    memory_budget: Option<usize>,
+) -> Option<usize> {
+    memory_budget.filter(|&memory_budget| {
+        (number_of_points as usize)
+            .saturating_mul(point_size)
+            .saturating_mul(2)
+            > memory_budget
+    })
+}
+
+// inserted in path key order like the out of core tree, with `promote` every few points go to the parent tile set
+pub fn build_tree<T: Tree>(
+    mut points: Vec<Point>,
+    bbox: &SpatialExtent,
+    capacity: usize,
+    promote: bool,
+) -> (T, Vec<Point>) {
+    let mut tree = T::new(bbox.bounds(), 1, capacity);
+
+    let bounds = tree.bounds().clone();
+
+    points
+        .par_iter_mut()
+        .for_each(|point| point.morton = T::path_key(&bounds, point));
+
+    points.par_sort_by(|point1, point2| point1.morton.cmp(&point2.morton));
+
+    let mut points_to_promote = vec![];
+
+    let number_of_points = points.len();
+
+    let promote_step = if promote {
+        4 * number_of_points / capacity
+    } else {
+        0
+    };
+
+    // moved into the tree, so the points are held once while tiling
+    for (index, point) in points.into_iter().enumerate() {
+        if promote_step > 0 && index % promote_step == 0 {
+            points_to_promote.push(point);
+            continue;
+        }
+
+        tree.insert(point, index, number_of_points);
+    }
+
+    (tree, points_to_promote)
+}
+
+fn write_tileset<T: Tree>(context: &TileContext, target_path: &Path, tree: &T) -> Result<TileSet> {
+    if context.options.implicit_tiling {
+        create_implicit_tileset(context, target_path, tree)
+    } else {
+        create_tile(context, target_path, tree)
+    }
+}
+
+// header bounds sampled on a grid, a projection can bend the edges of the box between its corners
+fn header_extent(
+    plan: &InputPlan,
+    frame: &LocalFrame,
+    options: &TilingOptions,
+) -> Result<SpatialExtent> {
+    let bounds = &plan.bounds;
+
+    let mut extent = SpatialExtent::default();
+
+    for i in 0..=2 {
+        for j in 0..=2 {
+            for &z in &[bounds.min.z, bounds.max.z] {
+                let x = bounds.min.x + (bounds.max.x - bounds.min.x) * i as f64 / 2.0;
+                let y = bounds.min.y + (bounds.max.y - bounds.min.y) * j as f64 / 2.0;
+
+                let (x, y, z) = plan.crs.to_ecef(x, y, z, options.geoid.as_deref())?;
+                let (x, y, z) = frame.to_local(x, y, z);
+
+                extent.include(x, y, z);
+            }
+        }
+    }
+
+    Ok(extent)
+}
+
+// points of the finished inputs against the total from the headers
+struct Progress {
+    points: AtomicU64,
+    total: u64,
+}
+
+impl Progress {
+    fn new(total: u64) -> Self {
+        Self {
+            points: AtomicU64::new(0),
+            total,
+        }
+    }
+
+    fn advance(&self, input: &InputPlan) {
+        let points = self
+            .points
+            .fetch_add(input.number_of_points, Ordering::Relaxed)
+            + input.number_of_points;
+
+        info!(
+            "Processed {:?}, {} of {} points ({:.0}%)",
+            input.path.file_name().unwrap_or_default(),
+            points,
+            self.total,
+            100.0 * points as f64 / self.total.max(1) as f64
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use las::{Transform, Vector, Write, Writer};
+    use std::collections::BTreeMap;
+    use std::env;
+    use std::fs;
+    use std::io;
+    use std::process;
+    use std::sync::Mutex;
+
+    // the files of a job, kept after the job owning the sink is dropped
+    #[derive(Clone, Default)]
+    struct MemorySink {
+        files: Arc<Mutex<BTreeMap<PathBuf, Vec<u8>>>>,
+    }
+
+    impl OutputSink for MemorySink {
+        fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+            self.files
+                .lock()
+                .unwrap()
+                .insert(path.to_owned(), contents.to_vec());
+
+            Ok(())
+        }
+    }
+
+    impl MemorySink {
+        fn json(&self, path: &str) -> serde_json::Value {
+            serde_json::from_slice(&self.files.lock().unwrap()[Path::new(path)]).unwrap()
+        }
+    }
+
+    // a directory of its own for every test, the tests of the crate run in parallel
+    fn test_directory(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!(
+            "{}-job-{}-{}",
+            env!("CARGO_PKG_NAME"),
+            process::id(),
+            name
+        ));
+
+        fs::create_dir_all(&directory).unwrap();
+
+        directory
+    }
+
+    // a LAS file without a CRS, so its coordinates are longitudes, latitudes and heights
+    fn write_las(path: &Path, coordinates: &[(f64, f64, f64)]) {
+        let mut builder = las::Builder::from((1, 2));
+        builder.transforms = Vector {
+            x: Transform {
+                scale: 1e-7,
+                offset: 0.0,
+            },
+            y: Transform {
+                scale: 1e-7,
+                offset: 0.0,
+            },
+            z: Transform {
+                scale: 1e-3,
+                offset: 0.0,
+            },
+        };
+
+        let mut writer = Writer::from_path(path, builder.into_header().unwrap()).unwrap();
+
+        for &(x, y, z) in coordinates {
+            writer
+                .write(las::Point {
+                    x,
+                    y,
+                    z,
+                    ..las::Point::default()
+                })
+                .unwrap();
+        }
+
+        writer.close().unwrap();
+    }
+
+    // a grid of points around a longitude and latitude, a few hundred meters across
+    fn grid(lon: f64, lat: f64, side: usize) -> Vec<(f64, f64, f64)> {
+        (0..side * side)
+            .map(|index| {
+                (
+                    lon + (index % side) as f64 * 1e-4,
+                    lat + (index / side) as f64 * 1e-4,
+                    400.0 + (index % 7) as f64,
+                )
+            })
+            .collect()
+    }
+
+    // every content and tile set linked from a tileset.json of the job is written
+    fn assert_links_resolve(sink: &MemorySink) {
+        let files = sink.files.lock().unwrap();
+
+        for (path, contents) in files.iter() {
+            if path.file_name().unwrap() != "tileset.json" {
+                continue;
+            }
+
+            let tileset: serde_json::Value = serde_json::from_slice(contents).unwrap();
+            let directory = path.parent().unwrap();
+            let root = &tileset["root"];
+
+            let uris = root["content"]["uri"].as_str().into_iter().chain(
+                root["children"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|child| child["content"]["uri"].as_str().unwrap()),
+            );
+
+            for uri in uris {
+                assert!(
+                    files.contains_key(&directory.join(uri)),
+                    "{:?} links the missing {:?}",
+                    path,
+                    uri
+                );
+            }
+        }
+    }
+
+    fn input_plan(path: &str) -> InputPlan {
+        InputPlan {
+            path: PathBuf::from(path),
+            crs: Crs::wgs84(),
+            number_of_points: 0,
+            name: String::new(),
+            extent: SpatialExtent::default(),
+            memory_budget: None,
+            center: (0.0, 0.0, 0.0),
+            bounds: Bounds::default(),
+            extra_bytes: ExtraBytesLayout::default(),
+        }
+    }
+
+    fn names(paths: &[&str]) -> Vec<String> {
+        let mut inputs = paths
+            .iter()
+            .map(|path| input_plan(path))
+            .collect::<Vec<_>>();
+
+        assign_names(&mut inputs);
+
+        inputs.into_iter().map(|input| input.name).collect()
+    }
+
+    #[test]
+    fn names_are_sanitized_stems() {
+        assert_eq!(
+            names(&["a/tile 1.las", "b/tile-2.laz", "c/ümlaut.las", "d/.las"]),
+            ["tile_1", "tile-2", "_mlaut", "_las"]
+        );
+    }
+
+    #[test]
+    fn colliding_names_are_numbered() {
+        assert_eq!(
+            names(&["a/tile.las", "b/tile.laz", "c/tile.las", "d/tile_2.las"]),
+            ["tile", "tile_2", "tile_3", "tile_2_2"]
+        );
+    }
+
+    #[test]
+    fn names_differing_in_case_collide() {
+        assert_eq!(
+            names(&["a/Tile.las", "b/TILE.las", "c/tile.las"]),
+            ["Tile", "TILE_2", "tile_3"]
+        );
+    }
+
+    // with a capacity of one point the root of the file keeps none, its tile set still links the rest
+    #[test]
+    fn file_roots_without_points_are_written() {
+        let directory = test_directory("empty-roots");
+        let las = directory.join("dup.las");
+        let single = directory.join("single.las");
+
+        write_las(&las, &grid(7.5, 46.5, 14));
+        write_las(&single, &[(7.6, 46.6, 400.0)]);
+
+        let sink = MemorySink::default();
+
+        TilerBuilder::new()
+            .inputs([&las, &single])
+            .capacity(1)
+            .sink(sink.clone())
+            .build()
+            .unwrap()
+            .run()
+            .unwrap();
+
+        let dup = sink.json("dup/tileset.json");
+
+        assert!(dup["root"].get("content").is_none());
+        assert!(!dup["root"]["children"].as_array().unwrap().is_empty());
+
+        let single = sink.json("single/tileset.json");
+
+        assert!(single["root"].get("content").is_none());
+        assert!(single["root"].get("children").is_none());
+
+        assert_links_resolve(&sink);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    // under a quarter of the capacity no point is promoted, so the global root links no content
+    #[test]
+    fn global_root_without_promoted_points() {
+        let directory = test_directory("unpromoted-root");
+        let las = directory.join("small.las");
+
+        write_las(&las, &grid(7.5, 46.5, 10));
+
+        let sink = MemorySink::default();
+
+        let result = TilerBuilder::new()
+            .inputs([&las])
+            .capacity(1000)
+            .content_format(ContentFormat::Glb)
+            .sink(sink.clone())
+            .build()
+            .unwrap()
+            .run()
+            .unwrap();
+
+        assert_eq!(result.stats.root_points, 0);
+
+        let root = sink.json("tileset.json");
+
+        assert!(root["root"].get("content").is_none());
+        assert_eq!(root["root"]["children"].as_array().unwrap().len(), 1);
+        assert!(!sink
+            .files
+            .lock()
+            .unwrap()
+            .contains_key(Path::new("root.glb")));
+
+        // the points are all in the tile set of the file
+        assert!(sink
+            .files
+            .lock()
+            .unwrap()
+            .contains_key(Path::new("small/root.glb")));
+
+        assert_links_resolve(&sink);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    // tiles follow space across the inputs, the root encloses every point and links the tiles below it
+    fn assert_unified_tiling(name: &str, memory_budget: Option<usize>) {
+        let directory = test_directory(name);
+        let inputs = [(7.5, 46.5), (7.51, 46.5), (7.5, 46.52)]
+            .iter()
+            .enumerate()
+            .map(|(index, &(lon, lat))| {
+                let path = directory.join(format!("part_{}.las", index));
+                let coordinates = grid(lon, lat, 60);
+
+                write_las(&path, &coordinates);
+
+                (path, coordinates)
+            })
+            .collect::<Vec<_>>();
+
+        let sink = MemorySink::default();
+
+        let mut builder = TilerBuilder::new()
+            .inputs(inputs.iter().map(|(path, _)| path))
+            .capacity(20)
+            .unified(true)
+            .sink(sink.clone());
+
+        if let Some(memory_budget) = memory_budget {
+            builder = builder.memory_budget(memory_budget);
+        }
+
+        let job = builder.build().unwrap();
+
+        let plan = job.plan().unwrap();
+        let frame = plan.frame.clone();
+
+        assert_eq!(plan.memory_budget, memory_budget);
+
+        let result = job.execute(plan).unwrap();
+
+        assert_eq!(result.stats.points, 10_800);
+        assert_eq!(result.stats.input_files, 3);
+
+        let tileset = sink.json("tileset.json");
+        let root = &tileset["root"];
+        let bbox = (0..12)
+            .map(|index| root["boundingVolume"]["box"][index].as_f64().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(root["transform"].as_array().unwrap().len(), 16);
+        assert!(root["children"].as_array().unwrap().len() > 1);
+
+        for (_, coordinates) in &inputs {
+            for &(lon, lat, height) in coordinates {
+                let (x, y, z) = Crs::wgs84().to_ecef(lon, lat, height, None).unwrap();
+                let (x, y, z) = frame.to_local(x, y, z);
+
+                for (axis, value) in [x, y, z].iter().enumerate() {
+                    let (center, half) = (bbox[axis], bbox[3 + axis * 4]);
+
+                    assert!(
+                        (value - center).abs() <= half + 1e-3,
+                        "{} outside {} ± {}",
+                        value,
+                        center,
+                        half
+                    );
+                }
+            }
+        }
+
+        // one tile set, not one per input
+        assert!(!sink
+            .files
+            .lock()
+            .unwrap()
+            .contains_key(Path::new(MANIFEST_PATH)));
+
+        assert_links_resolve(&sink);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn unified_tiling_spans_the_inputs() {
+        assert_unified_tiling("unified", None);
+    }
+
+    #[test]
+    fn unified_tiling_out_of_core_spans_the_inputs() {
+        assert_unified_tiling("unified-out-of-core", Some(1 << 20));
+    }
+
+    fn local(frame: &LocalFrame, (lon, lat, height): (f64, f64, f64)) -> (f64, f64, f64) {
+        let (x, y, z) = Crs::wgs84().to_ecef(lon, lat, height, None).unwrap();
+
+        frame.to_local(x, y, z)
+    }
+
+    fn contains(extent: &SpatialExtent, (x, y, z): (f64, f64, f64)) -> bool {
+        let tolerance = 1e-3;
+
+        extent.x_min - tolerance <= x
+            && x <= extent.x_max + tolerance
+            && extent.y_min - tolerance <= y
+            && y <= extent.y_max + tolerance
+            && extent.z_min - tolerance <= z
+            && z <= extent.z_max + tolerance
+    }
+
+    // each input is planned from its own header, the job extent is their union
+    #[test]
+    fn plans_mixed_extents_from_the_headers() {
+        let directory = test_directory("plan");
+        let low = directory.join("low.las");
+        let high = directory.join("high.las");
+
+        let low_points = grid(7.5, 46.5, 10);
+        let high_points = grid(7.53, 46.48, 4)
+            .into_iter()
+            .map(|(lon, lat, height)| (lon, lat, height + 1500.0))
+            .collect::<Vec<_>>();
+
+        write_las(&low, &low_points);
+        write_las(&high, &high_points);
+
+        let plan = TilerBuilder::new()
+            .inputs([&low, &high])
+            .sink(MemorySink::default())
+            .build()
+            .unwrap()
+            .plan()
+            .unwrap();
+
+        assert_eq!(plan.number_of_points, 116);
+        assert!(plan.failed_inputs.is_empty());
+        assert!(plan.frame.origin.iter().all(|axis| axis.is_finite()));
+
+        for (input, points) in plan.inputs.iter().zip([&low_points, &high_points]) {
+            assert_eq!(input.number_of_points, points.len() as u64);
+            assert!(input.memory_budget.is_none());
+
+            for &point in points {
+                assert!(contains(&input.extent, local(&plan.frame, point)));
+            }
+        }
+
+        let (low_extent, high_extent) = (&plan.inputs[0].extent, &plan.inputs[1].extent);
+
+        assert!(low_extent.z_max < high_extent.z_min);
+        assert_eq!(plan.extent.x_min, low_extent.x_min.min(high_extent.x_min));
+        assert_eq!(plan.extent.x_max, low_extent.x_max.max(high_extent.x_max));
+        assert_eq!(plan.extent.y_min, low_extent.y_min.min(high_extent.y_min));
+        assert_eq!(plan.extent.y_max, low_extent.y_max.max(high_extent.y_max));
+        assert_eq!(plan.extent.z_min, low_extent.z_min);
+        assert_eq!(plan.extent.z_max, high_extent.z_max);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    // the infinite header bounds of an empty file must not reach the frame of the job
+    #[test]
+    fn empty_inputs_fail_planning() {
+        let directory = test_directory("plan-empty");
+        let empty = directory.join("empty.las");
+        let full = directory.join("full.las");
+
+        write_las(&empty, &[]);
+        write_las(&full, &grid(7.5, 46.5, 3));
+
+        let job = |failure_policy| {
+            TilerBuilder::new()
+                .inputs([&empty, &full])
+                .failure_policy(failure_policy)
+                .sink(MemorySink::default())
+                .build()
+                .unwrap()
+        };
+
+        let plan = job(FailurePolicy::Skip).plan().unwrap();
+
+        assert_eq!(plan.inputs.len(), 1);
+        assert_eq!(plan.number_of_points, 9);
+        assert_eq!(plan.failed_inputs.len(), 1);
+        assert_eq!(plan.failed_inputs[0].path, empty);
+        assert!(plan.frame.origin.iter().all(|axis| axis.is_finite()));
+        assert!(plan.extent.is_valid());
+
+        match job(FailurePolicy::Abort).plan() {
+            Err(Error::Input(path, _)) => assert_eq!(path, empty),
+            plan => panic!("unexpected plan {:?}", plan.map(|plan| plan.inputs.len())),
+        }
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
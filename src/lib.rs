@@ -0,0 +1,39 @@
+mod attributes;
+mod crs;
+mod discovery;
+mod draco;
+mod error;
+mod external_sort;
+mod extra_bytes;
+mod frame;
+mod glb;
+mod implicit;
+mod job;
+mod meshopt;
+mod octree;
+mod quadtree;
+mod sink;
+mod spatial_extent;
+mod streaming;
+mod tiles;
+mod tree;
+
+pub use crate::attributes::OptionalAttribute;
+pub use crate::crs::{Crs, CrsKind, Datum, Ellipsoid, Geoid, Helmert, Projection};
+pub use crate::draco::DracoOptions;
+pub use crate::error::{Error, Result};
+pub use crate::extra_bytes::{ExtraBytesSchema, ExtraBytesType, ExtraDimension};
+pub use crate::frame::LocalFrame;
+pub use crate::job::{
+    FailedInput, FailurePolicy, InputPlan, TilerBuilder, TilingJob, TilingOptions, TilingPlan,
+    TilingResult, TilingStats,
+};
+pub use crate::meshopt::MeshoptOptions;
+pub use crate::sink::{DirectorySink, OutputSink};
+pub use crate::spatial_extent::SpatialExtent;
+pub use crate::tiles::{
+    AlphaSource, BoundingVolumeType, ColorEncoding, ContentFormat, ImplicitTiling, TileExtras,
+    TileSet, TileSetAsset, TileSetRoot, TileSetRootBoundingVolume, TileSetRootChild,
+    TileSetRootContent, TimeRange,
+};
+pub use crate::tree::TreeType;
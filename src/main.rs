@@ -1,278 +1,264 @@
-mod quadtree;
-mod spatial_extent;
-mod tiles;
-
-use crate::quadtree::{Aabb, Point, QuadTree};
-use crate::spatial_extent::SpatialExtent;
-use crate::tiles::{
-    create_tile, package_points, TileSet, TileSetAsset, TileSetRoot, TileSetRootBoundingVolume,
-    TileSetRootChild, TileSetRootContent,
+mod cli;
+
+use crate::cli::{
+    Alpha, Args, Attribute, BoundingVolume, Colors, Format, OnError, OverwritePolicy, Subdivision,
+};
+use cesium_3d_tiles_generator::{
+    AlphaSource, BoundingVolumeType, ColorEncoding, ContentFormat, Crs, DirectorySink,
+    DracoOptions, Error, FailurePolicy, Geoid, MeshoptOptions, OptionalAttribute, Result,
+    TilerBuilder, TilingPlan, TreeType,
 };
-use las::point::Classification;
-use las::{Color, Read, Reader};
-use morton_encoding::morton_encode;
-use rayon::prelude::*;
+use clap::Parser;
+use log::{error, info, warn};
 use std::fs;
-use std::io::Write;
-use std::path::{Path, PathBuf};
-
-const CAPACITY: usize = 100000;
-
-const LAS_PATH: &str = "C:\\temp\\B2";
-
-const OUTPUT_DIR: &str = "C:\\Cesium-1.88\\Apps\\tileset2";
+use std::path::Path;
+use std::process;
 
 fn main() {
-    let las_path = Path::new(LAS_PATH);
-
-    let output_dir = Path::new(OUTPUT_DIR);
-
-    let mut global_tileset = TileSet {
-        asset: TileSetAsset {
-            version: "1.0".to_string(),
-        },
-        geometric_error: 5000.0,
-        root: TileSetRoot {
-            content: TileSetRootContent {
-                uri: "root.pnts".to_string(),
-            },
-            bounding_volume: TileSetRootBoundingVolume {
-                bbox: [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
-            },
-            geometric_error: 2000.0,
-            refine: "ADD".to_string(),
-            children: Some(vec![]),
-        },
-    };
-
-    let mut children = vec![];
+    let args = Args::parse();
 
-    if las_path.is_dir() {
-        let files = fs::read_dir(las_path).expect("IO Error");
+    env_logger::Builder::new()
+        .filter_level(args.log_level())
+        .format_timestamp(None)
+        .format_target(false)
+        .init();
 
-        let mut las_files = vec![];
-        for file_result in files {
-            if let Ok(file) = file_result {
-                let path = file.path();
-                if path.is_file()
-                    && (path.extension().unwrap_or_default().eq("laz")
-                        || path.extension().unwrap_or_default().eq("las"))
-                {
-                    las_files.push(path);
-                }
-            }
-        }
+    if let Err(e) = run(&args) {
+        error!("{}", e);
+        process::exit(1);
+    }
+}
 
-        children = las_files
-            .par_iter()
-            .map(|path| -> (TileSetRootChild, Vec<Point>) {
-                let file_name = path
-                    .file_name()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .strip_suffix(".laz")
-                    .unwrap();
-
-                let (child_tileset, points_to_promote) =
-                    create_tileset_for_file(&path, &output_dir.join(file_name));
-
-                (
-                    TileSetRootChild {
-                        content: TileSetRootContent {
-                            uri: format!("{}/tileset.json", file_name),
-                        },
-                        bounding_volume: child_tileset.root.bounding_volume,
-                        geometric_error: child_tileset.root.geometric_error,
-                        refine: "ADD".to_string(),
-                    },
-                    points_to_promote,
-                )
-            })
-            .collect::<Vec<(TileSetRootChild, Vec<Point>)>>();
+fn run(args: &Args) -> Result<()> {
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .map_err(|e| Error::InvalidInput(e.to_string()))?;
     }
 
-    println!("All LAS/LAZ files are processed");
+    let source_crs = match &args.source_crs {
+        Some(crs) => Some(crs.parse::<Crs>()?),
+        None => None,
+    };
 
-    println!("Creating root tile set");
+    let geoid = match &args.geoid {
+        Some(path) => Some(Geoid::from_path(path)?),
+        None => None,
+    };
 
-    let mut bbox = SpatialExtent::default();
+    let output_dir = args.output.as_path();
+
+    let mut builder = TilerBuilder::new()
+        .inputs(&args.inputs)
+        .recursive(args.recursive)
+        .capacity(args.capacity)
+        .failure_policy(match args.on_error {
+            OnError::Abort => FailurePolicy::Abort,
+            OnError::Skip => FailurePolicy::Skip,
+        })
+        .bounding_volume(match args.bounding_volume {
+            BoundingVolume::Box => BoundingVolumeType::Box,
+            BoundingVolume::Region => BoundingVolumeType::Region,
+        })
+        .tree_type(match args.subdivision {
+            Subdivision::Quadtree => TreeType::QuadTree,
+            Subdivision::Octree => TreeType::OcTree,
+        })
+        .content_format(match args.format {
+            Format::Pnts => ContentFormat::Pnts,
+            Format::Glb => ContentFormat::Glb,
+        })
+        .color_encoding(match args.colors {
+            Colors::Rgb => ColorEncoding::Rgb,
+            Colors::Rgba => ColorEncoding::Rgba(match args.alpha {
+                Alpha::Intensity => AlphaSource::Intensity,
+                Alpha::UserData => AlphaSource::UserData,
+            }),
+            Colors::Rgb565 => ColorEncoding::Rgb565,
+        })
+        .implicit_tiling(args.implicit_tiling)
+        .unified(args.unified)
+        .normalize_intensity(args.normalize_intensity)
+        .attributes(args.attributes.iter().map(|attribute| match attribute {
+            Attribute::ReturnNumber => OptionalAttribute::ReturnNumber,
+            Attribute::NumberOfReturns => OptionalAttribute::NumberOfReturns,
+            Attribute::ScanAngle => OptionalAttribute::ScanAngle,
+            Attribute::ScanDirection => OptionalAttribute::ScanDirection,
+            Attribute::GpsTime => OptionalAttribute::GpsTime,
+            Attribute::PointSourceId => OptionalAttribute::PointSourceId,
+            Attribute::UserData => OptionalAttribute::UserData,
+        }))
+        .extra_bytes(args.extra_bytes)
+        .sink(DirectorySink::new(output_dir));
+
+    for input_list in &args.input_list {
+        builder = builder.input_list(input_list);
+    }
 
-    let mut global_tileset_points = vec![];
+    for pattern in &args.include {
+        builder = builder.include(pattern);
+    }
 
-    let mut global_tileset_root_children = vec![];
+    for pattern in &args.exclude {
+        builder = builder.exclude(pattern);
+    }
 
-    for child in children {
-        global_tileset_root_children.push(child.0);
-        for point in child.1 {
-            bbox.update(&point);
+    if let Some(crs) = source_crs {
+        builder = builder.source_crs(crs);
+    }
 
-            global_tileset_points.push(point);
-        }
+    if let Some(tolerance) = args.quantize_positions {
+        builder = builder.quantization_tolerance(tolerance);
     }
 
-    let half_width = (bbox.x_max - bbox.x_min) / 2.0;
+    if args.draco {
+        builder = builder.draco(DracoOptions {
+            position_bits: args.draco_position_bits,
+            generic_bits: args.draco_generic_bits,
+        });
+    }
 
-    let half_length = (bbox.y_max - bbox.y_min) / 2.0;
+    if args.meshopt {
+        builder = builder.meshopt(MeshoptOptions {
+            position_bits: args.meshopt_position_bits,
+        });
+    }
 
-    let half_height = (bbox.z_max - bbox.z_min) / 2.0;
+    if let Some(memory_budget) = args.memory_budget {
+        builder = builder.memory_budget(memory_budget);
+    }
 
-    global_tileset.root.children = Some(global_tileset_root_children);
+    if let Some(temp_dir) = &args.temp_dir {
+        builder = builder.temp_dir(temp_dir);
+    }
 
-    let mut global_quadtree = QuadTree::new(
-        Aabb {
-            x_center: bbox.x_min + half_width,
-            y_center: bbox.y_min + half_length,
-            z_center: bbox.z_min + half_height,
-            half_width,
-            half_length,
-            half_height,
-        },
-        1,
-        global_tileset_points.len(),
-    );
+    if let Some(geoid) = geoid {
+        info!("Converting orthometric heights with geoid {}", geoid.name());
 
-    for (index, point) in global_tileset_points.iter().enumerate() {
-        global_quadtree.insert(point, index, global_tileset_points.len());
+        builder = builder.geoid(geoid);
     }
 
-    global_tileset.root.geometric_error = 0.05
-        * (global_quadtree.bounds.half_width.powf(2.0_f64)
-            + global_quadtree.bounds.half_length.powf(2.0_f64))
-        .sqrt();
+    let job = builder.build()?;
 
-    global_tileset.root.bounding_volume = TileSetRootBoundingVolume::new(&global_quadtree);
+    let plan = job.plan()?;
 
-    global_tileset.geometric_error = global_tileset.root.geometric_error * 5.0;
+    if args.dry_run {
+        print_plan(&plan);
 
-    let root_pnts = package_points(&&global_quadtree);
+        return Ok(());
+    }
 
-    println!("Saving root tile set");
+    // after planning, so a clean overwrite never empties the directory for inputs that cannot be read
+    prepare_output_dir(output_dir, args.overwrite)?;
 
-    let mut pnts_file = std::fs::File::create(Path::new(OUTPUT_DIR).join("root.pnts")).unwrap();
-    pnts_file.write_all(root_pnts.as_slice());
+    let result = job.execute(plan)?;
 
-    let mut tileset_json_file = std::fs::File::create(output_dir.join("tileset.json")).unwrap();
-    tileset_json_file.write_all(
-        serde_json::to_string(&global_tileset)
-            .unwrap()
-            .into_bytes()
-            .as_slice(),
-    );
+    let failed_inputs = &result.stats.failed_inputs;
 
-    println!("SUCCESS: Point cloud 3D tiles created successfully");
-}
+    if !failed_inputs.is_empty() {
+        warn!(
+            "{} of {} LAS/LAZ files failed and were skipped:",
+            failed_inputs.len(),
+            result.stats.input_files
+        );
 
-fn create_tileset_for_file(source_path: &PathBuf, target_path: &PathBuf) -> (TileSet, Vec<Point>) {
-    let mut reader = Reader::from_path(source_path).expect("Can't read LAS file.");
+        for failed_input in failed_inputs {
+            warn!("  {:?}: {}", failed_input.path, failed_input.error);
+        }
+    }
 
-    println!(
-        "Processing LAS file {:?} with {} points",
-        source_path.file_name().unwrap_or_default(),
-        reader.header().number_of_points()
+    info!(
+        "SUCCESS: {} points from {} LAS/LAZ files tiled into {:?}",
+        result.stats.points,
+        result.stats.input_files - failed_inputs.len(),
+        output_dir
     );
 
-    let mut points = vec![];
-
-    let mut bbox = SpatialExtent::default();
+    Ok(())
+}
 
-    for point in reader.points() {
-        if let Ok(las_point) = point {
-            let color = if let Some(color) = las_point.color {
-                color
+fn print_plan(plan: &TilingPlan) {
+    for input in &plan.inputs {
+        println!(
+            "{} -> {}: {} points, CRS {}{}",
+            input.path.display(),
+            input.name,
+            input.number_of_points,
+            input.crs.name,
+            if input.memory_budget.is_some() {
+                ", out of core"
             } else {
-                Color::new(0xffff, 0xffff, 0x0000)
-            };
-
-            let (x, y, z) = geodetic_to_geocentric(las_point.y, las_point.x, las_point.z);
-
-            let point = Point {
-                morton: 0,
-                x,
-                y,
-                z,
-                r: color.red,
-                g: color.green,
-                b: color.blue,
-                classification: u8::from(las_point.classification),
-                is_edge_of_flight_line: las_point.is_edge_of_flight_line,
-                is_synthetic: las_point.is_synthetic,
-                is_key_point: las_point.is_key_point,
-                is_withheld: las_point.is_withheld,
-                is_overlap: las_point.is_overlap,
-            };
-
-            bbox.update(&point);
-
-            points.push(point);
-        }
+                ""
+            }
+        );
     }
 
-    let half_width = (bbox.x_max - bbox.x_min) / 2.0;
-
-    let half_length = (bbox.y_max - bbox.y_min) / 2.0;
-
-    let half_height = (bbox.z_max - bbox.z_min) / 2.0;
-
-    let mut quadtree = QuadTree::new(
-        Aabb {
-            x_center: bbox.x_min + half_width,
-            y_center: bbox.y_min + half_length,
-            z_center: bbox.z_min + half_height,
-            half_width,
-            half_length,
-            half_height,
-        },
-        1,
-        CAPACITY as usize,
-    );
-
-    for mut point in &mut points {
-        let x_norm =
-            (u32::MAX as f64 * (point.x - bbox.x_min) / (bbox.x_max - bbox.x_min)).round() as u32;
-        let y_norm =
-            (u32::MAX as f64 * (point.y - bbox.y_min) / (bbox.y_max - bbox.y_min)).round() as u32;
-        point.morton = morton_encode([x_norm, y_norm]);
+    for failed_input in &plan.failed_inputs {
+        println!(
+            "{}: skipped, {}",
+            failed_input.path.display(),
+            failed_input.error
+        );
     }
 
-    points.par_sort_by(|point1, point2| point1.morton.cmp(&point2.morton));
-
-    let mut points_to_promote = vec![];
-
-    for (index, point) in points.iter().enumerate() {
-        if 4 * points.len() / CAPACITY as usize > 0 {
-            if index % (4 * points.len() / CAPACITY as usize) == 0 {
-                points_to_promote.push(point.to_owned());
-                continue;
+    for dimension in &plan.extra_bytes.dimensions {
+        println!(
+            "extra dimension {}: {:?}{}",
+            dimension.name,
+            dimension.data_type,
+            if dimension.scale.is_some() || dimension.offset.is_some() {
+                ", scaled"
+            } else {
+                ""
             }
-        }
-
-        quadtree.insert(&point, index, points.len());
+        );
     }
 
+    let extent = &plan.extent;
+
     println!(
-        "Creating tile set {:?}",
-        target_path.file_name().unwrap_or_default()
+        "{} points in {} LAS/LAZ files, {:.1} x {:.1} x {:.1} m",
+        plan.number_of_points,
+        plan.inputs.len(),
+        extent.x_max - extent.x_min,
+        extent.y_max - extent.y_min,
+        extent.z_max - extent.z_min
     );
 
-    let tile_set = create_tile(target_path, &quadtree);
-
     println!(
-        "Tile set {:?} created",
-        target_path.file_name().unwrap_or_default()
+        "{:.1} MiB of points in memory{}",
+        plan.memory_estimate() as f64 / (1 << 20) as f64,
+        if plan.memory_budget.is_some() {
+            ", tiled out of core"
+        } else {
+            ""
+        }
     );
-
-    (tile_set, points_to_promote)
 }
 
-fn geodetic_to_geocentric(lat: f64, lon: f64, h: f64) -> (f64, f64, f64) {
-    let lat_rad = lat.to_radians();
-    let lon_rad = lon.to_radians();
-    let t: f64 = 1.0 - 1.0 / 298.257223563;
-    let nn = 6378137.0 / (1.0 - (1.0 - t.powf(2.0)) * lat_rad.sin().powf(2.0)).sqrt();
-    let x = (nn + h) * lat_rad.cos() * lon_rad.cos();
-    let y = (nn + h) * lat_rad.cos() * lon_rad.sin();
-    let z = (t.powf(2.0) * nn + h) * lat_rad.sin();
+fn prepare_output_dir(output_dir: &Path, overwrite: OverwritePolicy) -> Result<()> {
+    if output_dir.exists() {
+        if !output_dir.is_dir() {
+            return Err(Error::InvalidInput(format!(
+                "Output {:?} is not a directory",
+                output_dir
+            )));
+        }
+
+        match overwrite {
+            OverwritePolicy::Fail => {
+                if fs::read_dir(output_dir)?.next().is_some() {
+                    return Err(Error::InvalidInput(format!(
+                        "Output directory {:?} is not empty, use --overwrite to replace it",
+                        output_dir
+                    )));
+                }
+            }
+            OverwritePolicy::Overwrite => {}
+            OverwritePolicy::Clean => fs::remove_dir_all(output_dir)?,
+        }
+    }
 
-    (x, y, z)
+    Ok(fs::create_dir_all(output_dir)?)
 }
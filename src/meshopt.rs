@@ -0,0 +1,260 @@
+// meshoptimizer vertex codec version 0, the ATTRIBUTES mode of EXT_meshopt_compression
+const VERTEX_HEADER: u8 = 0xa0;
+
+const VERTEX_BLOCK_SIZE_BYTES: usize = 8192;
+
+const VERTEX_BLOCK_MAX_SIZE: usize = 256;
+
+const BYTE_GROUP_SIZE: usize = 16;
+
+const TAIL_MIN_SIZE: usize = 32;
+
+// views are stored without vertex filters: LAS points have no normals or rotations for the
+// octahedral and quaternion filters, and positions are quantized with KHR_mesh_quantization instead
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MeshoptOptions {
+    // positions as 16 bit integers with KHR_mesh_quantization, float positions when none
+    pub position_bits: Option<u8>,
+}
+
+// vertices are split into blocks, each byte of the vertex is delta encoded across a block in groups of 16
+pub fn encode_vertex_buffer(data: &[u8], vertex_size: usize) -> Vec<u8> {
+    assert!((4..=256).contains(&vertex_size) && vertex_size & 3 == 0);
+
+    let mut encoded = vec![VERTEX_HEADER];
+
+    let mut first_vertex = data[..vertex_size.min(data.len())].to_vec();
+    first_vertex.resize(vertex_size, 0);
+
+    let mut last_vertex = first_vertex.clone();
+
+    let block_size = ((VERTEX_BLOCK_SIZE_BYTES / vertex_size) & !(BYTE_GROUP_SIZE - 1))
+        .min(VERTEX_BLOCK_MAX_SIZE);
+
+    for block in data.chunks(block_size * vertex_size) {
+        let vertex_count = block.len() / vertex_size;
+        let mut deltas = vec![0u8; (vertex_count + BYTE_GROUP_SIZE - 1) & !(BYTE_GROUP_SIZE - 1)];
+
+        for (byte, last) in last_vertex.iter_mut().enumerate() {
+            for (vertex, delta) in block.chunks_exact(vertex_size).zip(deltas.iter_mut()) {
+                *delta = zigzag(vertex[byte].wrapping_sub(*last));
+                *last = vertex[byte];
+            }
+
+            encode_bytes(&deltas, &mut encoded);
+        }
+    }
+
+    // the first vertex closes the stream, padded so decoders can read the tail without bounds checks
+    encoded.resize(encoded.len() + TAIL_MIN_SIZE.saturating_sub(vertex_size), 0);
+    encoded.extend_from_slice(&first_vertex);
+
+    encoded
+}
+
+fn zigzag(value: u8) -> u8 {
+    ((value as i8 >> 7) as u8) ^ (value << 1)
+}
+
+// a header with two bits per group selects 0, 2, 4 or 8 bits per value, larger values follow the group
+fn encode_bytes(deltas: &[u8], encoded: &mut Vec<u8>) {
+    let header = encoded.len();
+    let groups = deltas.len() / BYTE_GROUP_SIZE;
+
+    encoded.resize(header + groups.div_ceil(4), 0);
+
+    for (index, group) in deltas.chunks_exact(BYTE_GROUP_SIZE).enumerate() {
+        let (bits_log2, bits) = [(0u8, 0), (1, 2), (2, 4)]
+            .iter()
+            .filter_map(|&(bits_log2, bits)| {
+                group_size(group, bits).map(|size| (size, bits_log2, bits))
+            })
+            .filter(|(size, _, _)| *size < BYTE_GROUP_SIZE)
+            .min_by_key(|(size, _, _)| *size)
+            .map_or((3, 8), |(_, bits_log2, bits)| (bits_log2, bits));
+
+        encoded[header + index / 4] |= bits_log2 << ((index % 4) * 2);
+
+        match bits {
+            0 => {}
+            8 => encoded.extend_from_slice(group),
+            _ => {
+                let sentinel = (1u8 << bits) - 1;
+
+                for values in group.chunks_exact(8 / bits) {
+                    encoded.push(
+                        values
+                            .iter()
+                            .fold(0, |byte, value| byte << bits | (*value).min(sentinel)),
+                    );
+                }
+
+                encoded.extend(group.iter().filter(|value| **value >= sentinel));
+            }
+        }
+    }
+}
+
+// none when the values don't fit, zero bits only encode groups of zeros
+fn group_size(group: &[u8], bits: usize) -> Option<usize> {
+    if bits == 0 {
+        return group.iter().all(|value| *value == 0).then_some(0);
+    }
+
+    let sentinel = (1u8 << bits) - 1;
+
+    Some(BYTE_GROUP_SIZE * bits / 8 + group.iter().filter(|value| **value >= sentinel).count())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // the version 0 vertex decoder of meshoptimizer, with its bounds checks, counting the group modes
+    pub(crate) fn decode_vertex_buffer(
+        encoded: &[u8],
+        vertex_count: usize,
+        vertex_size: usize,
+        modes: &mut [usize; 4],
+    ) -> Vec<u8> {
+        assert_eq!(encoded[0], VERTEX_HEADER);
+
+        let tail_size = vertex_size.max(TAIL_MIN_SIZE);
+        let mut last_vertex = encoded[encoded.len() - vertex_size..].to_vec();
+
+        let block_size = ((VERTEX_BLOCK_SIZE_BYTES / vertex_size) & !(BYTE_GROUP_SIZE - 1))
+            .min(VERTEX_BLOCK_MAX_SIZE);
+
+        let mut offset = 1;
+        let mut data = vec![0; vertex_count * vertex_size];
+
+        for block in data.chunks_mut(block_size * vertex_size) {
+            let count = block.len() / vertex_size;
+            let aligned = (count + BYTE_GROUP_SIZE - 1) & !(BYTE_GROUP_SIZE - 1);
+
+            for (byte, last) in last_vertex.iter_mut().enumerate() {
+                let deltas = decode_bytes(encoded, &mut offset, aligned, modes);
+
+                for (vertex, delta) in block.chunks_exact_mut(vertex_size).zip(deltas) {
+                    *last = last.wrapping_add((delta >> 1) ^ (delta & 1).wrapping_neg());
+                    vertex[byte] = *last;
+                }
+            }
+        }
+
+        assert_eq!(encoded.len() - offset, tail_size);
+
+        data
+    }
+
+    fn decode_bytes(
+        encoded: &[u8],
+        offset: &mut usize,
+        size: usize,
+        modes: &mut [usize; 4],
+    ) -> Vec<u8> {
+        let header = *offset;
+        *offset += (size / BYTE_GROUP_SIZE).div_ceil(4);
+
+        let mut deltas = vec![];
+
+        for group in 0..size / BYTE_GROUP_SIZE {
+            // the reference decoder reads groups without bounds checks while this much data is left
+            assert!(encoded.len() - *offset >= TAIL_MIN_SIZE);
+
+            let bits_log2 = (encoded[header + group / 4] >> ((group % 4) * 2)) & 3;
+            modes[bits_log2 as usize] += 1;
+
+            match bits_log2 {
+                0 => deltas.extend_from_slice(&[0; BYTE_GROUP_SIZE]),
+                3 => {
+                    deltas.extend_from_slice(&encoded[*offset..*offset + BYTE_GROUP_SIZE]);
+                    *offset += BYTE_GROUP_SIZE;
+                }
+                _ => {
+                    let bits = 1 << bits_log2;
+                    let sentinel = (1u8 << bits) - 1;
+                    let mut extra = *offset + BYTE_GROUP_SIZE * bits / 8;
+
+                    for index in 0..BYTE_GROUP_SIZE {
+                        let byte = encoded[*offset + index * bits / 8];
+                        let value = byte >> (8 - bits - index * bits % 8) & sentinel;
+
+                        if value == sentinel {
+                            deltas.push(encoded[extra]);
+                            extra += 1;
+                        } else {
+                            deltas.push(value);
+                        }
+                    }
+
+                    *offset = extra;
+                }
+            }
+        }
+
+        deltas
+    }
+
+    fn round_trip(data: &[u8], vertex_size: usize) -> [usize; 4] {
+        let mut modes = [0; 4];
+
+        let encoded = encode_vertex_buffer(data, vertex_size);
+        let decoded =
+            decode_vertex_buffer(&encoded, data.len() / vertex_size, vertex_size, &mut modes);
+
+        assert_eq!(decoded, data);
+
+        modes
+    }
+
+    #[test]
+    fn tail_padding() {
+        for &vertex_size in &[4, 12, 28, 32, 36, 256] {
+            for &vertex_count in &[1, 15, 16, 17, 31, 33, 257, 1000] {
+                let data = (0..vertex_count * vertex_size)
+                    .map(|index| (index * 7 + index / vertex_size) as u8)
+                    .collect::<Vec<_>>();
+
+                round_trip(&data, vertex_size);
+            }
+        }
+    }
+
+    #[test]
+    fn byte_group_modes() {
+        // one group of 16 vertices per byte of the vertex: zeros, 2 bit, 4 bit and 8 bit deltas,
+        // and deltas on the sentinels of the 2 and 4 bit modes
+        let deltas: [fn(usize) -> u8; 6] = [
+            |_| 0,
+            |index| (index % 2) as u8,
+            |index| (index % 2) as u8 * 2,
+            |index| (index % 8) as u8,
+            |index| if index == 5 { 90 } else { 3 },
+            |index| (index * 37) as u8,
+        ];
+
+        let mut data = vec![0u8; 16 * 8];
+
+        for (byte, delta) in deltas.iter().enumerate() {
+            let mut value = 0u8;
+
+            for vertex in 0..16 {
+                value = value.wrapping_add(delta(vertex));
+                data[vertex * 8 + byte] = value;
+            }
+        }
+
+        let modes = round_trip(&data, 8);
+
+        assert!(modes.iter().all(|&count| count > 0));
+    }
+
+    #[test]
+    fn zigzag_extremes() {
+        let data = [0u8, 127, 128, 255, 1, 254, 0, 128].repeat(40);
+
+        round_trip(&data, 4);
+        round_trip(&data, 8);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::quadtree::{Aabb, Point};
+use crate::tree::Tree;
+
+pub struct OcTree {
+    pub capacity: usize,
+    pub bounds: Aabb,
+    pub points: Vec<Point>,
+    pub children: Option<[Box<OcTree>; 8]>,
+    pub depth: u8,
+}
+
+impl OcTree {
+    pub fn new(bounds: Aabb, depth: u8, capacity: usize) -> OcTree {
+        OcTree {
+            capacity,
+            bounds,
+            points: vec![],
+            children: None,
+            depth,
+        }
+    }
+}
+
+impl Tree for OcTree {
+    const DIMENSIONS: u32 = 3;
+
+    fn new(bounds: Aabb, depth: u8, capacity: usize) -> Self {
+        OcTree::new(bounds, depth, capacity)
+    }
+
+    fn bounds(&self) -> &Aabb {
+        &self.bounds
+    }
+
+    fn points(&self) -> &[Point] {
+        &self.points
+    }
+
+    fn children(&self) -> Option<&[Box<Self>]> {
+        self.children.as_ref().map(|children| &children[..])
+    }
+
+    fn leaf(bounds: Aabb, depth: u8, capacity: usize, points: Vec<Point>) -> Self {
+        OcTree {
+            points,
+            ..OcTree::new(bounds, depth, capacity)
+        }
+    }
+
+    fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn points_mut(&mut self) -> &mut Vec<Point> {
+        &mut self.points
+    }
+
+    fn split(&mut self) -> &mut [Box<Self>] {
+        let (bounds, depth, capacity) = (&self.bounds, self.depth, self.capacity);
+
+        let child = |index: usize| {
+            Box::new(OcTree::new(
+                Self::child_bounds(bounds, index),
+                depth + 1,
+                capacity,
+            ))
+        };
+
+        self.children.get_or_insert_with(|| {
+            [
+                child(0),
+                child(1),
+                child(2),
+                child(3),
+                child(4),
+                child(5),
+                child(6),
+                child(7),
+            ]
+        })
+    }
+
+    fn contains(bounds: &Aabb, point: &Point) -> bool {
+        point.x >= bounds.x_center - bounds.half_width
+            && point.x <= bounds.x_center + bounds.half_width
+            && point.y >= bounds.y_center - bounds.half_length
+            && point.y <= bounds.y_center + bounds.half_length
+            && point.z >= bounds.z_center - bounds.half_height
+            && point.z <= bounds.z_center + bounds.half_height
+    }
+
+    // children are in morton order, bit 0 selects the x half, bit 1 the y half and bit 2 the z half
+    fn child_bounds(bounds: &Aabb, index: usize) -> Aabb {
+        let half_width = bounds.half_width / 2.0;
+
+        let half_length = bounds.half_length / 2.0;
+
+        let half_height = bounds.half_height / 2.0;
+
+        let offset = |bit: usize, half: f64| if index & bit == 0 { -half } else { half };
+
+        Aabb {
+            x_center: bounds.x_center + offset(1, half_width),
+            y_center: bounds.y_center + offset(2, half_length),
+            z_center: bounds.z_center + offset(4, half_height),
+            half_width,
+            half_length,
+            half_height,
+        }
+    }
+
+    fn geometric_error(&self) -> f64 {
+        0.05 * (self.bounds.half_width.powf(2.0_f64)
+            + self.bounds.half_length.powf(2.0_f64)
+            + self.bounds.half_height.powf(2.0_f64))
+        .sqrt()
+    }
+
+    fn child_offset(index: usize) -> [u32; 3] {
+        [
+            index as u32 & 1,
+            (index as u32 >> 1) & 1,
+            (index as u32 >> 2) & 1,
+        ]
+    }
+}
@@ -1,3 +1,5 @@
+use crate::tree::Tree;
+
 #[derive(Clone, Debug)]
 pub struct Point {
     pub morton: u64,
@@ -7,12 +9,56 @@ pub struct Point {
     pub r: u16,
     pub g: u16,
     pub b: u16,
+    // false when the input has no colors and the point has the fallback color
+    pub has_color: bool,
+    pub intensity: u16,
     pub classification: u8,
     pub is_edge_of_flight_line: bool,
     pub is_synthetic: bool,
     pub is_key_point: bool,
     pub is_withheld: bool,
     pub is_overlap: bool,
+    pub return_number: u8,
+    pub number_of_returns: u8,
+    pub scan_angle: f32,
+    pub scan_direction_flag: bool,
+    pub gps_time: f64,
+    pub point_source_id: u16,
+    pub user_data: u8,
+    // in the extra bytes layout of the job, empty without extra dimensions
+    pub extra_bytes: Vec<u8>,
+}
+
+// a single return without color or other attributes, the base of the points built by tests
+#[cfg(test)]
+impl Point {
+    pub fn at(x: f64, y: f64, z: f64) -> Point {
+        Point {
+            morton: 0,
+            x,
+            y,
+            z,
+            r: 0,
+            g: 0,
+            b: 0,
+            has_color: false,
+            intensity: 0,
+            classification: 0,
+            is_edge_of_flight_line: false,
+            is_synthetic: false,
+            is_key_point: false,
+            is_withheld: false,
+            is_overlap: false,
+            return_number: 1,
+            number_of_returns: 1,
+            scan_angle: 0.0,
+            scan_direction_flag: false,
+            gps_time: 0.0,
+            point_source_id: 0,
+            user_data: 0,
+            extra_bytes: vec![],
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -43,115 +89,95 @@ impl QuadTree {
             depth,
         }
     }
+}
+
+impl Tree for QuadTree {
+    const DIMENSIONS: u32 = 2;
 
-    pub fn insert(&mut self, point: &Point, index: usize, number_of_points: usize) {
-        if point.x >= self.bounds.x_center - self.bounds.half_width
-            && point.x < self.bounds.x_center + self.bounds.half_width
-            && point.y >= self.bounds.y_center - self.bounds.half_length
-            && point.y < self.bounds.y_center + self.bounds.half_length
-        {
-            let step = number_of_points / (self.capacity * 2_usize.pow((self.depth - 1) as u32));
-            if number_of_points / (self.capacity * 2_usize.pow((self.depth - 1) as u32)) > 4
-                && step > 0
-            {
-                if (index + 1 - self.depth as usize) % step == 0 {
-                    self.points.push(point.to_owned());
-                } else {
-                    if let Some(children) = &mut self.children {
-                        for child in children {
-                            child.insert(point, index, number_of_points);
-                        }
-                    } else {
-                        self.split();
-
-                        if let Some(children) = &mut self.children {
-                            for child in children {
-                                child.insert(point, index, number_of_points);
-                            }
-                        }
-                    }
-                }
-            } else {
-                if self.points.len() < self.capacity {
-                    self.points.push(point.to_owned());
-                } else {
-                    if let Some(children) = &mut self.children {
-                        for child in children {
-                            child.insert(point, index, number_of_points);
-                        }
-                    } else {
-                        self.split();
-
-                        if let Some(children) = &mut self.children {
-                            for child in children {
-                                child.insert(point, index, number_of_points);
-                            }
-                        }
-                    }
-                }
-            }
+    fn new(bounds: Aabb, depth: u8, capacity: usize) -> Self {
+        QuadTree::new(bounds, depth, capacity)
+    }
+
+    fn bounds(&self) -> &Aabb {
+        &self.bounds
+    }
+
+    fn points(&self) -> &[Point] {
+        &self.points
+    }
+
+    fn children(&self) -> Option<&[Box<Self>]> {
+        self.children.as_ref().map(|children| &children[..])
+    }
+
+    fn leaf(bounds: Aabb, depth: u8, capacity: usize, points: Vec<Point>) -> Self {
+        QuadTree {
+            points,
+            ..QuadTree::new(bounds, depth, capacity)
         }
     }
 
-    fn split(&mut self) {
-        let half_width = self.bounds.half_width / 2.0;
+    fn depth(&self) -> u8 {
+        self.depth
+    }
 
-        let half_length = self.bounds.half_length / 2.0;
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
 
-        let depth = self.depth + 1;
+    fn points_mut(&mut self) -> &mut Vec<Point> {
+        &mut self.points
+    }
 
-        let tl = QuadTree::new(
-            Aabb {
-                x_center: self.bounds.x_center - half_width,
-                y_center: self.bounds.y_center + half_length,
-                z_center: self.bounds.z_center,
-                half_width,
-                half_length,
-                half_height: self.bounds.half_height,
-            },
-            depth,
-            self.capacity,
-        );
-
-        let tr = QuadTree::new(
-            Aabb {
-                x_center: self.bounds.x_center + half_width,
-                y_center: self.bounds.y_center + half_length,
-                z_center: self.bounds.z_center,
-                half_width,
-                half_length,
-                half_height: self.bounds.half_height,
-            },
-            depth,
-            self.capacity,
-        );
-
-        let bl = QuadTree::new(
-            Aabb {
-                x_center: self.bounds.x_center - half_width,
-                y_center: self.bounds.y_center - half_length,
-                z_center: self.bounds.z_center,
-                half_width,
-                half_length,
-                half_height: self.bounds.half_height,
+    fn split(&mut self) -> &mut [Box<Self>] {
+        let (bounds, depth, capacity) = (&self.bounds, self.depth, self.capacity);
+
+        let child = |index: usize| {
+            Box::new(QuadTree::new(
+                Self::child_bounds(bounds, index),
+                depth + 1,
+                capacity,
+            ))
+        };
+
+        self.children
+            .get_or_insert_with(|| [child(0), child(1), child(2), child(3)])
+    }
+
+    fn contains(bounds: &Aabb, point: &Point) -> bool {
+        point.x >= bounds.x_center - bounds.half_width
+            && point.x <= bounds.x_center + bounds.half_width
+            && point.y >= bounds.y_center - bounds.half_length
+            && point.y <= bounds.y_center + bounds.half_length
+    }
+
+    // children are split top left, top right, bottom left, bottom right
+    fn child_bounds(bounds: &Aabb, index: usize) -> Aabb {
+        let half_width = bounds.half_width / 2.0;
+
+        let half_length = bounds.half_length / 2.0;
+
+        Aabb {
+            x_center: match index {
+                0 | 2 => bounds.x_center - half_width,
+                _ => bounds.x_center + half_width,
             },
-            depth,
-            self.capacity,
-        );
-
-        let br = QuadTree::new(
-            Aabb {
-                x_center: self.bounds.x_center + half_width,
-                y_center: self.bounds.y_center - half_length,
-                z_center: self.bounds.z_center,
-                half_width,
-                half_length,
-                half_height: self.bounds.half_height,
+            y_center: match index {
+                0 | 1 => bounds.y_center + half_length,
+                _ => bounds.y_center - half_length,
             },
-            depth,
-            self.capacity,
-        );
+            z_center: bounds.z_center,
+            half_width,
+            half_length,
+            half_height: bounds.half_height,
+        }
+    }
+
+    fn geometric_error(&self) -> f64 {
+        0.05 * (self.bounds.half_width.powf(2.0_f64) + self.bounds.half_length.powf(2.0_f64)).sqrt()
+    }
 
-        self.children = Some([Box::new(tl), Box::new(tr), Box::new(bl), Box::new(br)]);
+    fn child_offset(index: usize) -> [u32; 3] {
+        [[0, 1, 0], [1, 1, 0], [0, 0, 0], [1, 0, 0]][index]
     }
 }
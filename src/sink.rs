@@ -0,0 +1,33 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub trait OutputSink: Sync {
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+}
+
+pub struct DirectorySink {
+    root: PathBuf,
+}
+
+impl DirectorySink {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl OutputSink for DirectorySink {
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let target = self.root.join(path);
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(target, contents)
+    }
+}
@@ -1,5 +1,6 @@
-use crate::quadtree::Point;
+use crate::quadtree::{Aabb, Point};
 
+#[derive(Clone, Debug)]
 pub struct SpatialExtent {
     pub x_min: f64,
     pub x_max: f64,
@@ -48,4 +49,52 @@ impl SpatialExtent {
             self.z_max = point.z;
         }
     }
+
+    pub fn include(&mut self, x: f64, y: f64, z: f64) {
+        self.x_min = self.x_min.min(x);
+        self.x_max = self.x_max.max(x);
+        self.y_min = self.y_min.min(y);
+        self.y_max = self.y_max.max(y);
+        self.z_min = self.z_min.min(z);
+        self.z_max = self.z_max.max(z);
+    }
+
+    pub fn union(&mut self, other: &SpatialExtent) {
+        if other.is_valid() {
+            self.include(other.x_min, other.y_min, other.z_min);
+            self.include(other.x_max, other.y_max, other.z_max);
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.x_min.is_finite()
+            && self.x_max.is_finite()
+            && self.y_min.is_finite()
+            && self.y_max.is_finite()
+            && self.z_min.is_finite()
+            && self.z_max.is_finite()
+            && self.x_min <= self.x_max
+            && self.y_min <= self.y_max
+            && self.z_min <= self.z_max
+    }
+
+    // grown by the rounding error of center plus half size, so the extreme points stay inside
+    pub fn bounds(&self) -> Aabb {
+        let padding = |min: f64, max: f64| 4.0 * f64::EPSILON * min.abs().max(max.abs());
+
+        let half_width = (self.x_max - self.x_min) / 2.0;
+
+        let half_length = (self.y_max - self.y_min) / 2.0;
+
+        let half_height = (self.z_max - self.z_min) / 2.0;
+
+        Aabb {
+            x_center: self.x_min + half_width,
+            y_center: self.y_min + half_length,
+            z_center: self.z_min + half_height,
+            half_width: half_width + padding(self.x_min, self.x_max),
+            half_length: half_length + padding(self.y_min, self.y_max),
+            half_height: half_height + padding(self.z_min, self.z_max),
+        }
+    }
 }
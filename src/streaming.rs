@@ -0,0 +1,617 @@
+use crate::error::{Error, Result};
+use crate::external_sort::{fan_in, sort, SpillDirectory, SpillFile};
+use crate::implicit::{write_content, write_implicit_tileset, TileCoordinates};
+use crate::quadtree::{Aabb, Point};
+use crate::spatial_extent::SpatialExtent;
+use crate::tiles::{write_tile, TileContext, TileSet, TileSetRoot, TileSetRootChild};
+use crate::tree::Tree;
+use log::{debug, info};
+use std::collections::BTreeMap;
+use std::env;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+// a node on the path from the root to the node receiving points, its children before it are written
+struct OpenNode {
+    bounds: Aabb,
+    depth: u8,
+    index: usize,
+    directory: PathBuf,
+    coordinates: TileCoordinates,
+    points: Vec<Point>,
+    children: Option<Vec<TileSetRootChild>>,
+    closed: u8,
+}
+
+// builds the same tree as `Tree::insert` from points in path key order, writing every node once its
+// subtree is complete so only the open path is held in memory
+struct StreamingTree<'a, T: Tree> {
+    context: &'a TileContext<'a>,
+    base_dir: &'a Path,
+    number_of_points: usize,
+    path: Vec<OpenNode>,
+    // the points held by the open path and how many the memory budget leaves room for
+    held: usize,
+    limit: usize,
+    tiles: BTreeMap<TileCoordinates, bool>,
+    tree: PhantomData<T>,
+}
+
+impl<'a, T: Tree> StreamingTree<'a, T> {
+    fn new(
+        context: &'a TileContext<'a>,
+        base_dir: &'a Path,
+        bounds: Aabb,
+        number_of_points: usize,
+        limit: usize,
+    ) -> Self {
+        Self {
+            context,
+            base_dir,
+            number_of_points,
+            path: vec![OpenNode {
+                bounds,
+                depth: 1,
+                index: 0,
+                directory: base_dir.to_owned(),
+                coordinates: TileCoordinates::root(),
+                points: vec![],
+                children: None,
+                closed: 0,
+            }],
+            held: 0,
+            limit,
+            tiles: BTreeMap::new(),
+            tree: PhantomData,
+        }
+    }
+
+    fn insert(&mut self, point: Point, index: usize) -> Result<()> {
+        let capacity = self.context.options.capacity;
+
+        if !T::contains(&self.path[0].bounds, &point) {
+            return Ok(());
+        }
+
+        let mut level = 0;
+
+        loop {
+            let node = &self.path[level];
+
+            if T::keeps(
+                node.depth,
+                capacity,
+                node.points.len(),
+                index,
+                self.number_of_points,
+            ) || !T::splits(node.depth, &node.bounds)
+            {
+                return self.keep(level, point);
+            }
+
+            let child = (0..1 << T::DIMENSIONS)
+                .find(|&child| T::contains(&T::child_bounds(&node.bounds, child), &point));
+
+            let child = match child {
+                Some(child) => child,
+                None => return Ok(()),
+            };
+
+            if self.path.len() > level + 1 && self.path[level + 1].index != child {
+                self.close(level + 1)?;
+            }
+
+            if self.path.len() == level + 1 {
+                let node = &mut self.path[level];
+
+                // only reachable below the resolution of the path key, the point stays with its parent
+                if node.closed & 1 << child != 0 {
+                    debug!("Keeping a point out of order at depth {}", node.depth);
+
+                    return self.keep(level, point);
+                }
+
+                node.children.get_or_insert_with(Vec::new);
+
+                let child = OpenNode {
+                    bounds: T::child_bounds(&node.bounds, child),
+                    depth: node.depth + 1,
+                    index: child,
+                    directory: node.directory.join(child.to_string()),
+                    coordinates: node.coordinates.child(T::child_offset(child)),
+                    points: vec![],
+                    children: None,
+                    closed: 0,
+                };
+
+                self.path.push(child);
+            }
+
+            level += 1;
+        }
+    }
+
+    // nodes hold at most a tile of points unless they cannot be split, which many coincident points
+    // can make exceed the budget
+    fn keep(&mut self, level: usize, point: Point) -> Result<()> {
+        self.path[level].points.push(point);
+        self.held += 1;
+
+        if self.held > self.limit {
+            return Err(Error::InvalidInput(format!(
+                "The open tiles hold more than the {} points the memory budget leaves room for, \
+                 raise the budget",
+                self.limit
+            )));
+        }
+
+        Ok(())
+    }
+
+    // writes the open nodes from the deepest up to `level`, handing their summaries to their parents
+    fn close(&mut self, level: usize) -> Result<()> {
+        while self.path.len() > level.max(1) {
+            let node = self.path.pop().expect("open path is not empty");
+            let index = node.index;
+
+            self.held -= node.points.len();
+
+            let child = self.write(node)?;
+
+            let parent = self.path.last_mut().expect("open path has a root");
+
+            parent.closed |= 1 << index;
+
+            if let (Some(children), Some(child)) = (&mut parent.children, child) {
+                children.push(child);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write(&mut self, node: OpenNode) -> Result<Option<TileSetRootChild>> {
+        let context = self.context;
+        let tree = T::leaf(
+            node.bounds,
+            node.depth,
+            context.options.capacity,
+            node.points,
+        );
+
+        if context.options.implicit_tiling {
+            write_content(
+                context,
+                self.base_dir,
+                &tree,
+                node.coordinates,
+                &mut self.tiles,
+            )?;
+
+            return Ok(None);
+        }
+
+        let root = TileSetRoot::with_children(&tree, node.children, context);
+
+        // a node is only opened for a point kept by it or by a node below it, so it is never empty
+        write_tile(context, &node.directory, &tree, root)?;
+
+        Ok(Some(TileSetRootChild::new(node.index, &tree, context)))
+    }
+
+    fn finish(mut self) -> Result<TileSet> {
+        self.close(1)?;
+
+        let root = self.path.pop().expect("open path has a root");
+        let context = self.context;
+        let subdivided = root.children.is_some();
+        let tree = T::leaf(root.bounds, 1, context.options.capacity, root.points);
+
+        if context.options.implicit_tiling {
+            write_content(
+                context,
+                self.base_dir,
+                &tree,
+                root.coordinates,
+                &mut self.tiles,
+            )?;
+
+            return write_implicit_tileset(context, self.base_dir, &tree, subdivided, &self.tiles);
+        }
+
+        let mut tile_set_root = TileSetRoot::with_children(&tree, root.children, context);
+        tile_set_root.transform = context.transform;
+
+        write_tile(context, self.base_dir, &tree, tile_set_root)
+    }
+}
+
+pub fn spill_directory(context: &TileContext) -> Result<SpillDirectory> {
+    let extra_bytes = context.extra_bytes.record_size();
+
+    match &context.options.temp_dir {
+        Some(temp_dir) => SpillDirectory::new(temp_dir, extra_bytes),
+        None => SpillDirectory::new(&env::temp_dir(), extra_bytes),
+    }
+}
+
+pub fn spill<I>(directory: &SpillDirectory, points: I) -> Result<(SpillFile, SpatialExtent)>
+where
+    I: Iterator<Item = Result<Point>>,
+{
+    let mut bbox = SpatialExtent::default();
+
+    let mut writer = directory.writer()?;
+
+    for point in points {
+        let point = point?;
+
+        bbox.update(&point);
+
+        writer.push(&point)?;
+    }
+
+    Ok((writer.finish()?, bbox))
+}
+
+// points are spilled to disk, sorted in runs that fit in the memory budget and merged while the tree is written
+pub fn create_tileset_out_of_core<T, I>(
+    points: I,
+    source_path: &Path,
+    target_path: &Path,
+    context: &TileContext,
+    memory_budget: usize,
+) -> Result<(TileSet, Vec<Point>, usize, SpatialExtent)>
+where
+    T: Tree,
+    I: Iterator<Item = Result<Point>>,
+{
+    let directory = spill_directory(context)?;
+
+    let (spilled, bbox) = spill(&directory, points)?;
+
+    if spilled.is_empty() || !bbox.is_valid() {
+        return Err(Error::InvalidBounds(format!(
+            "{:?} contains no points with finite coordinates",
+            source_path.file_name().unwrap_or_default()
+        )));
+    }
+
+    info!(
+        "Creating tile set {:?}",
+        target_path.file_name().unwrap_or_default()
+    );
+
+    let (tile_set, points_to_promote, number_of_points) = build_out_of_core::<T>(
+        &directory,
+        vec![spilled],
+        bbox.bounds(),
+        target_path,
+        context,
+        memory_budget,
+        true,
+    )?;
+
+    info!(
+        "Tile set {:?} created",
+        target_path.file_name().unwrap_or_default()
+    );
+
+    Ok((tile_set, points_to_promote, number_of_points, bbox))
+}
+
+// with `promote`, every few points are returned for the parent tile set instead of being tiled
+pub fn build_out_of_core<T: Tree>(
+    directory: &SpillDirectory,
+    inputs: Vec<SpillFile>,
+    bounds: Aabb,
+    target_path: &Path,
+    context: &TileContext,
+    memory_budget: usize,
+    promote: bool,
+) -> Result<(TileSet, Vec<Point>, usize)> {
+    let number_of_points = inputs.iter().map(SpillFile::len).sum();
+    let point_size = context.extra_bytes.point_size();
+
+    // the open path holds up to a tile of points on every level, down to the resolution of the path
+    // key; only nodes that cannot be split hold more, which the tree checks as it goes
+    let open_tiles = (T::MAX_DEPTH as usize)
+        .saturating_mul(context.options.capacity)
+        .min(number_of_points)
+        .saturating_mul(point_size);
+
+    let sort_budget = memory_budget
+        .checked_sub(open_tiles)
+        .filter(|&sort_budget| fan_in(sort_budget / 4) >= 2)
+        .ok_or_else(|| {
+            Error::InvalidInput(format!(
+                "A memory budget of {} bytes per thread is too small for the {} bytes of open tiles \
+                 and the sort buffers, raise the budget or lower the tile capacity",
+                memory_budget, open_tiles
+            ))
+        })?;
+
+    // of the rest, half holds a run being sorted and a quarter the merge buffers
+    let run_length = sort_budget / 2 / point_size;
+
+    let merged = sort(
+        directory,
+        inputs,
+        run_length,
+        fan_in(sort_budget / 4),
+        |point| T::path_key(&bounds, point),
+    )?;
+
+    // while the tree is written only the merge buffers are held besides the open path
+    let limit = (memory_budget - sort_budget / 4) / point_size;
+
+    let mut tree = StreamingTree::<T>::new(context, target_path, bounds, number_of_points, limit);
+
+    let mut points_to_promote = vec![];
+
+    let promote_step = if promote {
+        4 * number_of_points / context.options.capacity
+    } else {
+        0
+    };
+
+    for (index, point) in merged.enumerate() {
+        let point = point?;
+
+        if promote_step > 0 && index % promote_step == 0 {
+            points_to_promote.push(point);
+            continue;
+        }
+
+        tree.insert(point, index)?;
+    }
+
+    Ok((tree.finish()?, points_to_promote, number_of_points))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extra_bytes::ExtraBytesSchema;
+    use crate::frame::LocalFrame;
+    use crate::job::{build_tree, TilingOptions};
+    use crate::octree::OcTree;
+    use crate::quadtree::QuadTree;
+    use crate::sink::OutputSink;
+    use std::convert::TryInto;
+    use std::io;
+    use std::sync::Mutex;
+
+    // the positions of the points of every written tile by directory, relative to its RTC center
+    #[derive(Default)]
+    struct TileSink {
+        tiles: Mutex<BTreeMap<PathBuf, Vec<[u32; 3]>>>,
+    }
+
+    impl OutputSink for TileSink {
+        fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "pnts")
+            {
+                let directory = path.parent().unwrap_or(Path::new("")).to_owned();
+
+                self.tiles
+                    .lock()
+                    .unwrap()
+                    .insert(directory, pnts_positions(contents));
+            }
+
+            Ok(())
+        }
+    }
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn pnts_positions(contents: &[u8]) -> Vec<[u32; 3]> {
+        let json_length = u32_at(contents, 12) as usize;
+        let feature_table: serde_json::Value =
+            serde_json::from_slice(&contents[28..28 + json_length]).unwrap();
+
+        let points_length = feature_table["POINTS_LENGTH"].as_u64().unwrap() as usize;
+        let binary = &contents[28 + json_length..];
+
+        let mut positions = (0..points_length)
+            .map(|index| [0, 1, 2].map(|axis| u32_at(binary, index * 12 + axis * 4)))
+            .collect::<Vec<_>>();
+
+        positions.sort_unstable();
+        positions
+    }
+
+    fn tree_tiles<T: Tree>(
+        tree: &T,
+        directory: PathBuf,
+        tiles: &mut BTreeMap<PathBuf, Vec<[u32; 3]>>,
+    ) {
+        let bounds = tree.bounds();
+
+        if !tree.points().is_empty() {
+            let mut positions = tree
+                .points()
+                .iter()
+                .map(|point| {
+                    [
+                        ((point.x - bounds.x_center as f32 as f64) as f32).to_bits(),
+                        ((point.y - bounds.y_center as f32 as f64) as f32).to_bits(),
+                        ((point.z - bounds.z_center as f32 as f64) as f32).to_bits(),
+                    ]
+                })
+                .collect::<Vec<_>>();
+
+            positions.sort_unstable();
+            tiles.insert(directory.clone(), positions);
+        }
+
+        for (index, child) in tree.children().into_iter().flatten().enumerate() {
+            tree_tiles(child.as_ref(), directory.join(index.to_string()), tiles);
+        }
+    }
+
+    // a clustered cloud, so the tree is deeper in some places than others
+    fn cloud(number_of_points: usize) -> Vec<Point> {
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut random = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        };
+
+        (0..number_of_points)
+            .map(|index| {
+                let spread = if index % 3 == 0 { 100.0 } else { 10.0 };
+
+                Point::at(
+                    20.0 + spread * random(),
+                    30.0 + spread * random(),
+                    5.0 + spread * random() / 10.0,
+                )
+            })
+            .collect()
+    }
+
+    const CAPACITY: usize = 50;
+
+    // the positions of the tiles written by the out of core tree
+    fn out_of_core<T: Tree>(
+        points: &[Point],
+        bbox: &SpatialExtent,
+        memory_budget: usize,
+    ) -> Result<BTreeMap<PathBuf, Vec<[u32; 3]>>> {
+        let sink = TileSink::default();
+        let frame = LocalFrame::new(6378137.0, 0.0, 0.0);
+        let options = TilingOptions {
+            capacity: CAPACITY,
+            ..TilingOptions::default()
+        };
+        let extra_bytes = ExtraBytesSchema::default();
+        let context = TileContext {
+            sink: &sink,
+            frame: &frame,
+            options: &options,
+            extra_bytes: &extra_bytes,
+            transform: None,
+        };
+
+        let directory = SpillDirectory::new(&env::temp_dir(), 0)?;
+        let (spilled, _) = spill(&directory, points.iter().cloned().map(Ok))?;
+
+        build_out_of_core::<T>(
+            &directory,
+            vec![spilled],
+            bbox.bounds(),
+            Path::new(""),
+            &context,
+            memory_budget,
+            false,
+        )?;
+
+        Ok(sink.tiles.into_inner().unwrap())
+    }
+
+    fn extent(points: &[Point]) -> SpatialExtent {
+        let mut bbox = SpatialExtent::default();
+
+        for point in points {
+            bbox.update(point);
+        }
+
+        bbox
+    }
+
+    fn assert_same_tiles<T: Tree>(points: Vec<Point>) -> BTreeMap<PathBuf, Vec<[u32; 3]>> {
+        let bbox = extent(&points);
+
+        // a budget small enough for the sort to spill runs and merge them
+        let tiles = out_of_core::<T>(&points, &bbox, 1 << 20).unwrap();
+
+        let (tree, _) = build_tree::<T>(points, &bbox, CAPACITY, false);
+
+        let mut expected = BTreeMap::new();
+        tree_tiles(&tree, PathBuf::new(), &mut expected);
+
+        assert!(expected.len() > 10);
+        assert_eq!(
+            tiles.keys().collect::<Vec<_>>(),
+            expected.keys().collect::<Vec<_>>()
+        );
+
+        for (directory, positions) in &expected {
+            assert_eq!(
+                &tiles[directory], positions,
+                "points of tile {:?}",
+                directory
+            );
+        }
+
+        tiles
+    }
+
+    #[test]
+    fn streaming_quadtree_matches_the_in_memory_tree() {
+        assert_same_tiles::<QuadTree>(cloud(5000));
+    }
+
+    #[test]
+    fn streaming_octree_matches_the_in_memory_tree() {
+        assert_same_tiles::<OcTree>(cloud(5000));
+    }
+
+    // a lone point next to many at the origin, the tree descends to the resolution of the path key
+    fn coincident(number_of_points: usize) -> Vec<Point> {
+        let mut points = vec![Point::at(0.0, 0.0, 0.0); number_of_points];
+        points.push(Point::at(1.0, 1.0, 1.0));
+        points
+    }
+
+    #[test]
+    fn streaming_quadtree_stops_at_the_path_key_resolution() {
+        assert_same_tiles::<QuadTree>(coincident(5000));
+    }
+
+    #[test]
+    fn streaming_octree_stops_at_the_path_key_resolution() {
+        assert_same_tiles::<OcTree>(coincident(5000));
+    }
+
+    // a far outlier puts the cloud deep in the tree, where sampling alone would keep many points
+    fn skewed(number_of_points: usize) -> Vec<Point> {
+        let mut points = cloud(number_of_points);
+        points.push(Point::at(20.0 + 1e5, 30.0 + 1e5, 5.0 + 1e3));
+        points
+    }
+
+    #[test]
+    fn skewed_tiles_hold_at_most_a_tile_of_points() {
+        for tiles in [
+            assert_same_tiles::<QuadTree>(skewed(20_000)),
+            assert_same_tiles::<OcTree>(skewed(20_000)),
+        ] {
+            for (directory, positions) in &tiles {
+                assert!(
+                    positions.len() <= CAPACITY,
+                    "points of tile {:?}",
+                    directory
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn coincident_points_beyond_the_budget_fail() {
+        let points = coincident(20_000);
+
+        match out_of_core::<QuadTree>(&points, &extent(&points), 1 << 20) {
+            Err(Error::InvalidInput(message)) => assert!(message.contains("raise the budget")),
+            result => panic!("unexpected result {:?}", result.map(|tiles| tiles.len())),
+        }
+    }
+}
@@ -1,15 +1,30 @@
-use crate::quadtree::{Aabb, QuadTree};
-use las::{Color, Point};
+use crate::attributes::{gps_time_range, point_attributes, OptionalAttribute};
+use crate::crs::WGS84;
+use crate::draco::DracoPointCloud;
+use crate::error::Result;
+use crate::extra_bytes::ExtraBytesSchema;
+use crate::frame::LocalFrame;
+use crate::glb::package_glb;
+use crate::job::TilingOptions;
+use crate::quadtree::{Aabb, Point};
+use crate::sink::OutputSink;
+use crate::tree::Tree;
+use log::debug;
 use serde::Serialize;
-use std::borrow::BorrowMut;
-use std::collections::{BTreeMap, HashMap};
-use std::io::Write;
-use std::path::{Path, PathBuf};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::f64::consts::{PI, TAU};
+use std::path::Path;
 
 const MAGIC: &str = "pnts";
 
 const VERSION: u32 = 1;
 
+// largest value of a 16 bit quantized position component
+const QUANTIZED_STEPS: f64 = 65535.0;
+
+const DRACO_EXTENSION: &str = "3DTILES_draco_point_compression";
+
 #[derive(Debug)]
 pub struct Header<'a> {
     pub magic: &'a str,
@@ -31,9 +46,26 @@ pub struct AttributePosition {
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub struct FeatureTableHeader {
     pub points_length: u32,
-    pub rtc_center: Vec<f32>,
-    pub position: AttributePosition,
-    pub rgb: AttributePosition,
+    // float32 values held as f64, which JSON numbers are read as, so clients read them exactly
+    pub rtc_center: Vec<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<AttributePosition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position_quantized: Option<AttributePosition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantized_volume_offset: Option<[f64; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantized_volume_scale: Option<[f64; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rgb: Option<AttributePosition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rgba: Option<AttributePosition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rgb565: Option<AttributePosition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub constant_rgba: Option<[u8; 4]>,
+    #[serde(rename = "extensions", skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<serde_json::Value>,
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -46,10 +78,38 @@ pub struct TileSetRootContent {
     pub uri: String,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoundingVolumeType {
+    Box,
+    Region,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContentFormat {
+    Pnts,
+    Glb,
+}
+
+// per-point colors in pnts content
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorEncoding {
+    Rgb,
+    Rgba(AlphaSource),
+    Rgb565,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlphaSource {
+    Intensity,
+    UserData,
+}
+
 #[derive(Serialize, Debug, Clone)]
 pub struct TileSetRootBoundingVolume {
-    #[serde(rename = "box")]
-    pub bbox: [f64; 12],
+    #[serde(rename = "box", skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<[f64; 12]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<[f64; 6]>,
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -59,24 +119,70 @@ pub struct TileSetRootChild {
     pub bounding_volume: TileSetRootBoundingVolume,
     pub geometric_error: f64,
     pub refine: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extras: Option<TileExtras>,
+}
+
+// summaries of the content of a tile, for viewers to filter tiles by
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TileExtras {
+    pub gps_time: TimeRange,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TimeRange {
+    pub minimum: f64,
+    pub maximum: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImplicitTiling {
+    pub subdivision_scheme: String,
+    pub subtree_levels: u32,
+    pub available_levels: u32,
+    pub subtrees: TileSetRootContent,
 }
 
 #[derive(Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct TileSetRoot {
-    pub content: TileSetRootContent,
+    // none for a node without points of its own above nodes with points
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<TileSetRootContent>,
     pub bounding_volume: TileSetRootBoundingVolume,
     pub geometric_error: f64,
     pub refine: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub children: Option<Vec<TileSetRootChild>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transform: Option<[f64; 16]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub implicit_tiling: Option<ImplicitTiling>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extras: Option<TileExtras>,
 }
 
 #[derive(Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct TileSet {
-    pub(crate) asset: TileSetAsset,
-    pub(crate) geometric_error: f64,
-    pub(crate) root: TileSetRoot,
+    pub asset: TileSetAsset,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extensions_used: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extensions_required: Option<Vec<String>>,
+    pub geometric_error: f64,
+    pub root: TileSetRoot,
+}
+
+pub struct TileContext<'a> {
+    pub sink: &'a dyn OutputSink,
+    pub frame: &'a LocalFrame,
+    pub options: &'a TilingOptions,
+    pub extra_bytes: &'a ExtraBytesSchema,
+    // set when the tile set written is the top level one of the job
+    pub transform: Option<[f64; 16]>,
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -88,256 +194,470 @@ pub struct BatchTableAttribute {
     byte_offset: u32,
 }
 
+#[derive(Serialize, Debug)]
+pub struct BatchTableHeader {
+    #[serde(flatten)]
+    pub properties: BTreeMap<String, BatchTableAttribute>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<serde_json::Value>,
+}
+
+impl ContentFormat {
+    pub fn content_uri(&self) -> &'static str {
+        match self {
+            ContentFormat::Pnts => "root.pnts",
+            ContentFormat::Glb => "root.glb",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ContentFormat::Pnts => "pnts",
+            ContentFormat::Glb => "glb",
+        }
+    }
+}
+
 impl TileSetRootBoundingVolume {
-    pub fn new(quadtree: &QuadTree) -> Self {
-        let aabb = &quadtree.bounds;
+    pub fn new<T: Tree>(tree: &T, context: &TileContext) -> Self {
+        match context.options.bounding_volume {
+            BoundingVolumeType::Box => Self::from_bounds(tree.bounds()),
+            BoundingVolumeType::Region => Self::region(tree, context.frame),
+        }
+    }
 
-        if let Some(first_point) = &quadtree.points.get(0) {
-            let mut z_min = first_point.z;
+    // axis aligned in the local frame, which the root transform orients on the globe; the full node
+    // bounds, so the box encloses the content of every descendant for additive refinement and
+    // implicit tiling can subdivide it into the boxes of the children
+    pub fn from_bounds(bounds: &Aabb) -> Self {
+        Self {
+            bbox: Some([
+                bounds.x_center,
+                bounds.y_center,
+                bounds.z_center,
+                bounds.half_width,
+                0.0,
+                0.0,
+                0.0,
+                bounds.half_length,
+                0.0,
+                0.0,
+                0.0,
+                bounds.half_height,
+            ]),
+            region: None,
+        }
+    }
 
-            let mut z_max = first_point.z;
+    // west, south, east, north in radians and heights above the ellipsoid, ignoring the transform; from
+    // the node bounds, so the region encloses the content of every descendant for additive refinement
+    fn region<T: Tree>(tree: &T, frame: &LocalFrame) -> Self {
+        Self::enclosing_region(surface_samples(tree.bounds()), frame)
+    }
 
-            for p in &quadtree.points {
-                if p.z < z_min {
-                    z_min = p.z;
-                } else if p.z > z_max {
-                    z_max = p.z;
-                }
-            }
+    fn enclosing_region<I>(positions: I, frame: &LocalFrame) -> Self
+    where
+        I: IntoIterator<Item = (f64, f64, f64)>,
+    {
+        let mut region = [f64::MAX, f64::MAX, f64::MIN, f64::MIN, f64::MAX, f64::MIN];
+
+        // the same longitudes in [0, 2π), across the antimeridian their span is the smaller one
+        let mut shifted = (f64::MAX, f64::MIN);
+
+        for (x, y, z) in positions {
+            let (x, y, z) = frame.to_ecef(x, y, z);
+            let (lat, lon, h) = WGS84.geocentric_to_geodetic(x, y, z);
+            let (lat, lon) = (lat.to_radians(), lon.to_radians());
+            let shifted_lon = if lon < 0.0 { lon + TAU } else { lon };
+
+            region[0] = region[0].min(lon);
+            region[1] = region[1].min(lat);
+            region[2] = region[2].max(lon);
+            region[3] = region[3].max(lat);
+            region[4] = region[4].min(h);
+            region[5] = region[5].max(h);
+            shifted = (shifted.0.min(shifted_lon), shifted.1.max(shifted_lon));
+        }
 
-            let half_height = (z_max - z_min) / 2.0_f64;
-
-            Self {
-                bbox: [
-                    aabb.x_center,
-                    aabb.y_center,
-                    z_min + half_height,
-                    aabb.half_width,
-                    0.0,
-                    0.0,
-                    0.0,
-                    aabb.half_length,
-                    0.0,
-                    0.0,
-                    0.0,
-                    half_height,
-                ],
-            }
-        } else {
-            Self {
-                bbox: [
-                    aabb.x_center,
-                    aabb.y_center,
-                    aabb.z_center,
-                    aabb.half_width,
-                    0.0,
-                    0.0,
-                    0.0,
-                    aabb.half_length,
-                    0.0,
-                    0.0,
-                    0.0,
-                    0.0,
-                ],
-            }
+        // 3D Tiles writes a region crossing the antimeridian with west greater than east
+        if shifted.1 - shifted.0 < region[2] - region[0] {
+            let unshift = |lon: f64| if lon > PI { lon - TAU } else { lon };
+
+            region[0] = unshift(shifted.0);
+            region[2] = unshift(shifted.1);
+        }
+
+        Self {
+            bbox: None,
+            region: Some(region),
         }
     }
 }
 
-impl TileSetRoot {
-    pub fn new(quadtree: &QuadTree) -> Self {
-        let content = TileSetRootContent {
-            uri: "root.pnts".to_string(),
-        };
+impl TileExtras {
+    pub fn new<T: Tree>(tree: &T, context: &TileContext) -> Option<Self> {
+        if !context
+            .options
+            .attributes
+            .contains(&OptionalAttribute::GpsTime)
+        {
+            return None;
+        }
 
-        let children = match &quadtree.children {
-            Some(children) => {
-                let child_0 = &children.get(0).unwrap();
-                let child_1 = &children.get(1).unwrap();
-                let child_2 = &children.get(2).unwrap();
-                let child_3 = &children.get(3).unwrap();
-
-                let geometric_error_0 = 0.05
-                    * (child_0.bounds.half_width.powf(2.0_f64)
-                        + child_0.bounds.half_length.powf(2.0_f64))
-                    .sqrt();
-                let geometric_error_1 = 0.05
-                    * (child_1.bounds.half_width.powf(2.0_f64)
-                        + child_1.bounds.half_length.powf(2.0_f64))
-                    .sqrt();
-                let geometric_error_2 = 0.05
-                    * (child_2.bounds.half_width.powf(2.0_f64)
-                        + child_2.bounds.half_length.powf(2.0_f64))
-                    .sqrt();
-                let geometric_error_3 = 0.05
-                    * (child_3.bounds.half_width.powf(2.0_f64)
-                        + child_3.bounds.half_length.powf(2.0_f64))
-                    .sqrt();
-
-                Some(vec![
-                    TileSetRootChild {
-                        content: TileSetRootContent {
-                            uri: "0/tileset.json".to_string(),
-                        },
-                        bounding_volume: TileSetRootBoundingVolume::new(&child_0),
-                        geometric_error: geometric_error_0,
-                        refine: "ADD".to_string(),
-                    },
-                    TileSetRootChild {
-                        content: TileSetRootContent {
-                            uri: "1/tileset.json".to_string(),
-                        },
-                        bounding_volume: TileSetRootBoundingVolume::new(&child_1),
-                        geometric_error: geometric_error_1,
-                        refine: "ADD".to_string(),
-                    },
-                    TileSetRootChild {
-                        content: TileSetRootContent {
-                            uri: "2/tileset.json".to_string(),
-                        },
-                        bounding_volume: TileSetRootBoundingVolume::new(&child_2),
-                        geometric_error: geometric_error_2,
-                        refine: "ADD".to_string(),
-                    },
-                    TileSetRootChild {
-                        content: TileSetRootContent {
-                            uri: "3/tileset.json".to_string(),
-                        },
-                        bounding_volume: TileSetRootBoundingVolume::new(&child_3),
-                        geometric_error: geometric_error_3,
-                        refine: "ADD".to_string(),
-                    },
-                ])
-            }
-            None => None,
-        };
+        gps_time_range(tree.points()).map(|(minimum, maximum)| Self {
+            gps_time: TimeRange { minimum, maximum },
+        })
+    }
+}
 
-        let geometric_error = 0.05
-            * (quadtree.bounds.half_width.powf(2.0_f64)
-                + quadtree.bounds.half_length.powf(2.0_f64))
-            .sqrt();
+// a grid on the bottom and top of the bounds, the ellipsoid curves away below the local frame so
+// the lowest height and the extreme longitudes and latitudes can lie between the corners; the grid
+// also runs through the face point closest to the frame origin, where the height is lowest and the
+// latitudes along the north and south edges are extreme
+fn surface_samples(aabb: &Aabb) -> Vec<(f64, f64, f64)> {
+    const STEPS: usize = 8;
+
+    let axis = |center: f64, half: f64| {
+        let mut steps = (0..=STEPS)
+            .map(|i| center + half * (2.0 * i as f64 / STEPS as f64 - 1.0))
+            .collect::<Vec<_>>();
+
+        steps.push(0.0_f64.clamp(center - half, center + half));
+        steps
+    };
+
+    let mut samples = vec![];
+
+    for &x in &axis(aabb.x_center, aabb.half_width) {
+        for &y in &axis(aabb.y_center, aabb.half_length) {
+            samples.push((x, y, aabb.z_center - aabb.half_height));
+            samples.push((x, y, aabb.z_center + aabb.half_height));
+        }
+    }
+
+    samples
+}
+
+impl TileSetRootChild {
+    pub fn new<T: Tree>(index: usize, child: &T, context: &TileContext) -> Self {
+        TileSetRootChild {
+            content: TileSetRootContent {
+                uri: format!("{}/tileset.json", index),
+            },
+            bounding_volume: TileSetRootBoundingVolume::new(child, context),
+            geometric_error: child.geometric_error(),
+            refine: "ADD".to_string(),
+            extras: TileExtras::new(child, context),
+        }
+    }
+}
+
+impl TileSetRoot {
+    pub fn new<T: Tree>(tree: &T, context: &TileContext) -> Self {
+        let children = tree.children().map(|children| {
+            children
+                .iter()
+                .enumerate()
+                .filter(|(_, child)| !child.is_empty())
+                .map(|(index, child)| TileSetRootChild::new(index, child.as_ref(), context))
+                .collect()
+        });
+
+        Self::with_children(tree, children, context)
+    }
+
+    // the children are passed in when they were written and dropped before their parent
+    pub fn with_children<T: Tree>(
+        tree: &T,
+        children: Option<Vec<TileSetRootChild>>,
+        context: &TileContext,
+    ) -> Self {
+        let content = Some(TileSetRootContent {
+            uri: context.options.content_format.content_uri().to_string(),
+        })
+        .filter(|_| !tree.points().is_empty());
 
         TileSetRoot {
             content,
-            bounding_volume: TileSetRootBoundingVolume::new(&quadtree),
-            geometric_error,
+            bounding_volume: TileSetRootBoundingVolume::new(tree, context),
+            geometric_error: tree.geometric_error(),
             refine: "ADD".to_string(),
             children,
+            transform: None,
+            implicit_tiling: None,
+            extras: TileExtras::new(tree, context),
         }
     }
 }
 
-pub fn create_tile(base_dir: &Path, quadtree: &QuadTree) -> TileSet {
-    let geometric_error = match quadtree.children {
-        Some(_) => {
-            0.05 * (quadtree.bounds.half_width.powf(2.0_f64)
-                + quadtree.bounds.half_length.powf(2.0_f64))
-            .sqrt()
+pub fn create_tile<T: Tree>(context: &TileContext, base_dir: &Path, tree: &T) -> Result<TileSet> {
+    let mut root = TileSetRoot::new(tree, context);
+    root.transform = context.transform;
+
+    create_tiles(context, base_dir, tree, root)
+}
+
+fn create_tiles<T: Tree>(
+    context: &TileContext,
+    base_dir: &Path,
+    tree: &T,
+    root: TileSetRoot,
+) -> Result<TileSet> {
+    let tile_set = write_tile(context, base_dir, tree, root)?;
+
+    if let Some(children) = tree.children() {
+        for (index, child) in children.iter().enumerate() {
+            if child.is_empty() {
+                continue;
+            }
+
+            create_tiles(
+                context,
+                &base_dir.join(index.to_string()),
+                child.as_ref(),
+                TileSetRoot::new(child.as_ref(), context),
+            )?;
         }
+    }
+
+    Ok(tile_set)
+}
+
+// the tileset.json and content of a single node, only the tileset.json without points
+pub fn write_tile<T: Tree>(
+    context: &TileContext,
+    base_dir: &Path,
+    tree: &T,
+    root: TileSetRoot,
+) -> Result<TileSet> {
+    let geometric_error = match root.children {
+        Some(_) => tree.geometric_error(),
         None => 0.0,
     };
 
     let tile_set = TileSet {
         asset: TileSetAsset {
-            version: "1.0".to_string(),
+            version: context.options.tileset_version().to_string(),
         },
+        extensions_used: context.options.tileset_extensions(),
+        extensions_required: context.options.tileset_extensions(),
         geometric_error,
-        root: TileSetRoot::new(quadtree),
+        root,
     };
 
-    if quadtree.points.len() == 0 {
-        return tile_set;
+    context.sink.write(
+        &base_dir.join("tileset.json"),
+        serde_json::to_string(&tile_set)?.into_bytes().as_slice(),
+    )?;
+
+    if tree.points().is_empty() {
+        return Ok(tile_set);
     }
 
-    let tile_content_binary_inner = package_points(&quadtree);
+    let tile_content_binary_inner = package_content(tree, context)?;
 
-    std::fs::create_dir_all(base_dir);
+    context.sink.write(
+        &base_dir.join(context.options.content_format.content_uri()),
+        tile_content_binary_inner.as_slice(),
+    )?;
 
-    let mut tileset_json_file = std::fs::File::create(base_dir.join("tileset.json")).unwrap();
-    tileset_json_file.write_all(
-        serde_json::to_string(&tile_set)
-            .unwrap()
-            .into_bytes()
-            .as_slice(),
-    );
+    Ok(tile_set)
+}
+
+pub fn package_content<T: Tree>(tree: &T, context: &TileContext) -> Result<Vec<u8>> {
+    match context.options.content_format {
+        ContentFormat::Pnts => package_points(tree, context),
+        ContentFormat::Glb => package_glb(tree, context),
+    }
+}
+
+pub fn package_points<T: Tree>(tree: &T, context: &TileContext) -> Result<Vec<u8>> {
+    let points_length = tree.points().len();
+    let center = rtc_center(tree.bounds());
 
-    let mut pnts_file = std::fs::File::create(base_dir.join("root.pnts")).unwrap();
-    pnts_file.write_all(tile_content_binary_inner.as_slice());
+    // set up feature table
+
+    let mut feature_table_header = FeatureTableHeader {
+        points_length: points_length as u32,
+        rtc_center: center.to_vec(),
+        position: None,
+        position_quantized: None,
+        quantized_volume_offset: None,
+        quantized_volume_scale: None,
+        rgb: None,
+        rgba: None,
+        rgb565: None,
+        constant_rgba: None,
+        extensions: None,
+    };
 
-    match &quadtree.children {
-        Some(children) => {
-            create_tile(&*base_dir.join("0"), &children.get(0).unwrap());
-            create_tile(&*base_dir.join("1"), &children.get(1).unwrap());
-            create_tile(&*base_dir.join("2"), &children.get(2).unwrap());
-            create_tile(&*base_dir.join("3"), &children.get(3).unwrap());
+    // empty tiles stay uncompressed, Draco has nothing to encode for them
+    let mut draco = context
+        .options
+        .draco
+        .filter(|_| points_length > 0)
+        .map(|options| DracoPointCloud::new(points_length, options));
+
+    let mut draco_properties = BTreeMap::new();
+
+    let quantized = context
+        .options
+        .quantization_tolerance
+        .filter(|_| draco.is_none())
+        .and_then(|tolerance| quantize_positions(tree, tolerance));
+
+    let mut coordinates_serialized = match (&mut draco, quantized) {
+        (Some(draco), _) => {
+            let mut coordinates = Vec::with_capacity(points_length * 3);
+
+            for point in tree.points() {
+                coordinates.push((point.x - center[0]) as f32);
+                coordinates.push((point.y - center[1]) as f32);
+                coordinates.push((point.z - center[2]) as f32);
+            }
+
+            draco_properties.insert("POSITION", draco.add_positions(coordinates));
+            feature_table_header.position = Some(AttributePosition { byte_offset: 0 });
+
+            vec![]
         }
-        None => {}
-    }
+        (None, Some((coordinates, offset, scale))) => {
+            feature_table_header.position_quantized = Some(AttributePosition { byte_offset: 0 });
+            feature_table_header.quantized_volume_offset = Some(offset.map(f64::from));
+            feature_table_header.quantized_volume_scale = Some(scale.map(f64::from));
 
-    tile_set
-}
+            coordinates
+        }
+        (None, None) => {
+            let mut coordinates = Vec::with_capacity(points_length * 12);
 
-pub fn package_points(quadtree: &&QuadTree) -> Vec<u8> {
-    let points_length = quadtree.points.len();
+            for point in tree.points() {
+                let x_relative = (point.x - center[0]) as f32;
+                let y_relative = (point.y - center[1]) as f32;
+                let z_relative = (point.z - center[2]) as f32;
 
-    let mut coordinates_serialized: Vec<u8> = Vec::with_capacity(points_length * 12);
+                coordinates.extend_from_slice(&x_relative.to_le_bytes());
+                coordinates.extend_from_slice(&y_relative.to_le_bytes());
+                coordinates.extend_from_slice(&z_relative.to_le_bytes());
+            }
 
-    let mut colors_serialized: Vec<u8> = Vec::with_capacity(points_length);
+            feature_table_header.position = Some(AttributePosition { byte_offset: 0 });
 
-    let mut classification_serialized = Vec::with_capacity(points_length);
+            coordinates
+        }
+    };
 
-    let mut is_edge_of_flight_line_serialized = Vec::with_capacity(points_length);
+    let colors_position = Some(AttributePosition {
+        byte_offset: coordinates_serialized.len() as u32,
+    });
+
+    let mut colors_serialized = vec![];
+
+    // points of inputs without colors all have the same fallback color, written once for the tile
+    match tree.points().first() {
+        Some(point) if tree.points().iter().all(|point| !point.has_color) => {
+            feature_table_header.constant_rgba = Some([
+                (point.r >> 8) as u8,
+                (point.g >> 8) as u8,
+                (point.b >> 8) as u8,
+                255,
+            ]);
+        }
+        _ => {
+            let color_encoding = match (&draco, context.options.color_encoding) {
+                (Some(_), ColorEncoding::Rgb565) => ColorEncoding::Rgb,
+                (_, color_encoding) => color_encoding,
+            };
+
+            let (semantic, components) = match color_encoding {
+                ColorEncoding::Rgb => {
+                    feature_table_header.rgb = colors_position;
+                    ("RGB", 3)
+                }
+                ColorEncoding::Rgba(_) => {
+                    feature_table_header.rgba = colors_position;
+                    ("RGBA", 4)
+                }
+                ColorEncoding::Rgb565 => {
+                    feature_table_header.rgb565 = colors_position;
+                    ("RGB565", 1)
+                }
+            };
 
-    let mut is_synthetic_serialized = Vec::with_capacity(points_length);
+            colors_serialized = encode_colors(tree.points(), color_encoding);
 
-    let mut is_key_point_serialized = Vec::with_capacity(points_length);
+            if let Some(draco) = &mut draco {
+                draco_properties.insert(semantic, draco.add_colors(&colors_serialized, components));
+                colors_serialized.clear();
+            }
+        }
+    }
 
-    let mut is_withheld_serialized = Vec::with_capacity(points_length);
+    // set up batch table, compressed properties keep their definition without values in the binary
 
-    let mut is_overlap_serialized = Vec::with_capacity(points_length);
+    let mut batch_table_header = BatchTableHeader {
+        properties: BTreeMap::new(),
+        extensions: None,
+    };
 
-    for point in &quadtree.points {
-        let x_relative = (point.x - quadtree.bounds.x_center) as f32;
-        let y_relative = (point.y - quadtree.bounds.y_center) as f32;
-        let z_relative = (point.z - quadtree.bounds.z_center) as f32;
+    let mut batch_table_binary = vec![];
 
-        coordinates_serialized.append(x_relative.to_le_bytes().to_vec().as_mut());
-        coordinates_serialized.append(y_relative.to_le_bytes().to_vec().as_mut());
-        coordinates_serialized.append(z_relative.to_le_bytes().to_vec().as_mut());
+    let mut draco_batch_properties = BTreeMap::new();
 
-        colors_serialized.push((point.r >> 8) as u8);
-        colors_serialized.push((point.g >> 8) as u8);
-        colors_serialized.push((point.b >> 8) as u8);
+    for attribute in point_attributes(tree.points(), context) {
+        let size = attribute.component_type.size();
 
-        classification_serialized.push(point.classification);
+        let compressed = draco
+            .as_mut()
+            .and_then(|draco| draco.add_point_attribute(&attribute));
 
-        is_edge_of_flight_line_serialized.push(point.is_edge_of_flight_line as u8);
+        let byte_offset = match compressed {
+            Some(id) => {
+                draco_batch_properties.insert(attribute.name.clone(), id);
 
-        is_key_point_serialized.push(point.is_key_point as u8);
+                0
+            }
+            None => {
+                batch_table_binary.resize(
+                    batch_table_binary.len() + (size - batch_table_binary.len() % size) % size,
+                    0,
+                );
 
-        is_overlap_serialized.push(point.is_overlap as u8);
+                let byte_offset = batch_table_binary.len() as u32;
+                batch_table_binary.extend_from_slice(&attribute.values);
 
-        is_synthetic_serialized.push(point.is_synthetic as u8);
+                byte_offset
+            }
+        };
 
-        is_withheld_serialized.push(point.is_withheld as u8);
+        batch_table_header.properties.insert(
+            attribute.name,
+            BatchTableAttribute {
+                component_type: attribute.component_type.batch_table_name().to_string(),
+                ty: "SCALAR".to_string(),
+                byte_offset,
+            },
+        );
     }
 
-    // set up feature table
+    if let Some(draco) = draco {
+        coordinates_serialized = draco.encode();
 
-    let feature_table_header = FeatureTableHeader {
-        points_length: points_length as u32,
-        rtc_center: vec![
-            quadtree.bounds.x_center as f32,
-            quadtree.bounds.y_center as f32,
-            quadtree.bounds.z_center as f32,
-        ],
-        position: AttributePosition { byte_offset: 0 },
-        rgb: AttributePosition {
-            byte_offset: coordinates_serialized.len() as u32,
-        },
-    };
+        feature_table_header.extensions = Some(json!({
+            DRACO_EXTENSION: {
+                "properties": draco_properties,
+                "byteOffset": 0,
+                "byteLength": coordinates_serialized.len(),
+            }
+        }));
 
-    let feature_table_header_json = serde_json::to_string(&feature_table_header).unwrap();
+        if !draco_batch_properties.is_empty() {
+            batch_table_header.extensions =
+                Some(json!({ DRACO_EXTENSION: { "properties": draco_batch_properties } }));
+        }
+    }
+
+    let feature_table_header_json = serde_json::to_string(&feature_table_header)?;
 
     let mut feature_table_header_json_bytes = feature_table_header_json.into_bytes();
     feature_table_header_json_bytes.resize(
@@ -359,82 +679,12 @@ pub fn package_points(quadtree: &&QuadTree) -> Vec<u8> {
         0,
     );
 
-    // set up batch table
-
-    let mut batch_table_header = BTreeMap::new();
-
-    let mut byte_offset = 0_u32;
-
-    batch_table_header.insert(
-        "Classification",
-        BatchTableAttribute {
-            component_type: "UNSIGNED_BYTE".to_string(),
-            ty: "SCALAR".to_string(),
-            byte_offset,
-        },
-    );
-
-    byte_offset += classification_serialized.len() as u32;
-
-    batch_table_header.insert(
-        "KeyPoint",
-        BatchTableAttribute {
-            component_type: "UNSIGNED_BYTE".to_string(),
-            ty: "SCALAR".to_string(),
-            byte_offset,
-        },
-    );
-
-    byte_offset += is_key_point_serialized.len() as u32;
-
-    batch_table_header.insert(
-        "EdgeOfFlightLine",
-        BatchTableAttribute {
-            component_type: "UNSIGNED_BYTE".to_string(),
-            ty: "SCALAR".to_string(),
-            byte_offset,
-        },
-    );
-
-    byte_offset += is_edge_of_flight_line_serialized.len() as u32;
-
-    batch_table_header.insert(
-        "Overlap",
-        BatchTableAttribute {
-            component_type: "UNSIGNED_BYTE".to_string(),
-            ty: "SCALAR".to_string(),
-            byte_offset,
-        },
-    );
-
-    byte_offset += is_overlap_serialized.len() as u32;
-
-    batch_table_header.insert(
-        "Withheld",
-        BatchTableAttribute {
-            component_type: "UNSIGNED_BYTE".to_string(),
-            ty: "SCALAR".to_string(),
-            byte_offset,
-        },
-    );
-
-    byte_offset += is_withheld_serialized.len() as u32;
-
-    batch_table_header.insert(
-        "Synthetic",
-        BatchTableAttribute {
-            component_type: "UNSIGNED_BYTE".to_string(),
-            ty: "SCALAR".to_string(),
-            byte_offset,
-        },
-    );
-
-    let batch_table_header_json = serde_json::to_string(&batch_table_header).unwrap();
+    let batch_table_header_json = serde_json::to_string(&batch_table_header)?;
 
     let mut batch_table_header_json_bytes = batch_table_header_json.into_bytes();
+    // the batch table starts 8 byte aligned after the feature table, so its binary body does too
     batch_table_header_json_bytes.resize(
-        batch_table_header_json_bytes.len()
-            + (8 - (28 + batch_table_header_json_bytes.len()) % 8) % 8,
+        batch_table_header_json_bytes.len() + (8 - batch_table_header_json_bytes.len() % 8) % 8,
         0x20,
     );
 
@@ -443,24 +693,19 @@ pub fn package_points(quadtree: &&QuadTree) -> Vec<u8> {
     let mut batch_table_bytes = vec![];
 
     batch_table_bytes.append(&mut batch_table_header_json_bytes);
-    batch_table_bytes.append(&mut classification_serialized);
-    batch_table_bytes.append(&mut is_key_point_serialized);
-    batch_table_bytes.append(&mut is_edge_of_flight_line_serialized);
-    batch_table_bytes.append(&mut is_overlap_serialized);
-    batch_table_bytes.append(&mut is_withheld_serialized);
-    batch_table_bytes.append(&mut is_synthetic_serialized);
+    batch_table_bytes.append(&mut batch_table_binary);
 
     batch_table_bytes.resize(
-        batch_table_bytes.len() + (8 - (28 + batch_table_bytes.len()) % 8) % 8,
+        batch_table_bytes.len() + (8 - batch_table_bytes.len() % 8) % 8,
         0,
     );
 
     // set up tile points
 
-    let mut header = Header {
-        magic: "pnts",
-        version: 1,
-        byte_length: 28 as u32 + feature_table_bytes.len() as u32 + batch_table_bytes.len() as u32,
+    let header = Header {
+        magic: MAGIC,
+        version: VERSION,
+        byte_length: 28_u32 + feature_table_bytes.len() as u32 + batch_table_bytes.len() as u32,
         feature_table_json_byte_length: feature_table_json_byte_length as u32,
         feature_table_binary_byte_length: (feature_table_bytes.len()
             - feature_table_json_byte_length) as u32,
@@ -487,5 +732,528 @@ pub fn package_points(quadtree: &&QuadTree) -> Vec<u8> {
         .append(&mut header.batch_table_binary_byte_length.to_le_bytes().to_vec());
     tile_content_binary_inner.append(&mut feature_table_bytes);
     tile_content_binary_inner.append(&mut batch_table_bytes);
-    tile_content_binary_inner
+    Ok(tile_content_binary_inner)
+}
+
+// the RTC center as written to the feature table, positions are relative to its f32 value so they
+// do not carry its rounding, which reaches millimeters some kilometers from the frame origin
+fn rtc_center(bounds: &Aabb) -> [f64; 3] {
+    [
+        bounds.x_center as f32 as f64,
+        bounds.y_center as f32 as f64,
+        bounds.z_center as f32 as f64,
+    ]
+}
+
+// positions as 16 bit steps across the node bounds relative to the RTC center, none when half a step
+// exceeds the tolerance or a point lies outside the bounds; the offset is rounded down and the scale
+// up to f32, so the written volume still encloses the bounds and the steps are taken across it
+fn quantize_positions<T: Tree>(tree: &T, tolerance: f64) -> Option<(Vec<u8>, [f32; 3], [f32; 3])> {
+    let bounds = tree.bounds();
+    let center = rtc_center(bounds);
+
+    let lower = [
+        bounds.x_center - center[0] - bounds.half_width,
+        bounds.y_center - center[1] - bounds.half_length,
+        bounds.z_center - center[2] - bounds.half_height,
+    ];
+    let upper = [
+        bounds.x_center - center[0] + bounds.half_width,
+        bounds.y_center - center[1] + bounds.half_length,
+        bounds.z_center - center[2] + bounds.half_height,
+    ];
+
+    let offset = lower.map(|lower| {
+        let offset = lower as f32;
+
+        if offset as f64 > lower {
+            offset.next_down()
+        } else {
+            offset
+        }
+    });
+
+    let scale = [0, 1, 2].map(|axis| {
+        let size = upper[axis] - offset[axis] as f64;
+        let scale = size as f32;
+
+        if (scale as f64) < size {
+            scale.next_up()
+        } else {
+            scale
+        }
+    });
+
+    let error = 0.5
+        * scale
+            .iter()
+            .map(|&s| s as f64 * s as f64)
+            .sum::<f64>()
+            .sqrt()
+        / QUANTIZED_STEPS;
+
+    if error > tolerance {
+        debug!(
+            "Quantization error {:.4} m exceeds the tolerance, writing float positions",
+            error
+        );
+
+        return None;
+    }
+
+    let mut coordinates = Vec::with_capacity(tree.points().len() * 6);
+
+    for point in tree.points() {
+        let relative = [
+            point.x - center[0] - offset[0] as f64,
+            point.y - center[1] - offset[1] as f64,
+            point.z - center[2] - offset[2] as f64,
+        ];
+
+        for (value, &size) in relative.iter().zip(&scale) {
+            let step = if size > 0.0 {
+                (value / size as f64 * QUANTIZED_STEPS).round()
+            } else {
+                0.0
+            };
+
+            if !(0.0..=QUANTIZED_STEPS).contains(&step) {
+                debug!("A point lies outside of its tile bounds, writing float positions");
+
+                return None;
+            }
+
+            coordinates.extend_from_slice(&(step as u16).to_le_bytes());
+        }
+    }
+
+    Some((coordinates, offset, scale))
+}
+
+fn encode_colors(points: &[Point], color_encoding: ColorEncoding) -> Vec<u8> {
+    let mut colors = Vec::with_capacity(points.len() * 4);
+
+    for point in points {
+        match color_encoding {
+            ColorEncoding::Rgb => {
+                colors.extend_from_slice(&[
+                    (point.r >> 8) as u8,
+                    (point.g >> 8) as u8,
+                    (point.b >> 8) as u8,
+                ]);
+            }
+            ColorEncoding::Rgba(alpha_source) => {
+                let alpha = match alpha_source {
+                    // 257 maps the 16 bit LAS range to 8 bits, as for normalized intensities
+                    AlphaSource::Intensity => ((point.intensity as u32 + 128) / 257) as u8,
+                    AlphaSource::UserData => point.user_data,
+                };
+
+                colors.extend_from_slice(&[
+                    (point.r >> 8) as u8,
+                    (point.g >> 8) as u8,
+                    (point.b >> 8) as u8,
+                    alpha,
+                ]);
+            }
+            // red and blue in 5 bits, green in 6
+            ColorEncoding::Rgb565 => {
+                let color = (point.r >> 11) << 11 | (point.g >> 10) << 5 | point.b >> 11;
+
+                colors.extend_from_slice(&color.to_le_bytes());
+            }
+        }
+    }
+
+    colors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quadtree::QuadTree;
+    use crate::sink::DirectorySink;
+
+    fn region_around(lat: f64, lon: f64, half_size: f64) -> [f64; 6] {
+        let (x, y, z) = WGS84.geodetic_to_geocentric(lat, lon, 0.0);
+        let frame = LocalFrame::new(x, y, z);
+
+        let bounds = Aabb {
+            x_center: 0.0,
+            y_center: 0.0,
+            z_center: 0.0,
+            half_width: half_size,
+            half_length: half_size,
+            half_height: 10.0,
+        };
+
+        TileSetRootBoundingVolume::enclosing_region(surface_samples(&bounds), &frame)
+            .region
+            .unwrap()
+    }
+
+    #[test]
+    fn region_across_the_antimeridian() {
+        let [west, south, east, north, minimum_height, maximum_height] =
+            region_around(10.0, 180.0, 1000.0);
+
+        assert!(west > east);
+        assert!(west > PI - 0.001 && east < 0.001 - PI);
+        assert!(east + TAU - west < 0.001);
+        assert!(south < 10f64.to_radians() && 10f64.to_radians() < north);
+        assert!(minimum_height < -9.99 && maximum_height > 10.0);
+    }
+
+    #[test]
+    fn region_next_to_the_antimeridian() {
+        let [west, _, east, _, _, _] = region_around(10.0, 179.9, 1000.0);
+
+        assert!(west < east && east < PI);
+        assert!(west < 179.9f64.to_radians() && 179.9f64.to_radians() < east);
+    }
+
+    #[test]
+    fn region_across_the_prime_meridian() {
+        let [west, _, east, _, _, _] = region_around(-45.0, 0.0, 1000.0);
+
+        assert!(west < 0.0 && 0.0 < east);
+        assert!(east - west < 0.001);
+    }
+
+    // a tile beside the frame origin in x and past it in y, lowest at its bottom edge nearest the origin
+    #[test]
+    fn region_of_an_off_center_tile_encloses_its_points() {
+        let (x, y, z) = WGS84.geodetic_to_geocentric(46.5, 7.5, 0.0);
+        let frame = LocalFrame::new(x, y, z);
+
+        let bounds = Aabb {
+            x_center: 3_000.0,
+            y_center: 30_000.0,
+            z_center: 0.0,
+            half_width: 10_000.0,
+            half_length: 10_000.0,
+            half_height: 10.0,
+        };
+
+        let [west, south, east, north, minimum_height, maximum_height] =
+            TileSetRootBoundingVolume::enclosing_region(surface_samples(&bounds), &frame)
+                .region
+                .unwrap();
+
+        let mut points = vec![(0.0, 20_000.0, -10.0)];
+
+        for i in 0..=40 {
+            for j in 0..=40 {
+                for up in [-10.0, 10.0] {
+                    points.push((-7_000.0 + 500.0 * i as f64, 20_000.0 + 500.0 * j as f64, up));
+                }
+            }
+        }
+
+        for (x, y, z) in points {
+            let (x, y, z) = frame.to_ecef(x, y, z);
+            let (lat, lon, h) = WGS84.geocentric_to_geodetic(x, y, z);
+            let (lat, lon) = (lat.to_radians(), lon.to_radians());
+
+            assert!(west <= lon && lon <= east, "longitude {}", lon);
+            assert!(south <= lat && lat <= north, "latitude {}", lat);
+            assert!(
+                minimum_height <= h && h <= maximum_height,
+                "height {} outside {}..{}",
+                h,
+                minimum_height,
+                maximum_height
+            );
+        }
+    }
+
+    // a 100 x 80 x 10 m tile, its corners and a few points inside, the last one half a step off on every axis
+    fn quantization_tile() -> QuadTree {
+        let bounds = Aabb {
+            x_center: 1000.0,
+            y_center: -2000.0,
+            z_center: 30.0,
+            half_width: 50.0,
+            half_length: 40.0,
+            half_height: 5.0,
+        };
+
+        let points = vec![
+            Point::at(950.0, -2040.0, 25.0),
+            Point::at(1050.0, -1960.0, 35.0),
+            Point::at(1000.0, -2000.0, 30.0),
+            Point::at(1_012.345_678, -1_987.654_321, 31.234_567),
+            Point::at(950.000_7, -1_960.000_3, 34.999_9),
+        ];
+
+        QuadTree::leaf(bounds, 1, 100, points)
+    }
+
+    // half the diagonal of a quantization step
+    fn quantization_error(tree: &QuadTree) -> f64 {
+        let bounds = tree.bounds();
+
+        (bounds.half_width.powi(2) + bounds.half_length.powi(2) + bounds.half_height.powi(2)).sqrt()
+            / QUANTIZED_STEPS
+    }
+
+    #[test]
+    fn quantized_positions_stay_within_the_error() {
+        let tree = quantization_tile();
+        let error = quantization_error(&tree);
+
+        assert!(error < 0.001);
+
+        let (coordinates, offset, scale) = quantize_positions(&tree, 0.001).unwrap();
+
+        assert_eq!(coordinates.len(), tree.points().len() * 6);
+        assert_eq!(offset, [-50.0, -40.0, -5.0]);
+        assert_eq!(scale, [100.0, 80.0, 10.0]);
+
+        // the corners of the tile are the ends of the quantized range
+        assert_eq!(coordinates[0..6], [0, 0, 0, 0, 0, 0]);
+        assert_eq!(coordinates[6..12], [0xff; 6]);
+
+        let bounds = tree.bounds();
+
+        for (point, quantized) in tree.points().iter().zip(coordinates.chunks_exact(6)) {
+            let steps = quantized
+                .chunks_exact(2)
+                .map(|step| u16::from_le_bytes([step[0], step[1]]) as f64)
+                .collect::<Vec<_>>();
+
+            let decoded = [
+                bounds.x_center + steps[0] * scale[0] as f64 / QUANTIZED_STEPS + offset[0] as f64,
+                bounds.y_center + steps[1] * scale[1] as f64 / QUANTIZED_STEPS + offset[1] as f64,
+                bounds.z_center + steps[2] * scale[2] as f64 / QUANTIZED_STEPS + offset[2] as f64,
+            ];
+
+            let distance = ((decoded[0] - point.x).powi(2)
+                + (decoded[1] - point.y).powi(2)
+                + (decoded[2] - point.z).powi(2))
+            .sqrt();
+
+            assert!(distance <= error + 1e-12, "{} exceeds {}", distance, error);
+        }
+    }
+
+    #[test]
+    fn float_positions_beyond_the_tolerance() {
+        let tree = quantization_tile();
+
+        assert!(quantize_positions(&tree, quantization_error(&tree) * 0.99).is_none());
+    }
+
+    #[test]
+    fn float_positions_for_points_outside_the_tile() {
+        let mut tree = quantization_tile();
+        tree.points.push(Point::at(1000.0, -2000.0, 35.1));
+
+        assert!(quantize_positions(&tree, 0.01).is_none());
+    }
+
+    // the feature table JSON of the pnts of the tile, and the binary that follows it
+    fn pnts_feature_table(tree: &QuadTree, options: TilingOptions) -> (serde_json::Value, Vec<u8>) {
+        let sink = DirectorySink::new("unused");
+        let frame = LocalFrame::new(6378137.0, 0.0, 0.0);
+        let extra_bytes = ExtraBytesSchema::default();
+        let context = TileContext {
+            sink: &sink,
+            frame: &frame,
+            options: &options,
+            extra_bytes: &extra_bytes,
+            transform: None,
+        };
+
+        let pnts = package_points(tree, &context).unwrap();
+
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes([
+                pnts[offset],
+                pnts[offset + 1],
+                pnts[offset + 2],
+                pnts[offset + 3],
+            ]) as usize
+        };
+
+        let json_length = u32_at(12);
+        let feature_table = serde_json::from_slice(&pnts[28..28 + json_length]).unwrap();
+
+        (feature_table, pnts[28 + json_length..].to_vec())
+    }
+
+    // the feature table of the quantized pnts of the tile and its positions as a client decodes them
+    fn quantized_pnts(tree: &QuadTree, tolerance: f64) -> (serde_json::Value, Vec<[f64; 3]>) {
+        let (feature_table, binary) = pnts_feature_table(
+            tree,
+            TilingOptions {
+                quantization_tolerance: Some(tolerance),
+                ..TilingOptions::default()
+            },
+        );
+
+        let vector = |name: &str| [0, 1, 2].map(|axis| feature_table[name][axis].as_f64().unwrap());
+
+        let (rtc_center, offset, scale) = (
+            vector("RTC_CENTER"),
+            vector("QUANTIZED_VOLUME_OFFSET"),
+            vector("QUANTIZED_VOLUME_SCALE"),
+        );
+        let start = feature_table["POSITION_QUANTIZED"]["byteOffset"]
+            .as_u64()
+            .unwrap() as usize;
+
+        let positions = (0..tree.points().len())
+            .map(|index| {
+                [0, 1, 2].map(|axis| {
+                    let at = start + index * 6 + axis * 2;
+                    let step = u16::from_le_bytes([binary[at], binary[at + 1]]) as f64;
+
+                    rtc_center[axis] + step * scale[axis] / QUANTIZED_STEPS + offset[axis]
+                })
+            })
+            .collect();
+
+        (feature_table, positions)
+    }
+
+    fn distance(decoded: &[f64; 3], point: &Point) -> f64 {
+        ((decoded[0] - point.x).powi(2)
+            + (decoded[1] - point.y).powi(2)
+            + (decoded[2] - point.z).powi(2))
+        .sqrt()
+    }
+
+    #[test]
+    fn quantized_pnts_feature_table() {
+        let tree = quantization_tile();
+        let (feature_table, positions) = quantized_pnts(&tree, 0.001);
+
+        let vector = |name: &str| [0, 1, 2].map(|axis| feature_table[name][axis].as_f64().unwrap());
+
+        assert!(feature_table.get("POSITION").is_none());
+        assert_eq!(vector("RTC_CENTER"), [1000.0, -2000.0, 30.0]);
+        assert_eq!(vector("QUANTIZED_VOLUME_OFFSET"), [-50.0, -40.0, -5.0]);
+        assert_eq!(vector("QUANTIZED_VOLUME_SCALE"), [100.0, 80.0, 10.0]);
+
+        let error = quantization_error(&tree);
+
+        for (decoded, point) in positions.iter().zip(tree.points()) {
+            let distance = distance(decoded, point);
+
+            assert!(distance <= error + 1e-12, "{} exceeds {}", distance, error);
+        }
+    }
+
+    // the f32 RTC center is some millimeters off 50 km from the origin, the volume makes up for it
+    #[test]
+    fn quantized_pnts_far_from_the_origin() {
+        let bounds = Aabb {
+            x_center: 50_123.456_7,
+            y_center: -12_345.678_9,
+            z_center: 812.345_6,
+            half_width: 20.0,
+            half_length: 30.0,
+            half_height: 4.0,
+        };
+
+        assert_ne!(bounds.x_center as f32 as f64, bounds.x_center);
+
+        let points = (0..=64)
+            .map(|index| {
+                let fraction = |period: usize| (index % period) as f64 / (period - 1) as f64;
+
+                Point::at(
+                    bounds.x_center - bounds.half_width + 2.0 * bounds.half_width * fraction(5),
+                    bounds.y_center - bounds.half_length + 2.0 * bounds.half_length * fraction(7),
+                    bounds.z_center - bounds.half_height + 2.0 * bounds.half_height * fraction(3),
+                )
+            })
+            .collect();
+
+        let tree = QuadTree::leaf(bounds, 1, 100, points);
+        let tolerance = 0.000_6;
+
+        let (feature_table, positions) = quantized_pnts(&tree, tolerance);
+
+        assert!(feature_table.get("POSITION_QUANTIZED").is_some());
+
+        for (decoded, point) in positions.iter().zip(tree.points()) {
+            let distance = distance(decoded, point);
+
+            assert!(distance <= tolerance, "{} exceeds {}", distance, tolerance);
+        }
+    }
+
+    fn colored(r: u16, g: u16, b: u16) -> Point {
+        Point {
+            r,
+            g,
+            b,
+            has_color: true,
+            ..Point::at(0.0, 0.0, 0.0)
+        }
+    }
+
+    #[test]
+    fn rgb565_bits() {
+        let points = [
+            colored(u16::MAX, 0, 0),
+            colored(0, u16::MAX, 0),
+            colored(0, 0, u16::MAX),
+            // 16 of 31, 16 of 63 and 1 of 31, the low bits are dropped
+            colored(0x87ff, 0x43ff, 0x0fff),
+        ];
+
+        let colors = encode_colors(&points, ColorEncoding::Rgb565)
+            .chunks_exact(2)
+            .map(|color| u16::from_le_bytes([color[0], color[1]]))
+            .collect::<Vec<_>>();
+
+        assert_eq!(colors, [0xf800, 0x07e0, 0x001f, 0x8201]);
+    }
+
+    #[test]
+    fn constant_color_without_input_colors() {
+        let bounds = quantization_tile().bounds().clone();
+        let uncolored = |x| Point {
+            r: 0x8000,
+            g: 0x4000,
+            b: 0xffff,
+            ..Point::at(x, -2000.0, 30.0)
+        };
+
+        let tile = QuadTree::leaf(
+            bounds.clone(),
+            1,
+            100,
+            vec![uncolored(990.0), uncolored(1010.0)],
+        );
+        let (feature_table, _) = pnts_feature_table(&tile, TilingOptions::default());
+
+        assert_eq!(feature_table["CONSTANT_RGBA"], json!([128, 64, 255, 255]));
+        assert!(feature_table.get("RGB").is_none());
+
+        // a single colored point makes every point carry its color
+        let tile = QuadTree::leaf(
+            bounds,
+            1,
+            100,
+            vec![
+                uncolored(990.0),
+                Point {
+                    x: 1010.0,
+                    y: -2000.0,
+                    z: 30.0,
+                    ..colored(0xff00, 0, 0x0100)
+                },
+            ],
+        );
+        let (feature_table, binary) = pnts_feature_table(&tile, TilingOptions::default());
+
+        assert!(feature_table.get("CONSTANT_RGBA").is_none());
+
+        let start = feature_table["RGB"]["byteOffset"].as_u64().unwrap() as usize;
+        assert_eq!(&binary[start..start + 6], [128, 64, 255, 255, 0, 1]);
+    }
 }
@@ -0,0 +1,215 @@
+use crate::quadtree::{Aabb, Point};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TreeType {
+    QuadTree,
+    OcTree,
+}
+
+pub trait Tree: Sized + Sync {
+    // 2 for quadtrees, 3 for octrees
+    const DIMENSIONS: u32;
+
+    // nodes at the resolution of the path key are not split, below it points could not be ordered
+    const MAX_DEPTH: u8 = (64 / Self::DIMENSIONS) as u8 + 1;
+
+    fn new(bounds: Aabb, depth: u8, capacity: usize) -> Self;
+
+    fn bounds(&self) -> &Aabb;
+
+    fn points(&self) -> &[Point];
+
+    fn children(&self) -> Option<&[Box<Self>]>;
+
+    // whether neither the node nor any node below it holds points
+    fn is_empty(&self) -> bool {
+        self.points().is_empty()
+            && self
+                .children()
+                .into_iter()
+                .flatten()
+                .all(|child| child.is_empty())
+    }
+
+    // a node without children holding the given points
+    fn leaf(bounds: Aabb, depth: u8, capacity: usize, points: Vec<Point>) -> Self;
+
+    fn depth(&self) -> u8;
+
+    fn capacity(&self) -> usize;
+
+    fn points_mut(&mut self) -> &mut Vec<Point>;
+
+    // the children of the node, created the first time a point is passed on to them
+    fn split(&mut self) -> &mut [Box<Self>];
+
+    // points are moved into the tree, each into the one child containing it
+    fn insert(&mut self, point: Point, index: usize, number_of_points: usize) {
+        if !Self::contains(self.bounds(), &point) {
+            return;
+        }
+
+        if Self::keeps(
+            self.depth(),
+            self.capacity(),
+            self.points().len(),
+            index,
+            number_of_points,
+        ) || !Self::splits(self.depth(), self.bounds())
+        {
+            self.points_mut().push(point);
+            return;
+        }
+
+        if let Some(child) = self
+            .split()
+            .iter_mut()
+            .find(|child| Self::contains(child.bounds(), &point))
+        {
+            child.insert(point, index, number_of_points);
+        }
+    }
+
+    // upper bounds are inclusive so the points on the top faces of the root, and all points of flat
+    // data, are kept; points on a face shared by two children go to the first of them
+    fn contains(bounds: &Aabb, point: &Point) -> bool;
+
+    // whether a node at the given depth, already holding `kept` points, keeps the point at `index`;
+    // evenly sampled while the subtree holds many points, filled up to capacity near the leaves, and
+    // never more than capacity, a subtree holding most of the points would otherwise sample a lot
+    fn keeps(
+        depth: u8,
+        capacity: usize,
+        kept: usize,
+        index: usize,
+        number_of_points: usize,
+    ) -> bool {
+        let step = 2_usize
+            .checked_pow((depth - 1) as u32)
+            .and_then(|nodes| nodes.checked_mul(capacity))
+            .map_or(0, |sampled| number_of_points / sampled);
+
+        if step > 1 << Self::DIMENSIONS {
+            kept < capacity && (index + 1 - depth as usize).is_multiple_of(step)
+        } else {
+            kept < capacity
+        }
+    }
+
+    fn child_bounds(bounds: &Aabb, index: usize) -> Aabb;
+
+    // whether a node passes points on to children, it keeps them all once it is as deep as the path
+    // key resolves or too small for its children to differ from it, as with many coincident points
+    fn splits(depth: u8, bounds: &Aabb) -> bool {
+        let child = Self::child_bounds(bounds, 0);
+
+        depth < Self::MAX_DEPTH
+            && (child.x_center != bounds.x_center
+                || child.y_center != bounds.y_center
+                || child.z_center != bounds.z_center)
+    }
+
+    fn geometric_error(&self) -> f64;
+
+    // child indices from the root down, ordering points so that every node's points are contiguous
+    fn path_key(bounds: &Aabb, point: &Point) -> u64 {
+        let mut key = 0;
+        let mut bounds = bounds.clone();
+        let mut inside = true;
+
+        for _ in 0..64 / Self::DIMENSIONS {
+            key <<= Self::DIMENSIONS;
+
+            if !inside {
+                continue;
+            }
+
+            let child = (0..1 << Self::DIMENSIONS)
+                .map(|child| (child, Self::child_bounds(&bounds, child)))
+                .find(|(_, child_bounds)| Self::contains(child_bounds, point));
+
+            match child {
+                Some((child, child_bounds)) => {
+                    key |= child as u64;
+                    bounds = child_bounds;
+                }
+                None => inside = false,
+            }
+        }
+
+        key
+    }
+
+    // implicit tiling coordinates of a child, added to twice the coordinates of its parent
+    fn child_offset(index: usize) -> [u32; 3];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job::build_tree;
+    use crate::octree::OcTree;
+    use crate::quadtree::QuadTree;
+    use crate::spatial_extent::SpatialExtent;
+
+    // the number of points held by the subtree and its deepest node
+    fn summary<T: Tree>(tree: &T) -> (usize, u8) {
+        tree.children()
+            .into_iter()
+            .flatten()
+            .map(|child| summary(child.as_ref()))
+            .fold(
+                (tree.points().len(), tree.depth()),
+                |(points, depth), child| (points + child.0, depth.max(child.1)),
+            )
+    }
+
+    fn tree_of<T: Tree>(points: Vec<Point>, capacity: usize) -> T {
+        let mut bbox = SpatialExtent::default();
+
+        for point in &points {
+            bbox.update(point);
+        }
+
+        build_tree::<T>(points, &bbox, capacity, false).0
+    }
+
+    // the children of a node around coincident points soon have the same center as the node
+    fn assert_keeps_coincident_points<T: Tree>() {
+        let points = vec![Point::at(512_000.25, 4_096_000.5, 120.75); 30_000];
+
+        let (held, depth) = summary(&tree_of::<T>(points, 20));
+
+        assert_eq!(held, 30_000);
+        assert!(depth < T::MAX_DEPTH);
+    }
+
+    // a lone point next to many at the origin, where halving the bounds never stops changing them
+    fn assert_stops_at_the_path_key_resolution<T: Tree>() {
+        let mut points = vec![Point::at(0.0, 0.0, 0.0); 30_000];
+        points.push(Point::at(1.0, 1.0, 1.0));
+
+        let (held, depth) = summary(&tree_of::<T>(points, 20));
+
+        assert_eq!(held, 30_001);
+        assert_eq!(depth, T::MAX_DEPTH);
+    }
+
+    #[test]
+    fn quadtree_keeps_coincident_points() {
+        assert_keeps_coincident_points::<QuadTree>();
+        assert_stops_at_the_path_key_resolution::<QuadTree>();
+    }
+
+    #[test]
+    fn octree_keeps_coincident_points() {
+        assert_keeps_coincident_points::<OcTree>();
+        assert_stops_at_the_path_key_resolution::<OcTree>();
+    }
+
+    #[test]
+    fn sampling_steps_do_not_overflow_deep_nodes() {
+        assert!(QuadTree::keeps(200, 2000, 0, 1 << 20, usize::MAX));
+        assert!(!OcTree::keeps(200, 2000, 2000, 1 << 20, usize::MAX));
+    }
+}